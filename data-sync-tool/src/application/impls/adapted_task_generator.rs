@@ -0,0 +1,204 @@
+//! Adapted Task Generator
+//! Builds the tasks of a run through the adapter of the plan's data source, requesting the day the run fired for
+//! unless the watermark of the dataset is already past it
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Local;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{
+    application::sync_scheduling::TaskGenerator,
+    domain::{
+        data_source::{dataset::Dataset, source_adapter::SourceAdapter},
+        synchronization::{
+            custom_errors::TaskCreationError, sync_plan::SyncPlan, sync_task::SyncStatus,
+            value_objects::execution_result::ExecutionResult,
+        },
+    },
+};
+
+pub struct AdaptedTaskGenerator {
+    date_param: String,
+    date_format: String,
+    // datasets by id, their watermarks move with the rows of every run, and adapters by data source id
+    datasets: Mutex<HashMap<Uuid, Dataset>>,
+    adapters: HashMap<Uuid, Arc<dyn SourceAdapter>>,
+}
+
+impl AdaptedTaskGenerator {
+    /// Requests the day of each run in the `date_param` argument, e.g. Tushare's `trade_date` formatted `%Y%m%d`
+    pub fn new(date_param: &str, date_format: &str) -> Self {
+        Self {
+            date_param: date_param.to_string(),
+            date_format: date_format.to_string(),
+            datasets: Mutex::new(HashMap::new()),
+            adapters: HashMap::new(),
+        }
+    }
+
+    pub fn with_datasets(self, datasets: &[Dataset]) -> Self {
+        {
+            let mut known = self.datasets.lock().expect("Datasets should never be poisoned");
+            for dataset in datasets {
+                known.insert(*dataset.id(), dataset.clone());
+            }
+        }
+        return self;
+    }
+
+    /// The dataset as the runs so far left it, its watermark included
+    pub fn dataset(&self, dataset_id: &Uuid) -> Option<Dataset> {
+        return self.datasets.lock().expect("Datasets should never be poisoned").get(dataset_id).cloned();
+    }
+
+    pub fn with_adapter(mut self, datasource_id: Uuid, adapter: Arc<dyn SourceAdapter>) -> Self {
+        self.adapters.insert(datasource_id, adapter);
+        return self;
+    }
+}
+
+impl TaskGenerator for AdaptedTaskGenerator {
+    fn generate_tasks(&self, plan: &mut SyncPlan) -> Result<(), TaskCreationError> {
+        let dataset = plan
+            .dataset_id()
+            .and_then(|dataset_id| self.dataset(&dataset_id))
+            .ok_or(TaskCreationError::UnknownDataset)?;
+        let adapter = plan
+            .datasource_id()
+            .and_then(|datasource_id| self.adapters.get(&datasource_id))
+            .ok_or(TaskCreationError::UnknownDataSource)?;
+        let Some(fired_at) = plan.last_trigger_time() else {
+            return Err(TaskCreationError::MissingParam(self.date_param.clone()));
+        };
+        let mut params = Map::new();
+        params.insert(self.date_param.clone(), Value::String(fired_at.format(&self.date_format).to_string()));
+        let params = Value::Object(params);
+        // the day is in storage already
+        if dataset.watermark().as_ref().is_some_and(|watermark| !watermark.is_beyond(&params)) {
+            return Ok(());
+        }
+        plan.create_adapted_tasks(adapter.as_ref(), &dataset, &[params])?;
+        return Ok(());
+    }
+
+    fn record_run(&self, plan: &SyncPlan, results: &[ExecutionResult]) {
+        let Some(dataset_id) = plan.dataset_id() else {
+            return;
+        };
+        let finished: Vec<&ExecutionResult> =
+            results.iter().filter(|result| *result.status() == SyncStatus::Finished).collect();
+        if finished.is_empty() {
+            return;
+        }
+        let rows: Vec<Value> = finished
+            .iter()
+            .filter_map(|result| result.rows().as_ref())
+            .flatten()
+            .map(|row| Value::Object(row.clone()))
+            .collect();
+        let mut datasets = self.datasets.lock().expect("Datasets should never be poisoned");
+        let Some(dataset) = datasets.get_mut(dataset_id) else {
+            return;
+        };
+        if let Err(err) = dataset.record_successful_write(&Value::Array(rows), Local::now()) {
+            log::warn!("Failed to record the rows written to dataset {}: {}", dataset_id, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::prelude::*;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        domain::{
+            data_source::value_object::{api_param::APIParam, data_schema::Column},
+            synchronization::value_objects::task_spec::AuthPlacement,
+        },
+        infrastructure::adapters::tushare::TushareAdapter,
+    };
+
+    fn trade_date() -> APIParam {
+        return APIParam::new("trade_date", "", "str", true, None).unwrap();
+    }
+
+    #[test]
+    fn it_should_request_the_day_of_the_run_through_the_adapter() {
+        let mut dataset = Dataset::default();
+        dataset.set_id(Uuid::new_v4()).set_name("daily".to_string());
+        dataset.add_api_params(&vec![trade_date()]).unwrap();
+        dataset.add_columns_to_schema(&vec![Column::new("trade_date", "str", "").unwrap()]);
+        let datasource_id = Uuid::new_v4();
+        let generator = AdaptedTaskGenerator::new("trade_date", "%Y%m%d")
+            .with_datasets(&[dataset.clone()])
+            .with_adapter(datasource_id, Arc::new(TushareAdapter::default()));
+        let mut plan = SyncPlan::default();
+        plan.set_dataset_id(Some(*dataset.id()))
+            .set_datasource_id(Some(datasource_id))
+            .set_last_trigger_time(Some(Local.with_ymd_and_hms(2023, 6, 21, 18, 0, 0).unwrap()));
+
+        generator.generate_tasks(&mut plan).unwrap();
+
+        let spec = plan.tasks()[0].spec();
+        assert_eq!(spec.payload().as_ref().unwrap()["params"], json!({"trade_date": "20230621"}));
+        assert_eq!(*spec.auth_placement(), Some(AuthPlacement::BodyField { name: "token".to_string() }));
+        assert_eq!(*plan.tasks()[0].dataset_id(), Some(*dataset.id()));
+
+        plan.set_datasource_id(Some(Uuid::new_v4()));
+        assert!(matches!(generator.generate_tasks(&mut plan), Err(TaskCreationError::UnknownDataSource)));
+    }
+
+    #[test]
+    fn it_should_create_tasks_for_a_dataset_without_declared_params() {
+        let mut dataset = Dataset::default();
+        dataset.set_id(Uuid::new_v4()).set_name("daily".to_string());
+        let datasource_id = Uuid::new_v4();
+        let generator = AdaptedTaskGenerator::new("trade_date", "%Y%m%d")
+            .with_datasets(&[dataset.clone()])
+            .with_adapter(datasource_id, Arc::new(TushareAdapter::default()));
+        let mut plan = SyncPlan::default();
+        plan.set_dataset_id(Some(*dataset.id()))
+            .set_datasource_id(Some(datasource_id))
+            .set_last_trigger_time(Some(Local.with_ymd_and_hms(2023, 6, 21, 18, 0, 0).unwrap()));
+
+        generator.generate_tasks(&mut plan).unwrap();
+
+        assert_eq!(plan.tasks().len(), 1);
+        assert_eq!(plan.tasks()[0].spec().payload().as_ref().unwrap()["params"], json!({"trade_date": "20230621"}));
+    }
+
+    #[test]
+    fn it_should_not_request_a_day_the_watermark_is_past() {
+        let mut dataset = Dataset::default();
+        dataset.set_id(Uuid::new_v4()).set_watermark_column(Some("trade_date".to_string()));
+        dataset.add_api_params(&vec![trade_date()]).unwrap();
+        let datasource_id = Uuid::new_v4();
+        let generator = AdaptedTaskGenerator::new("trade_date", "%Y%m%d")
+            .with_datasets(&[dataset.clone()])
+            .with_adapter(datasource_id, Arc::new(TushareAdapter::default()));
+        let mut plan = SyncPlan::default();
+        plan.set_dataset_id(Some(*dataset.id()))
+            .set_datasource_id(Some(datasource_id))
+            .set_last_trigger_time(Some(Local.with_ymd_and_hms(2023, 6, 21, 18, 0, 0).unwrap()));
+        generator.generate_tasks(&mut plan).unwrap();
+
+        let task = &mut plan.tasks_mut()[0];
+        task.finished();
+        let mut result = ExecutionResult::from_task(task, Value::Null);
+        result.set_rows(Some(vec![json!({"trade_date": "20230621"}).as_object().unwrap().clone()]));
+        generator.record_run(&plan, &[result]);
+        plan.set_tasks(vec![]);
+        generator.generate_tasks(&mut plan).unwrap();
+
+        let watermark = generator.dataset(dataset.id()).unwrap().watermark().clone().unwrap();
+        assert_eq!(*watermark.value(), NaiveDate::from_ymd_opt(2023, 6, 21).unwrap());
+        assert!(plan.tasks().is_empty());
+    }
+}
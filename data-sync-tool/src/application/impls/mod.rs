@@ -0,0 +1,2 @@
+pub mod adapted_task_generator;
+pub mod sync_scheduler;
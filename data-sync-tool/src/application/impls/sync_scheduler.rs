@@ -0,0 +1,685 @@
+//! Sync Scheduler
+//! Polls the plan repository for due plans and hands their tasks to the executor
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use futures::{
+    future::join_all,
+    stream::{FuturesUnordered, StreamExt},
+};
+use tokio::{sync::watch, time::MissedTickBehavior};
+use uuid::Uuid;
+
+use crate::{
+    application::sync_scheduling::{PlanRun, SyncSchedulingService, TaskGenerator},
+    domain::synchronization::{
+        custom_errors::{RepositoryError, SchedulingError},
+        plan_graph::PlanGraph,
+        repository::SyncPlanRepository,
+        sync_plan::SyncPlan,
+        sync_task::SyncStatus,
+        task_executor::{CancelScope, TaskExecutor},
+        value_objects::{dry_run_report::DryRunReport, execution_result::ExecutionResult},
+    },
+};
+
+/// Marks a plan as running until dropped
+struct RunningPlan<'s> {
+    plan_id: Uuid,
+    running_plans: &'s Mutex<HashSet<Uuid>>,
+}
+
+impl Drop for RunningPlan<'_> {
+    fn drop(&mut self) {
+        self.running_plans.lock().unwrap().remove(&self.plan_id);
+    }
+}
+
+/// Cancel requests of plans and data sources, each plan in scope honours a request once by stopping before a run
+#[derive(Default)]
+struct CancelRequests {
+    requested_at: HashMap<CancelScope, DateTime<Local>>,
+    checked_at: HashMap<Uuid, DateTime<Local>>,
+}
+
+impl CancelRequests {
+    fn record(&mut self, scope: CancelScope, now: DateTime<Local>) {
+        // task ids do not outlive the run, so there is no later run of a task to stop
+        if !matches!(scope, CancelScope::Task(_)) {
+            self.requested_at.insert(scope, now);
+        }
+    }
+
+    /// Whether a request in scope of the plan came since the plan last checked
+    fn take(&mut self, plan: &SyncPlan, now: DateTime<Local>) -> bool {
+        let checked_at = self.checked_at.insert(*plan.id(), now);
+        let scopes = [Some(CancelScope::Plan(*plan.id())), plan.datasource_id().map(CancelScope::DataSource)];
+        return scopes
+            .iter()
+            .flatten()
+            .filter_map(|scope| self.requested_at.get(scope))
+            .any(|requested_at| checked_at.is_none_or(|checked_at| *requested_at >= checked_at));
+    }
+}
+
+pub struct SyncScheduler {
+    plan_repository: Arc<dyn SyncPlanRepository>,
+    executor: Arc<dyn TaskExecutor + Send + Sync>,
+    task_generator: Arc<dyn TaskGenerator>,
+    poll_interval: Duration,
+    running_plans: Mutex<HashSet<Uuid>>,
+    cancel_requests: Mutex<CancelRequests>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl SyncScheduler {
+    pub fn new(
+        plan_repository: Arc<dyn SyncPlanRepository>,
+        executor: Arc<dyn TaskExecutor + Send + Sync>,
+        task_generator: Arc<dyn TaskGenerator>,
+    ) -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            plan_repository,
+            executor,
+            task_generator,
+            poll_interval: Duration::from_secs(10),
+            running_plans: Mutex::new(HashSet::new()),
+            cancel_requests: Mutex::new(CancelRequests::default()),
+            shutdown,
+        }
+    }
+
+    /// How often the repository is asked for due plans
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        return self;
+    }
+
+    fn claim(&self, plan_id: Uuid) -> Result<RunningPlan<'_>, SchedulingError> {
+        if !self.running_plans.lock().unwrap().insert(plan_id) {
+            return Err(SchedulingError::PlanAlreadyRunning(plan_id));
+        }
+        return Ok(RunningPlan {
+            plan_id,
+            running_plans: &self.running_plans,
+        });
+    }
+
+    /// Move the plan past the trigger time it fired at, then run its tasks and save how they ended.
+    /// Every run generates its tasks afresh, those of the previous run that did not settle are carried over.
+    /// The trigger time is saved first so a plan whose tasks cannot be built does not fire on every poll.
+    async fn run_plan(
+        &self,
+        plan: &mut SyncPlan,
+        fired_at: DateTime<Local>,
+    ) -> Result<Vec<ExecutionResult>, SchedulingError> {
+        plan.advance(fired_at);
+        self.plan_repository
+            .update_plans(&[&*plan])
+            .await
+            .map_err(SchedulingError::PlanNotSaved)?;
+
+        let previous_tasks = std::mem::take(plan.tasks_mut());
+        if let Err(err) = self.task_generator.generate_tasks(plan) {
+            plan.set_tasks(previous_tasks);
+            return Err(err.into());
+        }
+        plan.carry_over(previous_tasks);
+        let sync_config = plan.sync_config().clone();
+        let results = self.executor.execute_all(plan.tasks_mut(), &sync_config).await;
+        self.task_generator.record_run(plan, &results);
+        if results.iter().all(|result| *result.status() == SyncStatus::Finished) {
+            plan.set_last_successful_trigger_time(Some(fired_at));
+        }
+        // the final status of the tasks, cancelled ones included
+        self.plan_repository
+            .update_plans(&[&*plan])
+            .await
+            .map_err(SchedulingError::PlanNotSaved)?;
+        return Ok(results);
+    }
+
+    /// Whether the plan was asked to cancel since it last checked, the request is honoured by the caller
+    fn is_cancelled(&self, plan: &SyncPlan) -> bool {
+        return self.cancel_requests.lock().unwrap().take(plan, Local::now());
+    }
+
+    fn is_running(&self, plan_id: &Uuid) -> bool {
+        return self.running_plans.lock().unwrap().contains(plan_id);
+    }
+
+    /// First upstream plan outside the due plans whose latest run did not get all of its data
+    async fn failed_upstream(&self, plan: &SyncPlan, due_plan_ids: &HashSet<Uuid>) -> Option<Uuid> {
+        for upstream_id in plan.upstream_plan_ids().iter().filter(|upstream_id| !due_plan_ids.contains(upstream_id)) {
+            let succeeded = match self.plan_repository.get_plan_by_id(upstream_id).await {
+                Ok(upstream) => upstream.last_run_succeeded(),
+                // a deleted upstream plan has nothing left to wait for
+                Err(RepositoryError::ItemNotFound) => true,
+                Err(_) => false,
+            };
+            if !succeeded {
+                return Some(*upstream_id);
+            }
+        }
+        return None;
+    }
+
+    /// Run the plan once for each trigger time its missed run policy asks for at `now`.
+    /// Each run requests the data of its own trigger time, the generator finds it in the plan's last trigger time.
+    /// A run that cannot start stops the catch-up, the results of the runs before it are kept.
+    async fn catch_up(&self, plan: &mut SyncPlan, now: DateTime<Local>) -> PlanRun {
+        let plan_id = *plan.id();
+        let fire_times = plan.due_runs(&now);
+        if fire_times.is_empty() {
+            plan.skip_until(now);
+            return match self.plan_repository.update_plans(&[&*plan]).await {
+                Ok(_) => PlanRun::new(plan_id, vec![]),
+                Err(err) => PlanRun::failed(plan_id, vec![], SchedulingError::PlanNotSaved(err)),
+            };
+        }
+
+        let mut results = vec![];
+        for fired_at in fire_times {
+            // a cancel while a run was in flight, or since the last one, stops the runs not started yet
+            if self.is_cancelled(plan) {
+                plan.skip_until(now);
+                let error = match self.plan_repository.update_plans(&[&*plan]).await {
+                    Ok(_) => SchedulingError::Cancelled,
+                    Err(err) => SchedulingError::PlanNotSaved(err),
+                };
+                return PlanRun::failed(plan_id, results, error);
+            }
+            // nothing says whether the exchange is open that day, the run is skipped and reported
+            if let Err(err) = plan.check_trading_day(&fired_at) {
+                plan.skip_until(fired_at);
+                let error = match self.plan_repository.update_plans(&[&*plan]).await {
+                    Ok(_) => SchedulingError::OutsideCalendar(err),
+                    Err(err) => SchedulingError::PlanNotSaved(err),
+                };
+                return PlanRun::failed(plan_id, results, error);
+            }
+            match self.run_plan(plan, fired_at).await {
+                Ok(run_results) => results.extend(run_results),
+                Err(err) => return PlanRun::failed(plan_id, results, err),
+            }
+        }
+        // a cancel during the last run was honoured by the run
+        self.is_cancelled(plan);
+        return PlanRun::new(plan_id, results);
+    }
+}
+
+#[async_trait]
+impl SyncSchedulingService for SyncScheduler {
+    async fn dispatch_due_plans(&self, now: DateTime<Local>) -> Result<Vec<PlanRun>, RepositoryError> {
+        let mut plans: HashMap<Uuid, SyncPlan> = self
+            .plan_repository
+            .get_plans_pass_due()
+            .await?
+            .into_iter()
+            .filter(|plan| *plan.active() && plan.should_trigger_at(&now))
+            .map(|plan| (*plan.id(), plan))
+            .collect();
+        let due_plan_ids: HashSet<Uuid> = plans.keys().copied().collect();
+        let (graph, cyclic) = PlanGraph::partial(&plans.values().collect::<Vec<_>>());
+        let mut runs: Vec<PlanRun> = cyclic
+            .into_iter()
+            .map(|plan_id| PlanRun::failed(plan_id, vec![], SchedulingError::DependencyCycle))
+            .collect();
+        let mut failed: HashSet<Uuid> = runs.iter().map(|run| *run.plan_id()).collect();
+        // plans waiting for a run already in flight, they are not failures
+        let mut deferred: HashSet<Uuid> = HashSet::new();
+
+        for wave in graph.waves() {
+            let mut runnable = vec![];
+            for plan_id in wave {
+                let plan = plans.remove(plan_id).expect("Every plan of the graph is due");
+                // a skipped plan is left due and runs on a later poll, once its upstream plans got their data
+                let upstream_ids = plan.upstream_plan_ids();
+                if let Some(upstream_id) = upstream_ids.iter().find(|upstream_id| failed.contains(upstream_id)) {
+                    failed.insert(*plan_id);
+                    runs.push(PlanRun::failed(*plan_id, vec![], SchedulingError::UpstreamFailed(*upstream_id)));
+                } else if let Some(upstream_id) = upstream_ids
+                    .iter()
+                    .find(|upstream_id| deferred.contains(upstream_id) || self.is_running(upstream_id))
+                {
+                    deferred.insert(*plan_id);
+                    runs.push(PlanRun::failed(*plan_id, vec![], SchedulingError::UpstreamRunning(*upstream_id)));
+                } else if let Some(upstream_id) = self.failed_upstream(&plan, &due_plan_ids).await {
+                    failed.insert(*plan_id);
+                    runs.push(PlanRun::failed(*plan_id, vec![], SchedulingError::UpstreamFailed(upstream_id)));
+                } else {
+                    runnable.push(plan);
+                }
+            }
+
+            let wave_runs = join_all(runnable.into_iter().map(|mut plan| async move {
+                let plan_id = *plan.id();
+                match self.claim(plan_id) {
+                    Ok(_running) => self.catch_up(&mut plan, now).await,
+                    Err(err) => PlanRun::failed(plan_id, vec![], err),
+                }
+            }))
+            .await;
+            for run in &wave_runs {
+                match run.error() {
+                    Some(SchedulingError::PlanAlreadyRunning(_)) => deferred.insert(*run.plan_id()),
+                    _ if !run.is_successful() => failed.insert(*run.plan_id()),
+                    _ => false,
+                };
+            }
+            runs.extend(wave_runs);
+        }
+        return Ok(runs);
+    }
+
+    async fn dispatch_plan(&self, plan_id: &Uuid, now: DateTime<Local>) -> Result<Vec<ExecutionResult>, SchedulingError> {
+        let _running = self.claim(*plan_id)?;
+        let mut plan = self
+            .plan_repository
+            .get_plan_by_id(plan_id)
+            .await
+            .map_err(SchedulingError::PlanNotLoaded)?;
+        // running a plan right away overrides an earlier cancel, a cancel during the run is honoured by the run
+        self.is_cancelled(&plan);
+        let results = self.run_plan(&mut plan, now).await;
+        self.is_cancelled(&plan);
+        return results;
+    }
+
+    async fn dry_run_plan(&self, plan_id: &Uuid) -> Result<DryRunReport, SchedulingError> {
+        let mut plan = self
+            .plan_repository
+            .get_plan_by_id(plan_id)
+            .await
+            .map_err(SchedulingError::PlanNotLoaded)?;
+        // the tasks of the next run as run_plan builds them, on a copy of the plan that is never saved
+        plan.advance(plan.trigger_time().unwrap_or_else(Local::now));
+        let previous_tasks = std::mem::take(plan.tasks_mut());
+        self.task_generator.generate_tasks(&mut plan)?;
+        plan.carry_over(previous_tasks);
+        return Ok(self.executor.dry_run(plan.tasks(), plan.sync_config()).await);
+    }
+
+    async fn run(&self) {
+        let mut shutdown = self.shutdown.subscribe();
+        let mut ticks = tokio::time::interval(self.poll_interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // a slow plan must not hold back the others, so each poll runs alongside the next ones
+        let mut dispatches = FuturesUnordered::new();
+
+        while !*shutdown.borrow_and_update() {
+            tokio::select! {
+                _ = ticks.tick() => dispatches.push(self.dispatch_due_plans(Local::now())),
+                // a failed poll is retried on the next tick
+                Some(_) = dispatches.next(), if !dispatches.is_empty() => {}
+                _ = shutdown.changed() => {}
+            }
+        }
+        while dispatches.next().await.is_some() {}
+    }
+
+    fn cancel(&self, scope: CancelScope) -> usize {
+        self.cancel_requests.lock().unwrap().record(scope, Local::now());
+        return self.executor.cancel_running(scope);
+    }
+
+    fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        domain::synchronization::{
+            custom_errors::{CalendarError, TaskCreationError},
+            sync_plan::{MissedRunPolicy, SyncFrequency},
+            sync_task::SyncTask,
+            trading_calendar::TradingCalendar,
+            value_objects::{dry_run_report::PlannedRequest, sync_config::SyncConfig},
+        },
+        infrastructure::repositories::plan_repo::InMemoryPlanRepository,
+    };
+
+    /// Finishes every task after a delay, answering each request with its own payload
+    struct SlowExecutor {
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl TaskExecutor for SlowExecutor {
+        async fn execute_all(&self, tasks: &mut [SyncTask], _sync_config: &SyncConfig) -> Vec<ExecutionResult> {
+            tokio::time::sleep(self.delay).await;
+            return tasks
+                .iter_mut()
+                .map(|task| {
+                    task.finished();
+                    ExecutionResult::from_task(task, task.spec().payload().clone().unwrap_or_default())
+                })
+                .collect();
+        }
+
+        async fn execute(&self, task: &mut SyncTask, _sync_config: &SyncConfig) -> ExecutionResult {
+            task.finished();
+            return ExecutionResult::from_task(task, serde_json::Value::Null);
+        }
+
+        async fn dry_run(&self, tasks: &[SyncTask], sync_config: &SyncConfig) -> DryRunReport {
+            let requests = tasks.iter().map(|task| PlannedRequest::from_spec(task.spec())).collect();
+            return DryRunReport::new(requests, 0, sync_config.sync_quota(), 0, self.delay);
+        }
+
+        async fn cancel(&self, task: &mut SyncTask) -> SyncStatus {
+            return task.cancel();
+        }
+
+        fn cancel_running(&self, _scope: CancelScope) -> usize {
+            return 0;
+        }
+    }
+
+    /// Requests the data of the day the plan fired at, the params of the missing day cannot be built
+    #[derive(Default)]
+    struct DailyGenerator {
+        missing_day: Option<NaiveDate>,
+    }
+
+    impl TaskGenerator for DailyGenerator {
+        fn generate_tasks(&self, plan: &mut SyncPlan) -> Result<(), TaskCreationError> {
+            let fired_on = plan.last_trigger_time().map(|fired_at| fired_at.date_naive());
+            if fired_on.is_some() && fired_on == self.missing_day {
+                return Err(TaskCreationError::MissingParam("trade_date".to_string()));
+            }
+            let trade_date = plan.last_trigger_time().map(|fired_at| fired_at.format("%Y%m%d").to_string());
+            let payload = json!({"api_name": "daily", "params": {"trade_date": trade_date}});
+            plan.create_tasks(&["https://api.tushare.pro"], &["POST"], &[Some(&payload)])?;
+            return Ok(());
+        }
+    }
+
+    async fn trigger_time(plan_repository: &InMemoryPlanRepository, plan_id: &Uuid) -> Option<DateTime<Local>> {
+        return *plan_repository.get_plan_by_id(plan_id).await.unwrap().trigger_time();
+    }
+
+    fn due_plan(active: bool) -> SyncPlan {
+        let mut plan = SyncPlan::default();
+        plan.set_id(Uuid::new_v4())
+            .set_active(active)
+            .set_trigger_time(Some(Local::now() - chrono::Duration::minutes(1)));
+        return plan;
+    }
+
+    fn scheduler_for(plan_repository: Arc<InMemoryPlanRepository>, delay: Duration) -> SyncScheduler {
+        return SyncScheduler::new(
+            plan_repository,
+            Arc::new(SlowExecutor { delay }),
+            Arc::new(DailyGenerator::default()),
+        )
+        .with_poll_interval(Duration::from_millis(10));
+    }
+
+    #[tokio::test]
+    async fn it_should_run_active_due_plans_and_advance_their_trigger_time() {
+        let (active_plan, inactive_plan) = (due_plan(true), due_plan(false));
+        let (active_id, inactive_id) = (*active_plan.id(), *inactive_plan.id());
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![active_plan, inactive_plan]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+        let now = Local::now();
+
+        let runs = scheduler.dispatch_due_plans(now).await.unwrap();
+
+        assert_eq!(runs.len(), 1);
+        assert_eq!(*runs[0].plan_id(), active_id);
+        let results = runs[0].results();
+        assert_eq!(results.len(), 1);
+        assert_eq!(*results[0].status(), SyncStatus::Finished);
+        assert!(trigger_time(&plan_repository, &active_id).await.unwrap() > now);
+        assert!(trigger_time(&plan_repository, &inactive_id).await.unwrap() < now);
+    }
+
+    #[tokio::test]
+    async fn it_should_generate_the_tasks_of_every_run() {
+        let plan = due_plan(true);
+        let plan_id = *plan.id();
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+        let trade_dates = || async {
+            let tasks = plan_repository.get_tasks_by_plan_id(&plan_id).await.unwrap();
+            tasks
+                .iter()
+                .map(|task| task.spec().payload().as_ref().unwrap()["params"]["trade_date"].clone())
+                .collect::<Vec<_>>()
+        };
+
+        let monday = Local.with_ymd_and_hms(2023, 6, 5, 17, 0, 0).unwrap();
+        scheduler.dispatch_plan(&plan_id, monday).await.unwrap();
+        assert_eq!(trade_dates().await, vec![json!("20230605")]);
+
+        scheduler.dispatch_plan(&plan_id, monday + chrono::Duration::days(1)).await.unwrap();
+        assert_eq!(trade_dates().await, vec![json!("20230606")]);
+    }
+
+    #[tokio::test]
+    async fn it_should_dry_run_a_plan_without_touching_it() {
+        let mut plan = due_plan(true);
+        let (plan_id, due_time) = (*plan.id(), *plan.trigger_time());
+        // a task of a previous run that finished is not requested again
+        DailyGenerator::default().generate_tasks(&mut plan).unwrap();
+        plan.tasks_mut()[0].set_status(SyncStatus::Finished);
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+
+        let report = scheduler.dry_run_plan(&plan_id).await.unwrap();
+
+        assert_eq!(report.requests().len(), 1);
+        assert_eq!(report.requests_per_endpoint().get("https://api.tushare.pro/"), Some(&1));
+        let trade_date = due_time.unwrap().format("%Y%m%d").to_string();
+        assert_eq!(report.requests()[0].payload().as_ref().unwrap()["params"]["trade_date"], json!(trade_date));
+        assert_eq!(trigger_time(&plan_repository, &plan_id).await, due_time);
+    }
+
+    #[tokio::test]
+    async fn it_should_run_once_per_missed_day_when_catching_up() {
+        let mut plan = due_plan(true);
+        let plan_id = *plan.id();
+        let now = Local::now();
+        let two_days_ago = (now - chrono::Duration::days(2)).with_nanosecond(0).unwrap();
+        plan.set_trigger_time(Some(two_days_ago))
+            .set_missed_run_policy(MissedRunPolicy::RunAll);
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+
+        let runs = scheduler.dispatch_due_plans(now).await.unwrap();
+
+        let trade_dates: Vec<&serde_json::Value> =
+            runs[0].results().iter().map(|result| &result.data()["params"]["trade_date"]).collect();
+        let missed_days: Vec<serde_json::Value> = (0..3)
+            .map(|day| json!((two_days_ago + chrono::Duration::days(day)).format("%Y%m%d").to_string()))
+            .collect();
+        assert_eq!(trade_dates, missed_days.iter().collect::<Vec<_>>());
+        assert_eq!(trigger_time(&plan_repository, &plan_id).await, Some(two_days_ago + chrono::Duration::days(3)));
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_catching_up_when_the_plan_is_cancelled() {
+        let mut plan = due_plan(true);
+        let plan_id = *plan.id();
+        let now = Local::now();
+        let two_days_ago = (now - chrono::Duration::days(2)).with_nanosecond(0).unwrap();
+        plan.set_trigger_time(Some(two_days_ago))
+            .set_missed_run_policy(MissedRunPolicy::RunAll);
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::from_millis(100));
+
+        let cancel_during_first_run = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            scheduler.cancel(CancelScope::Plan(plan_id))
+        };
+        let (runs, _) = tokio::join!(scheduler.dispatch_due_plans(now), cancel_during_first_run);
+
+        let runs = runs.unwrap();
+        assert_eq!(runs[0].results().len(), 1);
+        assert!(matches!(runs[0].error(), Some(SchedulingError::Cancelled)));
+        assert!(trigger_time(&plan_repository, &plan_id).await.unwrap() > now);
+    }
+
+    #[tokio::test]
+    async fn it_should_skip_the_next_run_of_a_plan_cancelled_between_runs() {
+        let plan = due_plan(true);
+        let plan_id = *plan.id();
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+
+        assert_eq!(scheduler.cancel(CancelScope::Plan(plan_id)), 0);
+        let now = Local::now();
+        let cancelled = scheduler.dispatch_due_plans(now).await.unwrap();
+
+        assert!(cancelled[0].results().is_empty());
+        assert!(matches!(cancelled[0].error(), Some(SchedulingError::Cancelled)));
+        assert!(trigger_time(&plan_repository, &plan_id).await.unwrap() > now);
+        // only the run due at the time of the cancel is skipped
+        let mut plan = plan_repository.get_plan_by_id(&plan_id).await.unwrap();
+        plan.set_trigger_time(Some(Local::now() - chrono::Duration::minutes(1)));
+        plan_repository.update_plans(&[&plan]).await.unwrap();
+        let next_runs = scheduler.dispatch_due_plans(Local::now()).await.unwrap();
+        assert!(next_runs[0].is_successful());
+        assert_eq!(next_runs[0].results().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_caught_up_runs_when_a_later_one_cannot_start() {
+        let mut plan = due_plan(true);
+        let plan_id = *plan.id();
+        let now = Local::now();
+        let yesterday = (now - chrono::Duration::days(1)).with_nanosecond(0).unwrap();
+        plan.set_trigger_time(Some(yesterday))
+            .set_missed_run_policy(MissedRunPolicy::RunAll);
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let generator = DailyGenerator {
+            missing_day: Some(now.date_naive()),
+        };
+        let executor = Arc::new(SlowExecutor { delay: Duration::ZERO });
+        let scheduler = SyncScheduler::new(plan_repository.clone(), executor, Arc::new(generator));
+
+        let runs = scheduler.dispatch_due_plans(now).await.unwrap();
+
+        assert_eq!(runs[0].results().len(), 1);
+        assert!(matches!(runs[0].error(), Some(SchedulingError::TaskCreationFailed(_))));
+        assert_eq!(trigger_time(&plan_repository, &plan_id).await, Some(yesterday + chrono::Duration::days(2)));
+    }
+
+    #[tokio::test]
+    async fn it_should_report_and_skip_runs_on_days_the_trading_calendar_does_not_cover() {
+        let mut plan = due_plan(true);
+        let plan_id = *plan.id();
+        let fired_at = plan.trigger_time().unwrap().with_nanosecond(0).unwrap();
+        plan.set_trigger_time(Some(fired_at));
+        let calendar = TradingCalendar::new("SSE", [(fired_at.date_naive() - chrono::Duration::days(3), true)]).unwrap();
+        let calendar = Arc::new(calendar);
+        plan.set_frequency(SyncFrequency::Daily).run_on_trading_days(calendar, fired_at).unwrap();
+        assert_eq!(*plan.trigger_time(), Some(fired_at));
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+
+        let runs = scheduler.dispatch_due_plans(Local::now()).await.unwrap();
+
+        assert!(runs[0].results().is_empty());
+        assert!(matches!(runs[0].error(), Some(SchedulingError::OutsideCalendar(CalendarError::NotCovered(..)))));
+        assert_eq!(trigger_time(&plan_repository, &plan_id).await, Some(fired_at + chrono::Duration::days(1)));
+        assert_eq!(*plan_repository.get_plan_by_id(&plan_id).await.unwrap().last_trigger_time(), None);
+    }
+
+    #[tokio::test]
+    async fn it_should_defer_downstream_plans_while_an_upstream_plan_runs() {
+        let (trade_cal, stock_basic, mut daily) = (due_plan(true), due_plan(true), due_plan(true));
+        let (trade_cal_id, stock_basic_id, daily_id) = (*trade_cal.id(), *stock_basic.id(), *daily.id());
+        let daily_due_time = *daily.trigger_time();
+        daily.depend_on(trade_cal_id, &[]).unwrap().depend_on(stock_basic_id, &[]).unwrap();
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![trade_cal, stock_basic, daily]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+        // a manual run of trade_cal is still in flight
+        let _running = scheduler.claim(trade_cal_id).unwrap();
+
+        let runs = scheduler.dispatch_due_plans(Local::now()).await.unwrap();
+
+        let run_of = |plan_id: Uuid| runs.iter().find(|run| *run.plan_id() == plan_id).unwrap();
+        assert!(run_of(stock_basic_id).is_successful());
+        assert!(matches!(run_of(trade_cal_id).error(), Some(SchedulingError::PlanAlreadyRunning(_))));
+        assert!(matches!(run_of(daily_id).error(), Some(SchedulingError::UpstreamRunning(id)) if *id == trade_cal_id));
+        assert_eq!(trigger_time(&plan_repository, &daily_id).await, daily_due_time);
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_downstream_plans_due_until_their_upstream_plan_succeeds() {
+        let (mut trade_cal, mut daily) = (due_plan(true), due_plan(true));
+        let (trade_cal_id, daily_id) = (*trade_cal.id(), *daily.id());
+        // trade_cal failed in an earlier cycle and is not due again yet
+        let failed_run = Local::now() - chrono::Duration::hours(1);
+        trade_cal
+            .set_trigger_time(Some(Local::now() + chrono::Duration::hours(1)))
+            .set_last_trigger_time(Some(failed_run));
+        daily.depend_on(trade_cal_id, &[&trade_cal]).unwrap();
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![trade_cal, daily]));
+        let scheduler = scheduler_for(plan_repository.clone(), Duration::ZERO);
+
+        let runs = scheduler.dispatch_due_plans(Local::now()).await.unwrap();
+        assert!(matches!(runs[0].error(), Some(SchedulingError::UpstreamFailed(id)) if *id == trade_cal_id));
+        assert!(trigger_time(&plan_repository, &daily_id).await.unwrap() < Local::now());
+
+        let mut trade_cal = plan_repository.get_plan_by_id(&trade_cal_id).await.unwrap();
+        trade_cal.set_last_successful_trigger_time(Some(failed_run));
+        plan_repository.update_plans(&[&trade_cal]).await.unwrap();
+        let runs = scheduler.dispatch_due_plans(Local::now()).await.unwrap();
+        assert!(runs[0].is_successful());
+    }
+
+    #[tokio::test]
+    async fn it_should_refuse_a_second_run_of_a_running_plan() {
+        let plan = due_plan(true);
+        let plan_id = *plan.id();
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = scheduler_for(plan_repository, Duration::from_millis(50));
+
+        let (first, second) = tokio::join!(
+            scheduler.dispatch_plan(&plan_id, Local::now()),
+            async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                scheduler.dispatch_plan(&plan_id, Local::now()).await
+            }
+        );
+
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(SchedulingError::PlanAlreadyRunning(id)) if id == plan_id));
+        assert!(scheduler.dispatch_plan(&plan_id, Local::now()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_should_let_running_plans_finish_on_shutdown() {
+        let plan = due_plan(true);
+        let plan_id = *plan.id();
+        let plan_repository = Arc::new(InMemoryPlanRepository::with_plans(vec![plan]));
+        let scheduler = Arc::new(scheduler_for(plan_repository.clone(), Duration::from_millis(50)));
+
+        let daemon = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.run().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        scheduler.shutdown();
+        tokio::time::timeout(Duration::from_secs(1), daemon).await.unwrap().unwrap();
+
+        assert!(trigger_time(&plan_repository, &plan_id).await.unwrap() > Local::now());
+        assert!(scheduler.running_plans.lock().unwrap().is_empty());
+    }
+}
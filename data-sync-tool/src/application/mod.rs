@@ -1,4 +1,5 @@
 pub mod datasource_management;
+pub mod impls;
 pub mod param_management;
 pub mod storage_management;
 pub mod sync_scheduling;
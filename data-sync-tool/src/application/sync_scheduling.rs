@@ -1,9 +1,70 @@
 /// Synchronization Application Services
 use async_trait::async_trait;
-use crate::domain::synchronization::sync_plan::SyncPlan;
-use std::error::Error;
+use chrono::prelude::*;
+use getset::Getters;
+use uuid::Uuid;
+
+use crate::domain::synchronization::{
+    custom_errors::{RepositoryError, SchedulingError, TaskCreationError},
+    sync_plan::SyncPlan,
+    sync_task::SyncStatus,
+    task_executor::CancelScope,
+    value_objects::{dry_run_report::DryRunReport, execution_result::ExecutionResult},
+};
+
+/// Outcome of dispatching one plan
+#[derive(Debug, Getters)]
+#[getset(get = "pub")]
+pub struct PlanRun {
+    plan_id: Uuid,
+    results: Vec<ExecutionResult>, // of every run that went through, missed runs caught up included
+    error: Option<SchedulingError>, // why the plan, or one of its missed runs, could not run
+}
+
+impl PlanRun {
+    pub fn new(plan_id: Uuid, results: Vec<ExecutionResult>) -> Self {
+        Self { plan_id, results, error: None }
+    }
+
+    /// A plan that stopped on the error, keeping the results of the runs before it
+    pub fn failed(plan_id: Uuid, results: Vec<ExecutionResult>, error: SchedulingError) -> Self {
+        Self {
+            plan_id,
+            results,
+            error: Some(error),
+        }
+    }
+
+    /// A run succeeds when every task of the plan finished
+    pub fn is_successful(&self) -> bool {
+        return self.error.is_none() && self.results.iter().all(|result| *result.status() == SyncStatus::Finished);
+    }
+}
+
+/// Builds the tasks of one run of a plan, e.g. from its parameter template and the trigger time it fired at
+pub trait TaskGenerator: Send + Sync {
+    fn generate_tasks(&self, plan: &mut SyncPlan) -> Result<(), TaskCreationError>;
+    /// Told the results of each run once they are handed over to storage, e.g. to move the watermark of the dataset
+    fn record_run(&self, _plan: &SyncPlan, _results: &[ExecutionResult]) {}
+}
 
 #[async_trait]
 pub trait SyncSchedulingService {
-    
-}
\ No newline at end of file
+    /// Run every active plan that is due at `now`, one run per plan.
+    /// Plans start once the latest run of each of their upstream plans succeeded, due in the same cycle or not.
+    /// They are left due when one did not, skipped while it failed and deferred while it is still running.
+    async fn dispatch_due_plans(&self, now: DateTime<Local>) -> Result<Vec<PlanRun>, RepositoryError>;
+    /// Run a plan right away, refused while a previous run of the plan is in flight
+    async fn dispatch_plan(&self, plan_id: &Uuid, now: DateTime<Local>) -> Result<Vec<ExecutionResult>, SchedulingError>;
+    /// Build the tasks of the next run of a plan and go through its execution without sending requests or saving the plan
+    async fn dry_run_plan(&self, plan_id: &Uuid) -> Result<DryRunReport, SchedulingError>;
+    /// Cancel the tasks in flight in scope, e.g. on a command from the CLI or the API.
+    /// Returns how many tasks were told to stop, their final status is saved with their plan.
+    /// Each plan in a plan or data source scope also skips the runs it has yet to start: the rest of its missed runs,
+    /// or its next run when none is in flight.
+    fn cancel(&self, scope: CancelScope) -> usize;
+    /// Keep dispatching due plans until shut down, the runs in flight are awaited before returning
+    async fn run(&self);
+    /// Ask the daemon loop to stop polling
+    fn shutdown(&self);
+}
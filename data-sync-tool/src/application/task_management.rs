@@ -1,7 +1,5 @@
 /// Synchronization Task Management Application Services
 use async_trait::async_trait;
-use crate::domain::synchronization::sync_plan::SyncPlan;
-use std::error::Error;
 
 #[async_trait]
 pub trait TaskManagementService {
@@ -0,0 +1,61 @@
+//! Tabular Data
+//! Reads rows as records, whether they come as a `fields`/`items` table or as a list of records
+
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+
+/// Records of a `fields`/`items` table, bare or under `data` as Tushare returns it, or of a list of records.
+/// None for anything else.
+pub fn records(rows: &Value) -> Option<Vec<Map<String, Value>>> {
+    let table = rows.get("data").unwrap_or(rows);
+    match (table.get("fields"), table.get("items"), table) {
+        (Some(Value::Array(fields)), Some(Value::Array(items)), _) => {
+            let records = items
+                .iter()
+                .filter_map(Value::as_array)
+                .map(|item| {
+                    fields
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .zip(item.iter().cloned())
+                        .collect()
+                })
+                .collect();
+            return Some(records);
+        }
+        (_, _, Value::Array(records)) => return Some(records.iter().filter_map(Value::as_object).cloned().collect()),
+        _ => return None,
+    }
+}
+
+/// Dates come as `20230621` or `2023-06-21`, quoted or not
+pub fn parse_date(value: &Value) -> Option<NaiveDate> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        _ => return None,
+    };
+    return NaiveDate::parse_from_str(&text, "%Y%m%d")
+        .or_else(|_| NaiveDate::parse_from_str(&text, "%Y-%m-%d"))
+        .ok();
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn it_should_read_tables_and_records_alike() {
+        let table = json!({"data": {"fields": ["ts_code", "trade_date"], "items": [["000001.SZ", 20230621]]}});
+        let records = json!([{"ts_code": "000001.SZ", "trade_date": "2023-06-21"}]);
+
+        let (from_table, from_records) = (super::records(&table).unwrap(), super::records(&records).unwrap());
+        assert_eq!(from_table[0]["ts_code"], from_records[0]["ts_code"]);
+        assert_eq!(parse_date(&from_table[0]["trade_date"]), parse_date(&from_records[0]["trade_date"]));
+        assert!(super::records(&json!({"code": 0})).is_none());
+        assert_eq!(parse_date(&json!(true)), None);
+    }
+}
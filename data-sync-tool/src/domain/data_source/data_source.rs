@@ -1,12 +1,19 @@
 //! Data Source Domain Object Definition
 
 use chrono::prelude::*;
-use fake::{ Fake};
 use getset::{CopyGetters, Getters, MutGetters, Setters};
-use std::{cell::RefCell, collections::HashMap, error, fmt, sync::Arc};
+use std::{collections::HashMap, error, fmt};
 use uuid::Uuid;
 
-use super::{dataset::Dataset, value_object::local_storage::LocalStorage};
+use super::{
+    dataset::Dataset,
+    value_object::{
+        api_credential::{ApiCredential, CredentialPool},
+        local_storage::LocalStorage,
+        rest_config::RestSourceConfig,
+    },
+};
+use crate::domain::synchronization::value_objects::sync_config::Quota;
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
@@ -44,8 +51,8 @@ pub struct DataSource {
     #[getset(get = "pub", set = "pub")]
     description: String,
 
-    #[getset(get = "pub", set = "pub")]
-    api_key: String,
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    credentials: CredentialPool, // keys used in turn, each with its own quota
 
     #[getset(get = "pub", set = "pub")]
     create_date: DateTime<Local>,
@@ -61,9 +68,13 @@ pub struct DataSource {
 
     #[getset(get = "pub", set = "pub")]
     local_storage: LocalStorage,
+
+    #[getset(get = "pub", set = "pub")]
+    rest_config: Option<RestSourceConfig>, // how a generic HTTP source is requested
 }
 
 impl DataSource {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: Uuid,
         name: &str,
@@ -76,26 +87,27 @@ impl DataSource {
     ) -> Result<Self> {
         let mut id_mapped_datasets: HashMap<String, Dataset> = HashMap::new();
 
-        datasets.into_iter().for_each(|v| {
+        datasets.iter().for_each(|v| {
             let dataset_id = v.id().to_string();
             id_mapped_datasets.insert(dataset_id, v.clone());
         });
 
         match last_update_time {
             None => {
-                if let Some(_) = update_successful {
+                if update_successful.is_some() {
                     return Err(Box::new(UpdateStatusShouldCoexistWithItsDate));
                 } else {
                     return Ok(Self {
                         id,
                         name: name.to_string(),
                         description: description.to_string(),
-                        api_key: api_key.to_string(),
+                        credentials: Self::single_key_pool(api_key),
                         create_date,
                         last_update_time: None,
                         update_successful: None,
                         datasets: id_mapped_datasets,
                         local_storage: LocalStorage::default(),
+                        rest_config: None,
                     });
                 }
             }
@@ -105,30 +117,45 @@ impl DataSource {
                         id,
                         name: name.to_string(),
                         description: description.to_string(),
-                        api_key: api_key.to_string(),
+                        credentials: Self::single_key_pool(api_key),
                         create_date,
                         last_update_time: Some(update_dt),
                         update_successful: Some(update_ok),
                         datasets: id_mapped_datasets,
-                        local_storage: LocalStorage::default()
+                        local_storage: LocalStorage::default(),
+                        rest_config: None,
                     });
                 } else {
                     return Ok(Self {
                         id,
                         name: name.to_string(),
                         description: description.to_string(),
-                        api_key: api_key.to_string(),
+                        credentials: Self::single_key_pool(api_key),
                         create_date,
                         last_update_time: Some(update_dt),
                         update_successful: Some(false),
                         datasets: id_mapped_datasets,
-                        local_storage: LocalStorage::default()
+                        local_storage: LocalStorage::default(),
+                        rest_config: None,
                     });
                 }
             }
         }
     }
 
+    /// Pool holding the key given to the constructor, empty when no key is given
+    fn single_key_pool(api_key: &str) -> CredentialPool {
+        if api_key.is_empty() {
+            return CredentialPool::default();
+        }
+        return CredentialPool::new(vec![ApiCredential::new("default", api_key, Quota::default())]);
+    }
+
+    pub fn add_credential(&mut self, credential: ApiCredential) -> &mut Self {
+        self.credentials.add(credential);
+        return self;
+    }
+
     pub fn set_last_update_time(&mut self, update_dt: DateTime<Local>) -> Result<&mut Self> {
         if self.create_date > update_dt {
             Err(Box::new(UpdateTimeEarlierThanCreationError))
@@ -197,8 +224,8 @@ impl DataSource {
 impl std::fmt::Display for DataSource {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f,
-               "DataSource(id: {},  name: {}, description: {}, api_key: {}, create_date: {}, last_update_time: {:?}, update_successful: {:?}, datasets: {:?})",
-               self.id, self.name, self.description, self.api_key, self.create_date.with_timezone(&Local), Some(self.last_update_time),
+               "DataSource(id: {},  name: {}, description: {}, credentials: {}, create_date: {}, last_update_time: {:?}, update_successful: {:?}, datasets: {:?})",
+               self.id, self.name, self.description, self.credentials.credentials().len(), self.create_date.with_timezone(&Local), Some(self.last_update_time),
                Some(self.update_successful), self.datasets)
     }
 }
@@ -209,12 +236,13 @@ impl Default for DataSource {
             id: Uuid::new_v4(),
             name: String::from("New DataSource"),
             description: String::from("Please write a description."),
-            api_key: String::from(""),
+            credentials: CredentialPool::default(),
             create_date: chrono::offset::Local::now(),
             last_update_time: None,
             update_successful: None,
             datasets: HashMap::new(),
-            local_storage: LocalStorage::default()
+            local_storage: LocalStorage::default(),
+            rest_config: None,
         }
     }
 }
@@ -6,14 +6,16 @@ use super::{
     value_object::{
         api_param::APIParam,
         data_schema::{Column, DataSchema},
+        endpoint_template::EndpointTemplate,
+        extraction_rules::ExtractionRules,
+        watermark::Watermark,
     },
 };
+use crate::domain::synchronization::custom_errors::TaskCreationError;
 use chrono::prelude::*;
-use fake::{ Fake};
 use getset::{Getters, MutGetters, Setters};
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::{cell::RefCell, collections::HashMap, error, fmt, sync::Arc};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, error, fmt};
 use uuid::Uuid;
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
@@ -49,9 +51,16 @@ pub struct Dataset {
     update_successful: Option<bool>,
     #[getset(get = "pub", set = "pub")]
     sync_enabled: bool,
+    #[getset(get = "pub", set = "pub")]
+    watermark_column: Option<String>, // date column that orders the rows, e.g. trade_date
+    #[getset(get = "pub", set = "pub")]
+    watermark: Option<Watermark>,
+    #[getset(get = "pub", set = "pub")]
+    extraction_rules: Option<ExtractionRules>, // where the records are in a response and how they page
 }
 
 impl Dataset {
+    #[allow(clippy::too_many_arguments, clippy::ptr_arg)]
     pub fn new(
         id: Uuid,
         name: &str,
@@ -65,19 +74,17 @@ impl Dataset {
         sync_enabled: bool,
     ) -> Result<Self> {
         let mut params_map: HashMap<String, APIParam> = HashMap::new();
-        api_params.into_iter().for_each(|p| {
+        api_params.iter().for_each(|p| {
             params_map.insert(p.name().clone(), p.clone());
         });
-        lazy_static! {
-            static ref RE: Regex = Regex::new("^/[a-zA-Z0-9-_]+$").unwrap();
-        }
-        if !RE.is_match(endpoint) {
+        // e.g. `/daily`, `/v2/daily` or `/stocks/{symbol}/bars/{interval}`
+        if !endpoint.starts_with('/') || EndpointTemplate::parse(endpoint).is_none() {
             return Err(Box::new(InvalidAPIEndpointFormat));
         }
 
         match last_update_time {
             None => {
-                if let Some(_) = update_successful {
+                if update_successful.is_some() {
                     return Err(Box::new(UpdateStatusShouldCoexistWithItsDate));
                 } else {
                     return Ok(Self {
@@ -86,11 +93,14 @@ impl Dataset {
                         description: description.to_string(),
                         endpoint: endpoint.to_string(),
                         api_params: params_map,
-                        schema: schema,
+                        schema,
                         create_date,
                         last_update_time: None,
                         update_successful: None,
                         sync_enabled,
+                        watermark_column: None,
+                        watermark: None,
+                        extraction_rules: None,
                     });
                 }
             }
@@ -102,11 +112,14 @@ impl Dataset {
                         description: description.to_string(),
                         endpoint: endpoint.to_string(),
                         api_params: params_map,
-                        schema: schema,
+                        schema,
                         create_date,
                         last_update_time: Some(update_dt),
                         update_successful: Some(update_ok),
                         sync_enabled,
+                        watermark_column: None,
+                        watermark: None,
+                        extraction_rules: None,
                     });
                 } else {
                     return Ok(Self {
@@ -115,11 +128,14 @@ impl Dataset {
                         description: description.to_string(),
                         endpoint: endpoint.to_string(),
                         api_params: params_map,
-                        schema: schema,
+                        schema,
                         create_date,
                         last_update_time: Some(update_dt),
                         update_successful: Some(false),
                         sync_enabled,
+                        watermark_column: None,
+                        watermark: None,
+                        extraction_rules: None,
                     });
                 }
             }
@@ -135,6 +151,54 @@ impl Dataset {
         }
     }
 
+    /// Called once rows of the dataset are handed over to storage, moves the watermark to the latest row
+    pub fn record_successful_write(&mut self, written_rows: &Value, write_time: DateTime<Local>) -> Result<&mut Self> {
+        self.set_last_update_time(write_time)?;
+        self.update_successful = Some(true);
+        let Some(column) = &self.watermark_column else {
+            return Ok(self);
+        };
+        if let Some(written) = Watermark::from_rows(column, written_rows) {
+            match &mut self.watermark {
+                Some(watermark) if watermark.column() == column => {
+                    watermark.advance(&written);
+                }
+                _ => self.watermark = Some(written),
+            }
+        }
+        return Ok(self);
+    }
+
+    /// Endpoint path segments with the placeholders bound from the params of a task, and the params left
+    /// for the query or the body, once the params are checked against the API params of the dataset
+    pub fn bind_endpoint(&self, params: &Value) -> std::result::Result<(Vec<String>, Value), TaskCreationError> {
+        let template = EndpointTemplate::parse(&self.endpoint)
+            .ok_or_else(|| TaskCreationError::InvalidEndpointTemplate(self.endpoint.clone()))?;
+        self.check_params(params, &template.placeholders())?;
+        return template.bind(params);
+    }
+
+    /// Every required API param has a value and every other param is a declared API param or a placeholder.
+    /// A dataset that declares no API params takes any param.
+    pub fn check_params(&self, params: &Value, placeholders: &[&str]) -> std::result::Result<(), TaskCreationError> {
+        let is_given = |name: &str| params.get(name).is_some_and(|value| !value.is_null());
+        if let Some(missing) = self.api_params.values().find(|param| *param.required() && !is_given(param.name())) {
+            return Err(TaskCreationError::MissingParam(missing.name().clone()));
+        }
+        if self.api_params.is_empty() {
+            return Ok(());
+        }
+        let unused = params
+            .as_object()
+            .into_iter()
+            .flat_map(Map::keys)
+            .find(|name| !self.api_params.contains_key(*name) && !placeholders.contains(&name.as_str()));
+        if let Some(unused) = unused {
+            return Err(TaskCreationError::UnusedParam(unused.clone()));
+        }
+        return Ok(());
+    }
+
     pub fn add_api_params(&mut self, api_params: &Vec<APIParam>) -> Result<&mut Self> {
         for api_param in api_params {
             self.api_params.insert(
@@ -201,6 +265,9 @@ impl Default for Dataset {
             last_update_time: None,
             update_successful: None,
             sync_enabled: false,
+            watermark_column: None,
+            watermark: None,
+            extraction_rules: None,
         }
     }
 }
@@ -1,4 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod data_source;
 pub mod dataset;
 pub mod repository;
 pub mod value_object;
+pub mod source_adapter;
\ No newline at end of file
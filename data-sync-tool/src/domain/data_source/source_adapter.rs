@@ -0,0 +1,22 @@
+// Source Adapter
+// Speaks the protocol of a remote data source: how a dataset is requested and how its rows come back
+
+use serde_json::{Map, Value};
+
+use super::dataset::Dataset;
+use crate::domain::synchronization::{
+    custom_errors::{RequestError, TaskCreationError},
+    value_objects::task_spec::TaskSpec,
+};
+
+/// One record of a dataset, keyed by column name
+pub type Row = Map<String, Value>;
+
+pub trait SourceAdapter: Send + Sync {
+    /// Spec of the request for the dataset with the generated params, the key is added when the request is sent
+    fn build_request(&self, dataset: &Dataset, params: &Value) -> Result<TaskSpec, TaskCreationError>;
+    /// Fail with the error class of a response that reports an error, e.g. in the body of an HTTP 200
+    fn check_response(&self, response: &Value) -> Result<(), RequestError>;
+    /// Rows of a successful response, typed after the schema of the dataset
+    fn parse_rows(&self, dataset: &Dataset, response: &Value) -> Result<Vec<Row>, RequestError>;
+}
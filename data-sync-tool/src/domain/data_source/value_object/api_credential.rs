@@ -0,0 +1,126 @@
+//! API Credential Value Object
+//! Keys a data source can be requested with, each with its own quota
+
+use std::{collections::HashMap, fmt};
+
+use chrono::NaiveDate;
+use getset::{Getters, Setters};
+use uuid::Uuid;
+
+use crate::domain::synchronization::value_objects::sync_config::Quota;
+
+#[derive(PartialEq, Eq, Clone, Getters, Setters)]
+#[getset(get = "pub", set = "pub")]
+pub struct ApiCredential {
+    id: Uuid,
+    name: String,
+    api_key: String,
+    quota: Quota,
+}
+
+impl ApiCredential {
+    pub fn new(name: &str, api_key: &str, quota: Quota) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            api_key: api_key.to_string(),
+            quota,
+        }
+    }
+}
+
+// the key itself never shows up in logs
+impl fmt::Debug for ApiCredential {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApiCredential")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("quota", &self.quota)
+            .finish()
+    }
+}
+
+/// Credentials of a data source used in turn, a key that ran out of its daily limit sits out until the next day
+#[derive(Debug, PartialEq, Eq, Clone, Default, Getters)]
+pub struct CredentialPool {
+    #[getset(get = "pub")]
+    credentials: Vec<ApiCredential>,
+    next: usize,
+    exhausted: HashMap<Uuid, NaiveDate>,
+}
+
+impl CredentialPool {
+    pub fn new(credentials: Vec<ApiCredential>) -> Self {
+        Self {
+            credentials,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, credential: ApiCredential) -> &mut Self {
+        self.credentials.push(credential);
+        return self;
+    }
+
+    pub fn remove(&mut self, credential_id: &Uuid) -> &mut Self {
+        self.credentials.retain(|credential| credential.id() != credential_id);
+        self.exhausted.remove(credential_id);
+        return self;
+    }
+
+    pub fn is_exhausted(&self, credential_id: &Uuid, today: NaiveDate) -> bool {
+        return self.exhausted.get(credential_id) == Some(&today);
+    }
+
+    /// Take the key out of rotation for the rest of the day
+    pub fn exhaust(&mut self, credential_id: &Uuid, today: NaiveDate) -> &mut Self {
+        self.exhausted.insert(*credential_id, today);
+        return self;
+    }
+
+    /// Keys still in rotation today
+    pub fn available(&self, today: NaiveDate) -> usize {
+        return self
+            .credentials
+            .iter()
+            .filter(|credential| !self.is_exhausted(credential.id(), today))
+            .count();
+    }
+
+    /// Next key in rotation, None when every key ran out today
+    pub fn next(&mut self, today: NaiveDate) -> Option<ApiCredential> {
+        for _ in 0..self.credentials.len() {
+            let position = self.next % self.credentials.len();
+            self.next = position + 1;
+            let credential = &self.credentials[position];
+            if !self.is_exhausted(credential.id(), today) {
+                return Some(credential.clone());
+            }
+        }
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_rotate_keys_and_skip_exhausted_ones_until_the_next_day() {
+        let today = NaiveDate::from_ymd_opt(2023, 6, 21).unwrap();
+        let (first, second) = (ApiCredential::new("first", "token-1", Quota::default()), ApiCredential::new("second", "token-2", Quota::default()));
+        let mut pool = CredentialPool::new(vec![first.clone(), second.clone()]);
+
+        assert_eq!(pool.next(today), Some(first.clone()));
+        assert_eq!(pool.next(today), Some(second.clone()));
+        pool.exhaust(second.id(), today);
+        assert_eq!(pool.next(today), Some(first.clone()));
+        assert_eq!(pool.next(today), Some(first.clone()));
+        assert_eq!(pool.available(today), 1);
+
+        pool.exhaust(first.id(), today);
+        assert_eq!(pool.next(today), None);
+        assert_eq!(pool.available(today.succ_opt().unwrap()), 2);
+        assert!(!format!("{:?}", pool).contains("token-1"));
+    }
+}
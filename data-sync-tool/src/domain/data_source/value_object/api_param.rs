@@ -2,7 +2,6 @@
 
 use super::field_type::FieldType;
 
-use fake::{ Fake};
 use getset::{CopyGetters, Getters, MutGetters, Setters};
 use uuid::Uuid;
 
@@ -56,5 +55,4 @@ impl Default for APIParam {
 
 #[cfg(test)]
 mod test {
-    use super::*;
 }
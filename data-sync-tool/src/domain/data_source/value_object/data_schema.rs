@@ -1,12 +1,25 @@
 // Data Schema Value Object Definition
 
-use fake::{ Fake};
 use getset::{Getters, MutGetters};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, error, fmt};
 
 use super::field_type::FieldType;
 use crate::common::errors::Result;
 
+/// A value of a record that cannot be cast to the type of its column
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MistypedValue {
+    pub column: String,
+    pub value: Value,
+}
+impl fmt::Display for MistypedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Value {} does not fit column {}!", self.value, self.column)
+    }
+}
+impl error::Error for MistypedValue {}
+
 #[derive(Debug,  PartialEq, Eq, Clone, Getters, MutGetters)]
 #[getset(get = "pub")]
 pub struct Column {
@@ -24,9 +37,28 @@ impl Column {
             description: description.to_string(),
         })
     }
+
+    /// Convert a value received from a remote to the type of the column, None when it does not fit.
+    /// Numbers sent as strings are parsed and nulls stay null.
+    pub fn cast(&self, value: &Value) -> Option<Value> {
+        match (&self.col_type, value) {
+            (_, Value::Null) => Some(Value::Null),
+            (FieldType::String, Value::String(_)) => Some(value.clone()),
+            (FieldType::String, Value::Number(number)) => Some(Value::String(number.to_string())),
+            (FieldType::Int, Value::Number(number)) => match number.as_i64() {
+                Some(int) => Some(Value::from(int)),
+                // integers sent as floats, e.g. 100.0
+                None => number.as_f64().filter(|float| float.fract() == 0.0).map(|float| Value::from(float as i64)),
+            },
+            (FieldType::Int, Value::String(text)) => text.trim().parse::<i64>().ok().map(Value::from),
+            (FieldType::Float, Value::Number(number)) => number.as_f64().map(Value::from),
+            (FieldType::Float, Value::String(text)) => text.trim().parse::<f64>().ok().map(Value::from),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug,  PartialEq, Eq, Clone, Getters)]
+#[derive(Debug,  PartialEq, Eq, Clone, Getters, Default)]
 pub struct DataSchema {
     #[getset(get = "pub", get_mut = "pub")]
     columns: HashMap<String, Column>,
@@ -44,6 +76,31 @@ impl DataSchema {
         }
     }
 
+    /// Record typed after the schema, keeping only its columns and filling the missing ones with null.
+    /// Without columns the record is kept as received.
+    pub fn typed_row(&self, record: &Map<String, Value>) -> std::result::Result<Map<String, Value>, MistypedValue> {
+        if self.columns.is_empty() {
+            return Ok(record.clone());
+        }
+        let mut row = Map::new();
+        for (name, column) in &self.columns {
+            let value = record.get(name).unwrap_or(&Value::Null);
+            let typed = column.cast(value).ok_or_else(|| MistypedValue {
+                column: name.clone(),
+                value: value.clone(),
+            })?;
+            row.insert(name.clone(), typed);
+        }
+        return Ok(row);
+    }
+
+    /// Column names in alphabetical order
+    pub fn column_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.columns.keys().cloned().collect();
+        names.sort();
+        return names;
+    }
+
     pub fn insert_columns(&mut self, columns: &Vec<Column>) -> &mut Self {
         for col in columns {
             let key = col.name().to_string();
@@ -65,15 +122,6 @@ impl DataSchema {
     }
 }
 
-impl Default for DataSchema {
-    fn default() -> Self {
-        Self {
-            columns: HashMap::new(),
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
-    use super::*;
 }
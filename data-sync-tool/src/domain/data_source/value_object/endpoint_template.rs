@@ -0,0 +1,171 @@
+// Endpoint Template Value Object
+// Endpoint path with named placeholders such as `/stocks/{symbol}/bars/{interval}`, bound from the params of a task
+
+use serde_json::{Map, Value};
+use url::{ParseError, Url};
+
+use crate::domain::synchronization::custom_errors::TaskCreationError;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EndpointTemplate {
+    segments: Vec<Vec<Part>>,
+}
+
+impl EndpointTemplate {
+    /// Parse `/`-separated segments made of letters, digits, `-_.~` and `{placeholder}`s, None when malformed
+    pub fn parse(template: &str) -> Option<Self> {
+        let template = template.strip_prefix('/').unwrap_or(template);
+        let mut segments = Vec::new();
+        for segment in template.split('/') {
+            let mut parts = Vec::new();
+            let mut rest = segment;
+            while !rest.is_empty() {
+                if let Some(placeholder) = rest.strip_prefix('{') {
+                    let (name, after) = placeholder.split_once('}')?;
+                    let is_identifier = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                    if !is_identifier {
+                        return None;
+                    }
+                    parts.push(Part::Placeholder(name.to_string()));
+                    rest = after;
+                    continue;
+                }
+                let literal_end = rest.find('{').unwrap_or(rest.len());
+                let literal = &rest[..literal_end];
+                if !literal.chars().all(|c| c.is_ascii_alphanumeric() || "-_.~".contains(c)) {
+                    return None;
+                }
+                parts.push(Part::Literal(literal.to_string()));
+                rest = &rest[literal_end..];
+            }
+            if parts.is_empty() {
+                return None;
+            }
+            segments.push(parts);
+        }
+        return Some(Self { segments });
+    }
+
+    pub fn placeholders(&self) -> Vec<&str> {
+        self.segments
+            .iter()
+            .flatten()
+            .filter_map(|part| match part {
+                Part::Placeholder(name) => Some(name.as_str()),
+                Part::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    /// Path segments with the placeholders substituted, not yet percent-encoded,
+    /// and the params left to send as query arguments or body
+    pub fn bind(&self, params: &Value) -> Result<(Vec<String>, Value), TaskCreationError> {
+        let mut remaining = match params {
+            Value::Object(params) => params.clone(),
+            _ => Map::new(),
+        };
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for parts in &self.segments {
+            let mut segment = String::new();
+            for part in parts {
+                match part {
+                    Part::Literal(literal) => segment.push_str(literal),
+                    Part::Placeholder(name) => {
+                        match params.get(name) {
+                            None | Some(Value::Null) => return Err(TaskCreationError::MissingParam(name.clone())),
+                            Some(Value::String(value)) if !value.is_empty() => segment.push_str(value),
+                            Some(value @ (Value::Number(_) | Value::Bool(_))) => segment.push_str(&value.to_string()),
+                            // an empty segment would change the path, a collection has no single segment
+                            Some(_) => return Err(TaskCreationError::InvalidParamValue(name.clone())),
+                        }
+                        remaining.remove(name);
+                    }
+                }
+            }
+            segments.push(segment);
+        }
+        return Ok((segments, Value::Object(remaining)));
+    }
+}
+
+/// Append path segments to the path of a base url, percent-encoding each of them
+pub fn append_segments(base: &Url, segments: &[String]) -> Result<Url, TaskCreationError> {
+    let mut url = base.clone();
+    url.path_segments_mut()
+        .map_err(|_| TaskCreationError::UrlParseError(ParseError::RelativeUrlWithCannotBeABaseBase))?
+        .pop_if_empty()
+        .extend(segments);
+    return Ok(url);
+}
+
+/// Url of an absolute endpoint whose path may hold placeholders, bound from the params, and the params left to send.
+/// An endpoint without placeholders is taken as it is.
+pub fn bind_url(endpoint: &str, params: Option<&Value>) -> Result<(Url, Option<Value>), TaskCreationError> {
+    let url = Url::parse(endpoint)?;
+    // the raw path, the url has percent-encoded the braces of the placeholders
+    let after_scheme = endpoint.split_once("://").map_or(endpoint, |(_, rest)| rest);
+    let path = after_scheme.find('/').map_or("", |start| &after_scheme[start..]);
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    if !path.contains('{') {
+        return Ok((url, params.cloned()));
+    }
+    let template =
+        EndpointTemplate::parse(path).ok_or_else(|| TaskCreationError::InvalidEndpointTemplate(endpoint.to_string()))?;
+    let (segments, remaining) = template.bind(params.unwrap_or(&Value::Null))?;
+    let mut base = url.clone();
+    base.set_path("/");
+    let mut bound = append_segments(&base, &segments)?;
+    bound.set_query(url.query());
+    return Ok((bound, params.map(|_| remaining)));
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn it_should_parse_placeholders_and_versioned_paths() {
+        let template = EndpointTemplate::parse("/stocks/{symbol}/bars/{interval}.json").unwrap();
+        assert_eq!(template.placeholders(), vec!["symbol", "interval"]);
+        assert!(EndpointTemplate::parse("/v2/daily").unwrap().placeholders().is_empty());
+
+        assert!(EndpointTemplate::parse("/stocks/{symbol").is_none());
+        assert!(EndpointTemplate::parse("/stocks/{1st}").is_none());
+        assert!(EndpointTemplate::parse("/stocks//bars").is_none());
+        assert!(EndpointTemplate::parse("/stocks?symbol=AAPL").is_none());
+    }
+
+    #[test]
+    fn it_should_bind_and_percent_encode_path_params() {
+        let template = EndpointTemplate::parse("/stocks/{symbol}/bars/{interval}").unwrap();
+        let params = json!({"symbol": "BRK/B", "interval": 5, "limit": 100});
+
+        let (segments, remaining) = template.bind(&params).unwrap();
+        assert_eq!(segments, vec!["stocks", "BRK/B", "bars", "5"]);
+        assert_eq!(remaining, json!({"limit": 100}));
+        let base = Url::parse("https://data.example.com/api/").unwrap();
+        let url = append_segments(&base, &segments).unwrap();
+        assert_eq!(url.as_str(), "https://data.example.com/api/stocks/BRK%2FB/bars/5");
+        let (url, remaining) = bind_url("https://data.example.com/api/stocks/{symbol}/bars/{interval}?adjusted=true", Some(&params)).unwrap();
+        assert_eq!(url.as_str(), "https://data.example.com/api/stocks/BRK%2FB/bars/5?adjusted=true");
+        assert_eq!(remaining, Some(json!({"limit": 100})));
+
+        let unbound = bind_url("https://data.example.com/stocks/{symbol}", None).unwrap_err();
+        assert!(matches!(unbound, TaskCreationError::MissingParam(name) if name == "symbol"));
+        let missing = template.bind(&json!({"symbol": "AAPL"})).unwrap_err();
+        assert!(matches!(missing, TaskCreationError::MissingParam(name) if name == "interval"));
+        for invalid in [json!(""), json!(["1d", "5m"]), json!({"minutes": 5})] {
+            let error = template.bind(&json!({"symbol": "AAPL", "interval": invalid})).unwrap_err();
+            assert!(matches!(error, TaskCreationError::InvalidParamValue(name) if name == "interval"));
+        }
+    }
+}
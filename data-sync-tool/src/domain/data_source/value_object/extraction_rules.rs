@@ -0,0 +1,350 @@
+// Extraction Rules Value Object
+// Where a vendor nests the records of a dataset in its responses and how each record maps to the columns
+
+use std::{collections::HashMap, error, fmt};
+
+use getset::{Getters, Setters};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::data_schema::{DataSchema, MistypedValue};
+use crate::domain::synchronization::{
+    custom_errors::RequestError,
+    value_objects::task_spec::{Pagination, PaginationMode},
+};
+
+#[derive(Debug, Clone)]
+pub struct InvalidJsonPath(String);
+impl fmt::Display for InvalidJsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid JSON path '{}'!", self.0)
+    }
+}
+impl error::Error for InvalidJsonPath {}
+
+/// Why the rows of a response could not be extracted
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ExtractionError {
+    /// No record array at the records path
+    NoRecords(String),
+    /// Records are kept as received without columns nor fields, they should be objects then
+    RecordNotAnObject,
+    Mistyped(MistypedValue),
+}
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractionError::NoRecords(path) => write!(f, "No record array at '{}'!", path),
+            ExtractionError::RecordNotAnObject => write!(f, "Records should be objects!"),
+            ExtractionError::Mistyped(mistyped) => mistyped.fmt(f),
+        }
+    }
+}
+impl error::Error for ExtractionError {}
+
+impl From<MistypedValue> for ExtractionError {
+    fn from(mistyped: MistypedValue) -> ExtractionError {
+        ExtractionError::Mistyped(mistyped)
+    }
+}
+
+impl From<ExtractionError> for RequestError {
+    fn from(err: ExtractionError) -> RequestError {
+        RequestError::InvalidResponse(err.to_string())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// JSONPath subset such as `$.data.items`, `dataset.data`, `results[*]` or `[0]`: keys, indexes and wildcards
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct JsonPath {
+    path: String,
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, InvalidJsonPath> {
+        let invalid = || InvalidJsonPath(path.to_string());
+        let mut segments = Vec::new();
+        let mut rest = path.trim();
+        rest = rest.strip_prefix('$').unwrap_or(rest);
+        while !rest.is_empty() {
+            if let Some(bracketed) = rest.strip_prefix('[') {
+                let (inside, after) = bracketed.split_once(']').ok_or_else(invalid)?;
+                let inside = inside.trim();
+                let segment = match inside {
+                    "*" => Segment::Wildcard,
+                    _ if inside.starts_with('\'') && inside.ends_with('\'') && inside.len() >= 2 => {
+                        Segment::Key(inside[1..inside.len() - 1].to_string())
+                    }
+                    _ => Segment::Index(inside.parse().map_err(|_| invalid())?),
+                };
+                segments.push(segment);
+                rest = after;
+                continue;
+            }
+            let key_start = rest.strip_prefix('.').unwrap_or(rest);
+            let key_end = key_start.find(['.', '[']).unwrap_or(key_start.len());
+            let key = &key_start[..key_end];
+            if key.is_empty() {
+                return Err(invalid());
+            }
+            segments.push(match key {
+                "*" => Segment::Wildcard,
+                _ => Segment::Key(key.to_string()),
+            });
+            rest = &key_start[key_end..];
+        }
+        return Ok(Self { path: path.to_string(), segments });
+    }
+
+    /// Path of a plain key at the top of a record
+    fn of_key(key: &str) -> Self {
+        Self {
+            path: key.to_string(),
+            segments: vec![Segment::Key(key.to_string())],
+        }
+    }
+
+    /// Every value the path leads to, in document order
+    pub fn select<'a>(&self, value: &'a Value) -> Vec<&'a Value> {
+        let mut selected = vec![value];
+        for segment in &self.segments {
+            selected = selected
+                .into_iter()
+                .flat_map(|value| -> Vec<&Value> {
+                    match (segment, value) {
+                        (Segment::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+                        (Segment::Index(index), Value::Array(values)) => values.get(*index).into_iter().collect(),
+                        (Segment::Wildcard, Value::Array(values)) => values.iter().collect(),
+                        (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+                        _ => vec![],
+                    }
+                })
+                .collect();
+        }
+        return selected;
+    }
+
+    /// First value the path leads to
+    pub fn first<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        return self.select(value).into_iter().next();
+    }
+
+    fn ends_with_wildcard(&self) -> bool {
+        return self.segments.last() == Some(&Segment::Wildcard);
+    }
+
+    /// Equivalent JSON pointer, None when the path has a wildcard before its end
+    pub fn to_pointer(&self) -> Option<String> {
+        let mut pointer = String::new();
+        for (position, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) => pointer.push_str(&format!("/{}", key.replace('~', "~0").replace('/', "~1"))),
+                Segment::Index(index) => pointer.push_str(&format!("/{}", index)),
+                // the records of `results[*]` are the array at `/results`
+                Segment::Wildcard if position + 1 == self.segments.len() => {}
+                Segment::Wildcard => return None,
+            }
+        }
+        return Some(pointer);
+    }
+}
+
+impl TryFrom<String> for JsonPath {
+    type Error = InvalidJsonPath;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        return Self::parse(&path);
+    }
+}
+
+impl From<JsonPath> for String {
+    fn from(path: JsonPath) -> String {
+        return path.path;
+    }
+}
+
+/// Rules a dataset declares to turn a raw response into rows of its schema
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters, Serialize, Deserialize)]
+#[getset(get = "pub", set = "pub")]
+pub struct ExtractionRules {
+    /// The record array, or the records themselves when the path ends with a wildcard
+    records: JsonPath,
+    /// Path of a column inside a record, columns without one are read at the key of their name
+    fields: HashMap<String, JsonPath>,
+    /// Number of records of the whole result, pages are requested until that many came back
+    total_count: Option<JsonPath>,
+    /// Token of the next page in a response, sent back at `page_token_key`, a JSON pointer into the payload
+    next_page_token: Option<JsonPath>,
+    page_token_key: Option<String>,
+}
+
+impl ExtractionRules {
+    pub fn new(records: &str) -> Result<Self, InvalidJsonPath> {
+        Ok(Self {
+            records: JsonPath::parse(records)?,
+            fields: HashMap::new(),
+            total_count: None,
+            next_page_token: None,
+            page_token_key: None,
+        })
+    }
+
+    pub fn with_field(mut self, column: &str, path: &str) -> Result<Self, InvalidJsonPath> {
+        self.fields.insert(column.to_string(), JsonPath::parse(path)?);
+        return Ok(self);
+    }
+
+    pub fn with_total_count(mut self, path: &str) -> Result<Self, InvalidJsonPath> {
+        self.total_count = Some(JsonPath::parse(path)?);
+        return Ok(self);
+    }
+
+    pub fn with_next_page_token(mut self, path: &str, page_token_key: &str) -> Result<Self, InvalidJsonPath> {
+        self.next_page_token = Some(JsonPath::parse(path)?);
+        self.page_token_key = Some(page_token_key.to_string());
+        return Ok(self);
+    }
+
+    /// Records of a response, each mapped to the columns of the schema and typed after them.
+    /// Without columns nor fields the records should be objects and are kept as received.
+    pub fn extract_rows(&self, schema: &DataSchema, response: &Value) -> Result<Vec<Map<String, Value>>, ExtractionError> {
+        let selected = self.records.select(response);
+        let records: Vec<&Value> = match self.records.ends_with_wildcard() {
+            true => selected,
+            false => match selected.as_slice() {
+                [Value::Array(records)] => records.iter().collect(),
+                _ => return Err(ExtractionError::NoRecords(self.records.path.clone())),
+            },
+        };
+
+        let mut columns = schema.column_names();
+        columns.extend(self.fields.keys().filter(|name| !schema.columns().contains_key(*name)).cloned());
+        let mut rows = Vec::with_capacity(records.len());
+        for record in records {
+            let mapped = match (columns.is_empty(), record) {
+                (true, Value::Object(record)) => record.clone(),
+                (true, _) => return Err(ExtractionError::RecordNotAnObject),
+                (false, _) => columns
+                    .iter()
+                    .map(|name| {
+                        let value = match self.fields.get(name) {
+                            Some(path) => path.first(record),
+                            None => JsonPath::of_key(name).first(record),
+                        };
+                        (name.clone(), value.cloned().unwrap_or(Value::Null))
+                    })
+                    .collect(),
+            };
+            rows.push(schema.typed_row(&mapped)?);
+        }
+        return Ok(rows);
+    }
+
+    /// Pagination of the requests of the dataset: a cursor on the next page token when the rules declare one,
+    /// otherwise the given pagination, told where the total count is. None when a path has a wildcard before its end.
+    pub fn pagination(&self, pagination: Option<Pagination>) -> Option<Pagination> {
+        let records_pointer = self.records.to_pointer()?;
+        let mut pagination = match (&self.next_page_token, &self.page_token_key) {
+            (Some(next_page_token), Some(page_token_key)) => {
+                let cursor = PaginationMode::Cursor {
+                    cursor_key: page_token_key.clone(),
+                    next_cursor_pointer: next_page_token.to_pointer()?,
+                };
+                Pagination::new(cursor, &records_pointer)
+            }
+            _ => {
+                let mut pagination = pagination?;
+                pagination.set_records_pointer(records_pointer);
+                pagination
+            }
+        };
+        if let Some(total_count) = &self.total_count {
+            pagination.set_total_count_pointer(Some(total_count.to_pointer()?));
+        }
+        return Some(pagination);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::{
+        data_source::value_object::data_schema::Column,
+        synchronization::value_objects::task_spec::NextPages,
+    };
+
+    #[test]
+    fn it_should_select_with_keys_indexes_and_wildcards() {
+        let response = json!({"results": [{"id": 1, "tags": ["a", "b"]}, {"id": 2, "tags": ["c"]}]});
+
+        assert_eq!(JsonPath::parse("$.results[*].id").unwrap().select(&response), vec![&json!(1), &json!(2)]);
+        assert_eq!(JsonPath::parse("results[1]['tags'][0]").unwrap().first(&response), Some(&json!("c")));
+        assert_eq!(JsonPath::parse("results.*.tags[*]").unwrap().select(&response).len(), 3);
+        assert!(JsonPath::parse("results[x]").is_err());
+        assert!(JsonPath::parse("results..id").is_err());
+    }
+
+    #[test]
+    fn it_should_extract_typed_rows_from_array_records() {
+        let schema = DataSchema::new(&vec![
+            Column::new("date", "str", "").unwrap(),
+            Column::new("close", "float", "").unwrap(),
+        ]);
+        let rules = ExtractionRules::new("dataset.data")
+            .and_then(|rules| rules.with_field("date", "[0]"))
+            .and_then(|rules| rules.with_field("close", "[1]"))
+            .unwrap();
+        let response = json!({"dataset": {"column_names": ["Date", "Close"], "data": [["2023-06-21", "183.96"]]}});
+
+        let rows = rules.extract_rows(&schema, &response).unwrap();
+        assert_eq!(Value::Object(rows[0].clone()), json!({"date": "2023-06-21", "close": 183.96}));
+        assert!(rules.extract_rows(&schema, &json!({"dataset": {}})).is_err());
+    }
+
+    #[test]
+    fn it_should_page_on_the_next_page_token() {
+        let rules = ExtractionRules::new("results[*]")
+            .and_then(|rules| rules.with_next_page_token("meta.next", "/page_token"))
+            .unwrap();
+        let response = json!({"results": [{"id": 1}, {"id": 2}], "meta": {"next": "p2"}});
+
+        let rows = rules.extract_rows(&DataSchema::default(), &response).unwrap();
+        assert_eq!(Value::Object(rows[1].clone()), json!({"id": 2}));
+
+        let pagination = rules.pagination(None).unwrap();
+        assert_eq!(*pagination.records_pointer(), "/results");
+        let NextPages::Continue(next_pages) = pagination.next_pages(&json!({}), &response, 0) else {
+            panic!("A response with a next page token should be followed by the next page");
+        };
+        assert_eq!(next_pages, vec![json!({"page_token": "p2"})]);
+    }
+
+    #[test]
+    fn it_should_page_until_the_total_count_came_back() {
+        let rules = ExtractionRules::new("results").and_then(|rules| rules.with_total_count("meta.total")).unwrap();
+        let pagination = rules.pagination(Some(Pagination::offset(""))).unwrap();
+        let page = pagination.first_page(None, 100);
+        let records = |count: usize| vec![json!({"id": 1}); count];
+
+        // the remote serves fewer records per page than asked for
+        let response = json!({"results": records(50), "meta": {"total": "120"}});
+        let NextPages::Continue(next_pages) = pagination.next_pages(&page, &response, 100) else {
+            panic!("Records short of the total count should be followed by the next page");
+        };
+        assert_eq!(next_pages[0]["offset"], 50);
+        let response = json!({"results": records(70), "meta": {"total": "120"}});
+        assert_eq!(pagination.next_pages(&next_pages[0], &response, 100), NextPages::Done);
+        assert!(rules.extract_rows(&DataSchema::default(), &json!({"results": [1]})).is_err());
+    }
+}
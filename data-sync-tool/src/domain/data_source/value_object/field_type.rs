@@ -3,14 +3,13 @@
 
 use std::{error, fmt};
 
-use fake::{Dummy};
 use lazy_static::lazy_static;
 use regex::Regex;
 
 lazy_static! {
-    static ref STRING_ARG_TYPE_PATTERN: Regex = Regex::new("^str|string|String$").unwrap();
-    static ref INT_ARG_TYPE_PATTERN: Regex = Regex::new("^int|Integer$").unwrap();
-    static ref FLOAT_ARG_TYPE_PATTERN: Regex = Regex::new("^int|Integer$").unwrap();
+    static ref STRING_ARG_TYPE_PATTERN: Regex = Regex::new("^(str|string|String)$").unwrap();
+    static ref INT_ARG_TYPE_PATTERN: Regex = Regex::new("^(int|Integer)$").unwrap();
+    static ref FLOAT_ARG_TYPE_PATTERN: Regex = Regex::new("^(float|Float|double)$").unwrap();
 }
 
 #[derive(Debug, Clone)]
@@ -45,3 +44,18 @@ impl TryFrom<String> for FieldType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_only_accept_whole_type_names() {
+        assert_eq!(FieldType::try_from("double".to_string()).unwrap(), FieldType::Float);
+        assert_eq!(FieldType::try_from("Integer".to_string()).unwrap(), FieldType::Int);
+        assert_eq!(FieldType::try_from("str".to_string()).unwrap(), FieldType::String);
+        assert!(FieldType::try_from("floating".to_string()).is_err());
+        assert!(FieldType::try_from("mint".to_string()).is_err());
+        assert!(FieldType::try_from("stringify".to_string()).is_err());
+    }
+}
@@ -1,7 +1,6 @@
 //! Local Storage Value Object
 //! 
 use getset::{Getters, Setters};
-use fake::{ Fake};
 
 
 #[derive(Getters, Setters, Debug, Default,  Clone, Eq, PartialEq)]
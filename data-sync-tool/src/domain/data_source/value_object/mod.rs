@@ -1,4 +1,9 @@
 pub mod api_param;
 pub mod data_schema;
 pub mod field_type;
-pub mod local_storage;
\ No newline at end of file
+pub mod local_storage;
+pub mod watermark;
+pub mod api_credential;
+pub mod rest_config;
+pub mod extraction_rules;
+pub mod endpoint_template;
@@ -0,0 +1,54 @@
+//! REST Source Configuration
+//! How a generic HTTP data source is requested, so adding a vendor takes configuration rather than code
+
+use getset::{Getters, Setters};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::endpoint_template::append_segments;
+use crate::domain::synchronization::{
+    custom_errors::TaskCreationError,
+    value_objects::task_spec::{AuthPlacement, RequestMethod},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters, Serialize, Deserialize)]
+#[getset(get = "pub", set = "pub")]
+pub struct RestSourceConfig {
+    base_url: Url,
+    request_method: RequestMethod,
+    auth_placement: Option<AuthPlacement>,
+    /// JSON pointer to the array of records in a response, empty when the response itself is the array
+    records_pointer: String,
+}
+
+impl RestSourceConfig {
+    /// GET requests without a key, answered with an array of records
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            base_url,
+            request_method: RequestMethod::Get,
+            auth_placement: None,
+            records_pointer: String::new(),
+        }
+    }
+
+    /// Url of a dataset, the segments of its bound endpoint appended to the path of the base url
+    pub fn endpoint_url(&self, segments: &[String]) -> Result<Url, TaskCreationError> {
+        return append_segments(&self.base_url, segments);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_append_the_endpoint_to_the_base_path() {
+        let config = RestSourceConfig::new(Url::parse("https://data.example.com/api/v3/").unwrap());
+        let segments = vec!["daily".to_string()];
+        assert_eq!(config.endpoint_url(&segments).unwrap().as_str(), "https://data.example.com/api/v3/daily");
+
+        let config = RestSourceConfig::new(Url::parse("https://data.example.com").unwrap());
+        assert_eq!(config.endpoint_url(&segments).unwrap().as_str(), "https://data.example.com/daily");
+    }
+}
@@ -0,0 +1,93 @@
+//! Watermark Value Object
+//! Latest date of a dataset that is already in storage, such as the max `trade_date` or `ann_date`
+
+use chrono::NaiveDate;
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::common::tabular::{parse_date, records};
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Watermark {
+    column: String,
+    value: NaiveDate,
+}
+
+impl Watermark {
+    pub fn new(column: &str, value: NaiveDate) -> Self {
+        Self {
+            column: column.to_string(),
+            value,
+        }
+    }
+
+    /// Highest date of the column among the rows, either a `fields`/`items` table or a list of records.
+    /// None when no row has a date in that column.
+    pub fn from_rows(column: &str, rows: &Value) -> Option<Self> {
+        return records(rows)?
+            .iter()
+            .filter_map(|record| record.get(column))
+            .filter_map(parse_date)
+            .max()
+            .map(|value| Self::new(column, value));
+    }
+
+    /// Move forward to a later watermark of the same column, an earlier one is ignored
+    pub fn advance(&mut self, other: &Watermark) -> &mut Self {
+        if other.column == self.column && other.value > self.value {
+            self.value = other.value;
+        }
+        return self;
+    }
+
+    /// Whether a request payload asks for data past the watermark.
+    /// The payload's own watermark column is checked first, then the end of its date window,
+    /// each at the top of the payload or among its `params` as Tushare sends them.
+    /// Payloads without either are always requested.
+    pub fn is_beyond(&self, payload: &Value) -> bool {
+        let date_of = |key: &str| {
+            payload
+                .get(key)
+                .or_else(|| payload.get("params").and_then(|params| params.get(key)))
+                .and_then(parse_date)
+        };
+        let requested = date_of(&self.column).or_else(|| date_of("end_date"));
+        match requested {
+            Some(requested) => return requested > self.value,
+            None => return true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn it_should_take_the_latest_date_of_the_written_rows() {
+        let rows = json!({"fields": ["ts_code", "trade_date"], "items": [["000001.SZ", "20230620"], ["000001.SZ", "20230621"]]});
+        let mut watermark = Watermark::new("trade_date", NaiveDate::from_ymd_opt(2023, 6, 1).unwrap());
+
+        watermark.advance(&Watermark::from_rows("trade_date", &rows).unwrap());
+        watermark.advance(&Watermark::new("trade_date", NaiveDate::from_ymd_opt(2023, 5, 1).unwrap()));
+
+        assert_eq!(*watermark.value(), NaiveDate::from_ymd_opt(2023, 6, 21).unwrap());
+        assert_eq!(Watermark::from_rows("ann_date", &rows), None);
+    }
+
+    #[test]
+    fn it_should_only_request_payloads_past_the_watermark() {
+        let watermark = Watermark::new("trade_date", NaiveDate::from_ymd_opt(2023, 6, 21).unwrap());
+
+        assert!(!watermark.is_beyond(&json!({"trade_date": "20230621"})));
+        assert!(watermark.is_beyond(&json!({"trade_date": "20230622"})));
+        assert!(watermark.is_beyond(&json!({"start_date": "20230601", "end_date": "20230630"})));
+        assert!(watermark.is_beyond(&json!({"ts_code": "000001.SZ"})));
+        assert!(!watermark.is_beyond(&json!({"api_name": "daily", "params": {"trade_date": "20230621"}})));
+        assert!(watermark.is_beyond(&json!({"api_name": "daily", "params": {"end_date": "20230630"}})));
+    }
+}
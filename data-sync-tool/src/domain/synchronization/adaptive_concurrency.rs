@@ -0,0 +1,144 @@
+// Adaptive Concurrency
+// AIMD limit on the requests in flight to a data source, raised while the remote is healthy and cut when it pushes back
+
+use std::time::Duration;
+
+use super::{sync_task::FailureReason, value_objects::sync_config::AdaptiveConcurrency};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConcurrencyLimit {
+    limit: u32,
+    in_flight: u32,
+    // bumped on every cut, requests sent before a cut cannot cut the limit again
+    generation: u64,
+    sampled: u32,
+    failed: u32,
+    total_latency: Duration,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(policy: &AdaptiveConcurrency, cap: u32) -> Self {
+        Self {
+            limit: (*policy.initial_concurrency()).clamp(1, cap.max(1)),
+            in_flight: 0,
+            generation: 0,
+            sampled: 0,
+            failed: 0,
+            total_latency: Duration::ZERO,
+        }
+    }
+
+    /// Current number of requests allowed in flight
+    pub fn limit(&self) -> u32 {
+        return self.limit;
+    }
+
+    pub fn in_flight(&self) -> u32 {
+        return self.in_flight;
+    }
+
+    /// Take a slot when the limit allows one more request, returns the generation the request is sent in
+    pub fn try_acquire(&mut self, cap: u32) -> Option<u64> {
+        self.limit = self.limit.clamp(1, cap.max(1));
+        if self.in_flight >= self.limit {
+            return None;
+        }
+        self.in_flight += 1;
+        return Some(self.generation);
+    }
+
+    /// Give the slot back without a response to learn from
+    pub fn abandon(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+    }
+
+    /// Give the slot back and adapt the limit to how the request went
+    pub fn release(
+        &mut self,
+        policy: &AdaptiveConcurrency,
+        cap: u32,
+        generation: u64,
+        latency: Duration,
+        failure: Option<FailureReason>,
+    ) {
+        self.abandon();
+        match failure {
+            Some(FailureReason::TooFrequent | FailureReason::Timeout) => {
+                if generation == self.generation {
+                    let decreased = self.limit as u64 * *policy.decrease_percent() as u64 / 100;
+                    self.limit = (decreased as u32).max(1);
+                    self.generation += 1;
+                    self.reset_sample();
+                }
+                return;
+            }
+            Some(_) => self.failed += 1,
+            None => {}
+        }
+
+        self.sampled += 1;
+        self.total_latency += latency;
+        if self.sampled < (*policy.sample_size()).max(1) {
+            return;
+        }
+        let average_latency = self.total_latency / self.sampled;
+        let error_percent = self.failed * 100 / self.sampled;
+        if average_latency <= *policy.max_latency() && error_percent <= *policy.max_error_percent() {
+            self.limit = (self.limit + 1).min(cap.max(1));
+        }
+        self.reset_sample();
+    }
+
+    fn reset_sample(&mut self) {
+        self.sampled = 0;
+        self.failed = 0;
+        self.total_latency = Duration::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn policy() -> AdaptiveConcurrency {
+        let mut policy = AdaptiveConcurrency::default();
+        policy.set_sample_size(2).set_max_latency(Duration::from_millis(100));
+        return policy;
+    }
+
+    #[test]
+    fn it_should_raise_the_limit_while_healthy_up_to_the_cap() {
+        let policy = policy();
+        let mut limit = ConcurrencyLimit::new(&policy, 3);
+
+        for _ in 0..10 {
+            let generation = limit.try_acquire(3).unwrap();
+            limit.release(&policy, 3, generation, Duration::from_millis(10), None);
+        }
+        assert_eq!(limit.limit(), 3);
+
+        // slow responses keep the limit where it is
+        let mut limit = ConcurrencyLimit::new(&policy, 3);
+        for _ in 0..4 {
+            let generation = limit.try_acquire(3).unwrap();
+            limit.release(&policy, 3, generation, Duration::from_millis(500), None);
+        }
+        assert_eq!(limit.limit(), 1);
+    }
+
+    #[test]
+    fn it_should_halve_the_limit_once_per_burst_of_throttled_requests() {
+        let policy = policy();
+        let mut limit = ConcurrencyLimit::new(&policy, 8);
+        limit.limit = 8;
+        let generations: Vec<u64> = (0..8).map(|_| limit.try_acquire(8).unwrap()).collect();
+        assert_eq!(limit.try_acquire(8), None);
+
+        for generation in generations {
+            limit.release(&policy, 8, generation, Duration::ZERO, Some(FailureReason::TooFrequent));
+        }
+
+        assert_eq!(limit.limit(), 4);
+        assert_eq!(limit.in_flight(), 0);
+    }
+}
@@ -0,0 +1,133 @@
+// Synchronization Checkpoint
+// Records how far the tasks of a plan got, so an interrupted plan resumes instead of starting over.
+// Tasks are known by the fingerprint of their request, which survives generating the tasks again.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::prelude::*;
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::sync_task::{FailureReason, SyncStatus, SyncTask};
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct FailedTask {
+    reason: FailureReason,
+    message: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct SyncCheckpoint {
+    sync_plan_id: Uuid,
+    finished: BTreeSet<String>,
+    failed: BTreeMap<String, FailedTask>,
+    pending: BTreeSet<String>,
+    update_time: DateTime<Local>,
+}
+
+impl SyncCheckpoint {
+    /// Take a checkpoint of the plan's tasks, everything neither finished nor failed is pending
+    pub fn from_tasks(sync_plan_id: Uuid, tasks: &[SyncTask]) -> Self {
+        let mut checkpoint = Self {
+            sync_plan_id,
+            finished: BTreeSet::new(),
+            failed: BTreeMap::new(),
+            pending: BTreeSet::new(),
+            update_time: Local::now(),
+        };
+        for task in tasks.iter().filter(|task| *task.sync_plan_id() == Some(sync_plan_id)) {
+            let fingerprint = task.spec().fingerprint();
+            match (task.status(), task.failure_reason()) {
+                (SyncStatus::Finished, _) => {
+                    checkpoint.finished.insert(fingerprint);
+                }
+                (SyncStatus::Failed, Some(reason)) => {
+                    let failed_task = FailedTask {
+                        reason: *reason,
+                        message: task.result_message().clone().unwrap_or_default(),
+                    };
+                    checkpoint.failed.insert(fingerprint, failed_task);
+                }
+                _ => {
+                    checkpoint.pending.insert(fingerprint);
+                }
+            }
+        }
+        return checkpoint;
+    }
+
+    /// A plan is done when no task is left to run again
+    pub fn is_complete(&self) -> bool {
+        return self.pending.is_empty() && self.failed.keys().all(|fingerprint| !self.should_retry(fingerprint));
+    }
+
+    /// Failed tasks are run again on resume, except those the remote rejected for their arguments
+    pub fn should_retry(&self, fingerprint: &str) -> bool {
+        match self.failed.get(fingerprint) {
+            Some(failed_task) => failed_task.reason != FailureReason::InvalidArgument,
+            None => false,
+        }
+    }
+
+    /// Restore the outcome of tasks that do not need to run again, returns how many were restored
+    pub fn restore(&self, tasks: &mut [SyncTask]) -> usize {
+        let mut restored = 0;
+        for task in tasks.iter_mut().filter(|task| *task.sync_plan_id() == Some(self.sync_plan_id)) {
+            let fingerprint = task.spec().fingerprint();
+            if self.finished.contains(&fingerprint) {
+                task.finished();
+                restored += 1;
+            } else if let Some(failed_task) = self.failed.get(&fingerprint) {
+                if !self.should_retry(&fingerprint) {
+                    task.fail(failed_task.reason, &failed_task.message);
+                    restored += 1;
+                }
+            }
+        }
+        return restored;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::synchronization::value_objects::task_spec::TaskSpec;
+
+    /// Tasks of a plan as its generator builds them, each run gets new task ids
+    fn plan_tasks(sync_plan_id: Uuid, count: usize) -> Vec<SyncTask> {
+        (0..count)
+            .map(|position| {
+                let mut spec = TaskSpec::default();
+                spec.set_payload(Some(json!({"ts_code": format!("00000{}.SZ", position)})));
+                let mut task = SyncTask::default();
+                task.set_sync_plan_id(Some(sync_plan_id)).set_spec(spec);
+                task
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_should_resume_only_unfinished_and_retryable_tasks() {
+        let plan_id = Uuid::new_v4();
+        let mut tasks = plan_tasks(plan_id, 4);
+        tasks[0].finished();
+        tasks[1].fail(FailureReason::InvalidArgument, "bad ts_code");
+        tasks[2].fail(FailureReason::DailyLimitExceeded, "quota used up");
+        let checkpoint = SyncCheckpoint::from_tasks(plan_id, &tasks);
+        assert!(!checkpoint.is_complete());
+
+        let mut restarted = plan_tasks(plan_id, 4);
+        let restored = checkpoint.restore(&mut restarted);
+
+        assert_eq!(restored, 2);
+        assert_eq!(*restarted[0].status(), SyncStatus::Finished);
+        assert_eq!(*restarted[1].status(), SyncStatus::Failed);
+        assert_eq!(*restarted[2].status(), SyncStatus::Created);
+        assert_eq!(*restarted[3].status(), SyncStatus::Created);
+    }
+}
@@ -0,0 +1,164 @@
+// Circuit Breaker
+// Stops requesting a data source that keeps failing and probes it again after a while
+
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use uuid::Uuid;
+
+use super::{events::sync_events::CircuitBreakerEvent, sync_task::FailureReason, value_objects::sync_config::CircuitBreakerPolicy};
+
+/// How long parked requests wait while a probe request is in flight
+pub const PROBE_WAIT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { successes: u32, probing: bool },
+}
+
+/// What a breaker let through, the single probe of a half open breaker must be given back when it is never sent
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Pass {
+    Request,
+    Probe,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CircuitBreaker {
+    datasource_id: Uuid,
+    state: CircuitState,
+}
+
+impl CircuitBreaker {
+    pub fn new(datasource_id: Uuid) -> Self {
+        Self {
+            datasource_id,
+            state: CircuitState::Closed { failures: 0 },
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        return self.state;
+    }
+
+    /// Only timeouts and errors of the remote tell that it is down, the other failures are about the request
+    pub fn is_outage(reason: FailureReason) -> bool {
+        return matches!(reason, FailureReason::Timeout | FailureReason::Other);
+    }
+
+    /// Whether a request may be sent now, or how long to wait before asking again.
+    /// An open breaker lets a single probe request through once its open duration is over.
+    pub fn try_pass(&mut self, now: Instant) -> (Result<Pass, Duration>, Option<CircuitBreakerEvent>) {
+        match self.state {
+            CircuitState::Closed { .. } => return (Ok(Pass::Request), None),
+            CircuitState::Open { until } if now < until => return (Err(until - now), None),
+            CircuitState::Open { .. } => {
+                self.state = CircuitState::HalfOpen { successes: 0, probing: true };
+                let event = CircuitBreakerEvent::HalfOpened {
+                    datasource_id: self.datasource_id,
+                };
+                return (Ok(Pass::Probe), Some(event));
+            }
+            CircuitState::HalfOpen { probing: true, .. } => return (Err(PROBE_WAIT), None),
+            CircuitState::HalfOpen { successes, probing: false } => {
+                self.state = CircuitState::HalfOpen { successes, probing: true };
+                return (Ok(Pass::Probe), None);
+            }
+        }
+    }
+
+    /// Give back the probe that was let through but never sent, only its holder may call this
+    pub fn abandon(&mut self) {
+        if let CircuitState::HalfOpen { successes, .. } = self.state {
+            self.state = CircuitState::HalfOpen { successes, probing: false };
+        }
+    }
+
+    /// Record the outcome of a request that was let through
+    pub fn record(&mut self, policy: &CircuitBreakerPolicy, failure: Option<FailureReason>, now: Instant) -> Option<CircuitBreakerEvent> {
+        let outage = failure.is_some_and(Self::is_outage);
+        match (self.state, outage) {
+            (CircuitState::Closed { failures }, true) => {
+                let failures = failures + 1;
+                if failures >= (*policy.failure_threshold()).max(1) {
+                    return Some(self.open(policy, failures, now));
+                }
+                self.state = CircuitState::Closed { failures };
+            }
+            (CircuitState::Closed { .. }, false) => {
+                if failure.is_none() {
+                    self.state = CircuitState::Closed { failures: 0 };
+                }
+            }
+            (CircuitState::HalfOpen { .. }, true) => return Some(self.open(policy, 1, now)),
+            (CircuitState::HalfOpen { successes, .. }, false) => {
+                let successes = successes + failure.is_none() as u32;
+                if successes >= (*policy.success_threshold()).max(1) {
+                    self.state = CircuitState::Closed { failures: 0 };
+                    return Some(CircuitBreakerEvent::Closed {
+                        datasource_id: self.datasource_id,
+                    });
+                }
+                self.state = CircuitState::HalfOpen { successes, probing: false };
+            }
+            // requests sent before the breaker opened do not change it
+            (CircuitState::Open { .. }, _) => {}
+        }
+        return None;
+    }
+
+    fn open(&mut self, policy: &CircuitBreakerPolicy, consecutive_failures: u32, now: Instant) -> CircuitBreakerEvent {
+        let open_duration = *policy.open_duration();
+        self.state = CircuitState::Open {
+            until: now + open_duration,
+        };
+        return CircuitBreakerEvent::Opened {
+            datasource_id: self.datasource_id,
+            consecutive_failures,
+            retry_at: Local::now() + chrono::Duration::from_std(open_duration).unwrap_or_else(|_| chrono::Duration::zero()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_open_after_failures_in_a_row_and_close_after_a_successful_probe() {
+        let mut policy = CircuitBreakerPolicy::default();
+        policy.set_failure_threshold(2).set_open_duration(Duration::from_secs(30));
+        let datasource_id = Uuid::new_v4();
+        let mut breaker = CircuitBreaker::new(datasource_id);
+        let start = Instant::now();
+
+        assert_eq!(breaker.record(&policy, Some(FailureReason::Timeout), start), None);
+        assert_eq!(breaker.record(&policy, Some(FailureReason::InvalidArgument), start), None);
+        let opened = breaker.record(&policy, Some(FailureReason::Other), start);
+        assert!(matches!(opened, Some(CircuitBreakerEvent::Opened { consecutive_failures: 2, .. })));
+        assert_eq!(breaker.try_pass(start + Duration::from_secs(10)).0, Err(Duration::from_secs(20)));
+
+        let later = start + Duration::from_secs(30);
+        assert_eq!(breaker.try_pass(later), (Ok(Pass::Probe), Some(CircuitBreakerEvent::HalfOpened { datasource_id })));
+        assert_eq!(breaker.try_pass(later).0, Err(PROBE_WAIT));
+        assert_eq!(breaker.record(&policy, None, later), Some(CircuitBreakerEvent::Closed { datasource_id }));
+        assert_eq!(breaker.state(), CircuitState::Closed { failures: 0 });
+    }
+
+    #[test]
+    fn it_should_open_again_when_the_probe_fails() {
+        let mut policy = CircuitBreakerPolicy::default();
+        policy.set_failure_threshold(1).set_open_duration(Duration::ZERO);
+        let mut breaker = CircuitBreaker::new(Uuid::new_v4());
+        let now = Instant::now();
+
+        breaker.record(&policy, Some(FailureReason::Timeout), now);
+        assert!(breaker.try_pass(now).0.is_ok());
+        let reopened = breaker.record(&policy, Some(FailureReason::Timeout), now);
+
+        assert!(matches!(reopened, Some(CircuitBreakerEvent::Opened { .. })));
+        assert!(matches!(breaker.state(), CircuitState::Open { .. }));
+    }
+}
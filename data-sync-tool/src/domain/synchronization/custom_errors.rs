@@ -1,7 +1,12 @@
 use std::error::{Error, self};
 use std::fmt;
+use std::time::Duration;
+use chrono::NaiveDate;
+use serde_json::Value;
 use url::ParseError;
-use derivative::Derivative;
+use uuid::Uuid;
+
+use super::sync_task::FailureReason;
 
 
 #[derive(Debug)]
@@ -11,6 +16,16 @@ pub enum TaskCreationError {
     // We will defer to the parse error implementation for their error.
     // Supplying extra info requires adding more data to the type.
     UrlParseError(ParseError),
+    /// A placeholder of the endpoint template or a required API param has no value in the params
+    MissingParam(String),
+    /// A param is neither a placeholder of the endpoint template nor a declared API param of the dataset
+    UnusedParam(String),
+    /// A placeholder of the endpoint template is bound to an empty string, an array or an object
+    InvalidParamValue(String),
+    InvalidEndpointTemplate(String),
+    /// The dataset of the plan, or the adapter of its data source, is not known to the task generator
+    UnknownDataset,
+    UnknownDataSource,
 }
 
 impl fmt::Display for TaskCreationError {
@@ -25,6 +40,18 @@ impl fmt::Display for TaskCreationError {
                 write!(f, "The provided string could not be parsed as a valid request method."),
             TaskCreationError::UrlParseError(..) =>
                 write!(f, "The provided string could not be parsed as an Url"),
+            TaskCreationError::MissingParam(ref name) =>
+                write!(f, "No value is given for the parameter {}", name),
+            TaskCreationError::UnusedParam(ref name) =>
+                write!(f, "The parameter {} is not used by the endpoint nor declared by the dataset", name),
+            TaskCreationError::InvalidParamValue(ref name) =>
+                write!(f, "The value of the parameter {} cannot be placed in the endpoint path", name),
+            TaskCreationError::InvalidEndpointTemplate(ref endpoint) =>
+                write!(f, "The endpoint {} is not a valid endpoint template", endpoint),
+            TaskCreationError::UnknownDataset =>
+                write!(f, "The dataset of the plan is not known"),
+            TaskCreationError::UnknownDataSource =>
+                write!(f, "No adapter is known for the data source of the plan"),
         }
     }
 }
@@ -38,6 +65,12 @@ impl error::Error for TaskCreationError {
             // cast to the trait object `&error::Error`. This works because the
             // underlying type already implements the `Error` trait.
             TaskCreationError::UrlParseError(ref e) => Some(e),
+            TaskCreationError::MissingParam(..)
+            | TaskCreationError::UnusedParam(..)
+            | TaskCreationError::InvalidParamValue(..)
+            | TaskCreationError::InvalidEndpointTemplate(..)
+            | TaskCreationError::UnknownDataset
+            | TaskCreationError::UnknownDataSource => None,
         }
     }
 }
@@ -84,4 +117,218 @@ impl fmt::Display for RepositoryError {
             RepositoryError::PermissionDenied => f.write_str("Permission denied"),
         }
     }
-}
\ No newline at end of file
+}
+/// Errors raised while sending a task's request to the remote data source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestError {
+    Timeout,
+    ConnectionFailed(String),
+    UnexpectedStatus(u16),
+    /// A non-2xx response with its JSON body, which often says more than the status, e.g. which limit was hit
+    ErrorResponse {
+        status: u16,
+        retry_after: Option<Duration>,
+        body: Value,
+    },
+    InvalidResponse(String),
+    // Errors reported by the remote itself, usually parsed from the response body
+    TooFrequent(Option<Duration>),
+    DailyLimitExceeded(String),
+    InvalidArgument(String),
+    /// The remote refused the request for another reason, e.g. a bad token or missing permissions
+    Rejected(String),
+}
+
+impl RequestError {
+    /// How long the remote asked us to wait before the next request, if it said so
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            RequestError::TooFrequent(retry_after) => *retry_after,
+            RequestError::ErrorResponse { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Classify the error so the retry policy knows how to handle it
+    pub fn failure_reason(&self) -> FailureReason {
+        match self {
+            RequestError::Timeout => FailureReason::Timeout,
+            RequestError::TooFrequent(..) => FailureReason::TooFrequent,
+            RequestError::DailyLimitExceeded(..) => FailureReason::DailyLimitExceeded,
+            RequestError::InvalidArgument(..) => FailureReason::InvalidArgument,
+            RequestError::UnexpectedStatus(status) | RequestError::ErrorResponse { status, .. } => match status {
+                429 => FailureReason::TooFrequent,
+                400 | 422 => FailureReason::InvalidArgument,
+                408 | 504 => FailureReason::Timeout,
+                _ => FailureReason::Other,
+            },
+            RequestError::ConnectionFailed(..)
+            | RequestError::InvalidResponse(..)
+            | RequestError::Rejected(..) => FailureReason::Other,
+        }
+    }
+}
+
+impl error::Error for RequestError {}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Timeout => f.write_str("Request timed out"),
+            RequestError::ConnectionFailed(reason) => write!(f, "Failed to reach the remote data source: {}", reason),
+            RequestError::UnexpectedStatus(code) => write!(f, "Remote data source responded with status {}", code),
+            RequestError::ErrorResponse { status, body, .. } => {
+                write!(f, "Remote data source responded with status {}: {}", status, body)
+            }
+            RequestError::InvalidResponse(reason) => write!(f, "Failed to parse the response: {}", reason),
+            RequestError::TooFrequent(_) => f.write_str("Requested too frequently"),
+            RequestError::DailyLimitExceeded(message) => write!(f, "Daily limit exceeded: {}", message),
+            RequestError::InvalidArgument(message) => write!(f, "Invalid request argument: {}", message),
+            RequestError::Rejected(message) => write!(f, "Remote data source rejected the request: {}", message),
+        }
+    }
+}
+
+/// Errors raised when a data source's request budget cannot be used
+#[derive(Debug)]
+pub enum QuotaError {
+    DailyLimitReached,
+    UsageNotPersisted(RepositoryError),
+}
+
+impl error::Error for QuotaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            QuotaError::DailyLimitReached => None,
+            QuotaError::UsageNotPersisted(ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QuotaError::DailyLimitReached => f.write_str("Daily request limit of the data source is reached"),
+            QuotaError::UsageNotPersisted(..) => f.write_str("Failed to record the request usage of the data source"),
+        }
+    }
+}
+
+impl From<RepositoryError> for QuotaError {
+    fn from(err: RepositoryError) -> QuotaError {
+        QuotaError::UsageNotPersisted(err)
+    }
+}
+
+/// Errors raised when a sync schedule cannot be built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    InvalidExpression(String),
+    UnknownTimeZone(String),
+}
+
+impl error::Error for ScheduleError {}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleError::InvalidExpression(expression) => write!(f, "Invalid cron expression: {}", expression),
+            ScheduleError::UnknownTimeZone(time_zone) => write!(f, "Unknown time zone: {}", time_zone),
+        }
+    }
+}
+
+/// Errors raised when a sync plan cannot be dispatched
+#[derive(Debug)]
+pub enum SchedulingError {
+    PlanAlreadyRunning(Uuid),
+    TaskCreationFailed(TaskCreationError),
+    PlanNotLoaded(RepositoryError),
+    PlanNotSaved(RepositoryError),
+    DependencyCycle,
+    UpstreamFailed(Uuid),
+    UpstreamRunning(Uuid),
+    Cancelled,
+    OutsideCalendar(CalendarError),
+}
+
+impl error::Error for SchedulingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SchedulingError::PlanAlreadyRunning(_) => None,
+            SchedulingError::TaskCreationFailed(ref e) => Some(e),
+            SchedulingError::PlanNotLoaded(ref e) => Some(e),
+            SchedulingError::PlanNotSaved(ref e) => Some(e),
+            SchedulingError::DependencyCycle => None,
+            SchedulingError::UpstreamFailed(_) => None,
+            SchedulingError::UpstreamRunning(_) => None,
+            SchedulingError::Cancelled => None,
+            SchedulingError::OutsideCalendar(ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for SchedulingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchedulingError::PlanAlreadyRunning(plan_id) => write!(f, "Sync plan {} is still running", plan_id),
+            SchedulingError::TaskCreationFailed(..) => f.write_str("Failed to create the tasks of the sync plan"),
+            SchedulingError::PlanNotLoaded(..) => f.write_str("Failed to load the sync plan"),
+            SchedulingError::PlanNotSaved(..) => f.write_str("Failed to save the next trigger time of the sync plan"),
+            SchedulingError::DependencyCycle => f.write_str("Sync plan is caught in a dependency cycle"),
+            SchedulingError::UpstreamFailed(plan_id) => write!(f, "Skipped because upstream sync plan {} did not succeed", plan_id),
+            SchedulingError::UpstreamRunning(plan_id) => write!(f, "Deferred until upstream sync plan {} finishes its run", plan_id),
+            SchedulingError::Cancelled => f.write_str("The run of the sync plan was cancelled"),
+            SchedulingError::OutsideCalendar(..) => f.write_str("Skipped because the trading calendar does not cover the day of the run"),
+        }
+    }
+}
+
+impl From<TaskCreationError> for SchedulingError {
+    fn from(err: TaskCreationError) -> SchedulingError {
+        SchedulingError::TaskCreationFailed(err)
+    }
+}
+
+/// Errors raised when a trading calendar cannot be built or is asked about a day it does not cover
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CalendarError {
+    UnknownFormat,
+    MissingField(&'static str),
+    InvalidDate(String),
+    NoDays(String),
+    NotCovered(String, NaiveDate),
+}
+
+impl error::Error for CalendarError {}
+
+impl fmt::Display for CalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalendarError::UnknownFormat => f.write_str("Calendar rows are neither a fields/items table nor a list of records"),
+            CalendarError::MissingField(field) => write!(f, "Calendar row has no {} field", field),
+            CalendarError::InvalidDate(date) => write!(f, "Invalid calendar date: {}", date),
+            CalendarError::NoDays(exchange) => write!(f, "No calendar day found for exchange {}", exchange),
+            CalendarError::NotCovered(exchange, day) => write!(f, "Calendar of exchange {} does not cover {}", exchange, day),
+        }
+    }
+}
+
+/// Errors raised when sync plans cannot be ordered by their dependencies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyError {
+    Cycle(Vec<Uuid>),
+}
+
+impl error::Error for DependencyError {}
+
+impl fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DependencyError::Cycle(plan_ids) => {
+                let plan_ids: Vec<String> = plan_ids.iter().map(Uuid::to_string).collect();
+                write!(f, "Sync plans caught in a dependency cycle: {}", plan_ids.join(", "))
+            }
+        }
+    }
+}
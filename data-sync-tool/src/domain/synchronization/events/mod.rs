@@ -0,0 +1 @@
+pub mod sync_events;
\ No newline at end of file
@@ -0,0 +1,29 @@
+// Synchronization Events
+// Raised by the synchronization domain for whoever has to be alerted
+
+use chrono::prelude::*;
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SyncEvent {
+    CircuitBreaker(CircuitBreakerEvent),
+}
+
+/// Transitions of the circuit breaker of a data source
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CircuitBreakerEvent {
+    /// Requests to the data source are held back until `retry_at`
+    Opened {
+        datasource_id: Uuid,
+        consecutive_failures: u32,
+        retry_at: DateTime<Local>,
+    },
+    /// Probe requests are let through to see whether the data source recovered
+    HalfOpened { datasource_id: Uuid },
+    /// The data source recovered and is requested as usual
+    Closed { datasource_id: Uuid },
+}
+
+pub trait EventPublisher: Send + Sync {
+    fn publish(&self, event: SyncEvent);
+}
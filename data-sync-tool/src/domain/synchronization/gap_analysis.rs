@@ -0,0 +1,216 @@
+// Gap Analysis
+// Finds the keys of a dataset that are missing from storage and plans their backfill
+
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+use getset::Getters;
+use serde_json::{Map, Value};
+
+use super::{
+    custom_errors::{CalendarError, TaskCreationError},
+    sync_plan::SyncPlan,
+    trading_calendar::TradingCalendar,
+};
+use crate::domain::data_source::{data_source::DataSource, dataset::Dataset, source_adapter::SourceAdapter};
+
+/// One row key of a dataset, keys of the same code sort by date
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash, Getters)]
+#[getset(get = "pub")]
+pub struct SyncKey {
+    code: Option<String>,
+    date: NaiveDate,
+}
+
+impl SyncKey {
+    pub fn new(date: NaiveDate, code: Option<&str>) -> Self {
+        Self {
+            code: code.map(str::to_string),
+            date,
+        }
+    }
+}
+
+/// Expected days of one code missing in a row
+#[derive(Debug, PartialEq, Eq, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct Gap {
+    code: Option<String>,
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+    missing: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct GapReport {
+    dataset_name: String,
+    expected: usize,
+    gaps: Vec<Gap>,
+}
+
+impl GapReport {
+    pub fn missing(&self) -> usize {
+        return self.gaps.iter().map(|gap| gap.missing).sum();
+    }
+}
+
+/// Gaps found in a dataset together with the payloads that request the missing keys
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct Backfill {
+    report: GapReport,
+    payloads: Vec<Value>,
+}
+
+impl Backfill {
+    /// Plan one task per missing key, requested through the adapter of the data source like scheduled plans
+    pub fn plan(
+        &self,
+        datasource: &DataSource,
+        dataset: &Dataset,
+        adapter: &dyn SourceAdapter,
+    ) -> Result<SyncPlan, TaskCreationError> {
+        let mut plan = SyncPlan::default();
+        plan.set_id(uuid::Uuid::new_v4())
+            .set_name(format!("Backfill {}", dataset.name()))
+            .set_description(format!("Backfill of {} missing keys", self.report.missing()))
+            .set_plan_for(*datasource.id(), datasource.name(), *dataset.id(), dataset.name());
+        plan.create_adapted_tasks(adapter, dataset, &self.payloads)?;
+
+        return Ok(plan);
+    }
+}
+
+/// Compares the keys a dataset should have with the keys in storage
+#[derive(Debug, PartialEq, Eq, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct GapAnalysis {
+    /// Request argument carrying the date of a key, e.g. `trade_date`
+    date_column: String,
+    /// Request argument carrying the code of a key, e.g. `ts_code`
+    code_column: String,
+}
+
+impl GapAnalysis {
+    pub fn new(date_column: &str, code_column: &str) -> Self {
+        Self {
+            date_column: date_column.to_string(),
+            code_column: code_column.to_string(),
+        }
+    }
+
+    /// Every trading day from `start` to `end` for every code, or for no code when the universe is empty.
+    /// Fails when the calendar does not cover the whole range.
+    pub fn expected_keys(
+        trading_calendar: &TradingCalendar,
+        start: NaiveDate,
+        end: NaiveDate,
+        codes: &[&str],
+    ) -> Result<BTreeSet<SyncKey>, CalendarError> {
+        let days = trading_calendar.trading_days(start, end)?;
+        if codes.is_empty() {
+            return Ok(days.into_iter().map(|day| SyncKey::new(day, None)).collect());
+        }
+        return Ok(codes
+            .iter()
+            .flat_map(|code| days.iter().map(move |day| SyncKey::new(*day, Some(code))))
+            .collect());
+    }
+
+    /// Report the expected keys missing from storage, a gap ends at the next stored key of the same code
+    pub fn analyze(&self, dataset: &Dataset, expected: &BTreeSet<SyncKey>, stored: &BTreeSet<SyncKey>) -> Backfill {
+        let mut gaps: Vec<Gap> = vec![];
+        let mut payloads = vec![];
+        let mut previous_missing: Option<&SyncKey> = None;
+
+        for key in expected {
+            if stored.contains(key) {
+                previous_missing = None;
+                continue;
+            }
+            match (gaps.last_mut(), previous_missing) {
+                (Some(gap), Some(previous)) if previous.code == key.code => {
+                    gap.last_day = key.date;
+                    gap.missing += 1;
+                }
+                _ => gaps.push(Gap {
+                    code: key.code.clone(),
+                    first_day: key.date,
+                    last_day: key.date,
+                    missing: 1,
+                }),
+            }
+            payloads.push(self.payload(key));
+            previous_missing = Some(key);
+        }
+
+        let report = GapReport {
+            dataset_name: dataset.name().clone(),
+            expected: expected.len(),
+            gaps,
+        };
+        return Backfill { report, payloads };
+    }
+
+    fn payload(&self, key: &SyncKey) -> Value {
+        let mut payload = Map::new();
+        payload.insert(self.date_column.clone(), Value::String(key.date.format("%Y%m%d").to_string()));
+        if let Some(code) = &key.code {
+            payload.insert(self.code_column.clone(), Value::String(code.clone()));
+        }
+        return Value::Object(payload);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::data_source::value_object::api_param::APIParam, infrastructure::adapters::tushare::TushareAdapter,
+    };
+
+    fn day(d: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(2023, 6, d).unwrap();
+    }
+
+    #[test]
+    fn it_should_plan_tasks_for_the_missing_keys_only() {
+        // the 24th and 25th are a weekend
+        let calendar = TradingCalendar::new("SSE", (19..=26).map(|d| (day(d), d != 24 && d != 25))).unwrap();
+        let expected = GapAnalysis::expected_keys(&calendar, day(19), day(26), &["000001.SZ", "600000.SH"]).unwrap();
+        let mut stored = expected.clone();
+        for missing in [
+            SyncKey::new(day(20), Some("000001.SZ")),
+            SyncKey::new(day(21), Some("000001.SZ")),
+            SyncKey::new(day(23), Some("000001.SZ")),
+            SyncKey::new(day(26), Some("000001.SZ")),
+            SyncKey::new(day(19), Some("600000.SH")),
+        ] {
+            stored.remove(&missing);
+        }
+        let mut dataset = Dataset::default();
+        dataset.set_id(Uuid::new_v4()).set_name("daily".to_string());
+        let params = ["trade_date", "ts_code"].map(|name| APIParam::new(name, "", "str", false, None).unwrap());
+        dataset.add_api_params(&params.to_vec()).unwrap();
+        let mut datasource = DataSource::default();
+        datasource.set_id(Uuid::new_v4()).set_name("tushare".to_string());
+
+        let backfill = GapAnalysis::new("trade_date", "ts_code").analyze(&dataset, &expected, &stored);
+        let plan = backfill.plan(&datasource, &dataset, &TushareAdapter::default()).unwrap();
+
+        let report = backfill.report();
+        assert_eq!((*report.expected(), report.missing()), (12, 5));
+        let gaps: Vec<(NaiveDate, NaiveDate, usize)> =
+            report.gaps().iter().map(|gap| (gap.first_day, gap.last_day, gap.missing)).collect();
+        assert_eq!(gaps, vec![(day(20), day(21), 2), (day(23), day(26), 2), (day(19), day(19), 1)]);
+        assert_eq!(plan.tasks().len(), 5);
+        let task = &plan.tasks()[4];
+        assert_eq!(task.spec().payload().as_ref().unwrap()["params"], json!({"trade_date": "20230619", "ts_code": "600000.SH"}));
+        assert_eq!(task.spec().payload().as_ref().unwrap()["api_name"], "daily");
+        assert_eq!((*task.datasource_id(), *task.dataset_id()), (Some(*datasource.id()), Some(*dataset.id())));
+    }
+}
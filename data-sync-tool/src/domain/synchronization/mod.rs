@@ -3,4 +3,12 @@ pub mod sync_plan;
 pub mod sync_task;
 pub mod value_objects;
 pub mod custom_errors;
-pub mod task_executor;
\ No newline at end of file
+pub mod task_executor;
+pub mod rate_limiter;
+pub mod checkpoint;
+pub mod trading_calendar;
+pub mod gap_analysis;
+pub mod plan_graph;
+pub mod adaptive_concurrency;
+pub mod events;
+pub mod circuit_breaker;
\ No newline at end of file
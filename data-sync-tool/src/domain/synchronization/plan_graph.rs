@@ -0,0 +1,127 @@
+// Plan Dependency Graph
+// Orders sync plans so every plan runs after the upstream plans it depends on
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use uuid::Uuid;
+
+use super::{custom_errors::DependencyError, sync_plan::SyncPlan};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlanGraph {
+    // upstream plans of each plan, limited to the plans of the graph
+    upstreams: BTreeMap<Uuid, BTreeSet<Uuid>>,
+    waves: Vec<Vec<Uuid>>,
+}
+
+impl PlanGraph {
+    /// Order the plans, failing with every plan that is on a cycle or depends on one
+    pub fn new(plans: &[&SyncPlan]) -> Result<Self, DependencyError> {
+        let (graph, cyclic) = Self::partial(plans);
+        if !cyclic.is_empty() {
+            return Err(DependencyError::Cycle(cyclic));
+        }
+        return Ok(graph);
+    }
+
+    /// Order the plans that can be ordered and return the others apart.
+    /// Upstream plans outside the given plans are not waited for.
+    pub fn partial(plans: &[&SyncPlan]) -> (Self, Vec<Uuid>) {
+        let plan_ids: BTreeSet<Uuid> = plans.iter().map(|plan| *plan.id()).collect();
+        let upstreams: BTreeMap<Uuid, BTreeSet<Uuid>> = plans
+            .iter()
+            .map(|plan| {
+                let upstreams = plan
+                    .upstream_plan_ids()
+                    .iter()
+                    .filter(|upstream_id| plan_ids.contains(upstream_id))
+                    .copied()
+                    .collect();
+                (*plan.id(), upstreams)
+            })
+            .collect();
+
+        // Kahn's algorithm, one wave per round of plans whose upstreams are all placed
+        let mut waves = vec![];
+        let mut placed = BTreeSet::new();
+        let mut remaining = plan_ids;
+        loop {
+            let wave: Vec<Uuid> = remaining
+                .iter()
+                .filter(|plan_id| upstreams[plan_id].is_subset(&placed))
+                .copied()
+                .collect();
+            if wave.is_empty() {
+                break;
+            }
+            for plan_id in &wave {
+                remaining.remove(plan_id);
+                placed.insert(*plan_id);
+            }
+            waves.push(wave);
+        }
+
+        return (Self { upstreams, waves }, remaining.into_iter().collect());
+    }
+
+    /// Groups of plans to run one after the other, the plans of a group do not depend on each other
+    pub fn waves(&self) -> &[Vec<Uuid>] {
+        return &self.waves;
+    }
+
+    pub fn upstreams(&self, plan_id: &Uuid) -> Option<&BTreeSet<Uuid>> {
+        return self.upstreams.get(plan_id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn plan() -> SyncPlan {
+        let mut plan = SyncPlan::default();
+        plan.set_id(Uuid::new_v4());
+        return plan;
+    }
+
+    #[test]
+    fn it_should_run_daily_bars_after_their_reference_data() {
+        let (stock_basic, trade_cal, mut daily) = (plan(), plan(), plan());
+        daily.depend_on(*stock_basic.id(), &[]).unwrap().depend_on(*trade_cal.id(), &[]).unwrap();
+
+        let graph = PlanGraph::new(&[&daily, &stock_basic, &trade_cal]).unwrap();
+
+        assert_eq!(graph.waves().len(), 2);
+        assert!(graph.waves()[0].contains(stock_basic.id()) && graph.waves()[0].contains(trade_cal.id()));
+        assert_eq!(graph.waves()[1], vec![*daily.id()]);
+    }
+
+    #[test]
+    fn it_should_reject_cycles_and_the_plans_behind_them() {
+        let (mut first, mut second, mut behind, independent) = (plan(), plan(), plan(), plan());
+        // plans stored before their dependencies were checked
+        first.set_upstream_plan_ids(vec![*second.id()]);
+        second.set_upstream_plan_ids(vec![*first.id()]);
+        behind.depend_on(*second.id(), &[&first, &second]).unwrap();
+
+        let (graph, cyclic) = PlanGraph::partial(&[&first, &second, &behind, &independent]);
+
+        assert_eq!(graph.waves().to_vec(), vec![vec![*independent.id()]]);
+        assert_eq!(cyclic.len(), 3);
+        assert!(matches!(PlanGraph::new(&[&first, &second]), Err(DependencyError::Cycle(plan_ids)) if plan_ids.len() == 2));
+    }
+
+    #[test]
+    fn it_should_refuse_a_dependency_closing_a_cycle() {
+        let (mut trade_cal, mut stock_basic, mut daily) = (plan(), plan(), plan());
+        stock_basic.depend_on(*trade_cal.id(), &[]).unwrap();
+        daily.depend_on(*stock_basic.id(), &[&stock_basic, &trade_cal]).unwrap();
+
+        let cycle = vec![*trade_cal.id(), *stock_basic.id(), *daily.id()];
+        let refused = trade_cal.depend_on(*daily.id(), &[&stock_basic, &daily]);
+
+        assert!(matches!(refused, Err(DependencyError::Cycle(plan_ids)) if plan_ids == cycle));
+        assert!(trade_cal.upstream_plan_ids().is_empty());
+        assert!(trade_cal.depend_on(*trade_cal.id(), &[]).is_err());
+    }
+}
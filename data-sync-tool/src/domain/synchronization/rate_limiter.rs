@@ -0,0 +1,203 @@
+// Request Budget
+// Token bucket and daily counter that decide whether a request to a data source may be sent now
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
+use getset::{Getters, Setters};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::value_objects::sync_config::Quota;
+
+/// Outcome of asking the budget for one request
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BudgetDecision {
+    Granted,
+    Wait(Duration),
+    DailyLimitReached,
+}
+
+/// Classic token bucket refilled continuously, a capacity of zero never throttles
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn per_minute(max_request_per_minute: u32, now: Instant) -> Self {
+        let capacity = max_request_per_minute as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: now,
+        }
+    }
+
+    /// Apply a new per-minute rate, keeping the tokens already earned
+    pub fn reconfigure(&mut self, max_request_per_minute: u32) {
+        let capacity = max_request_per_minute as f64;
+        if capacity != self.capacity {
+            self.capacity = capacity;
+            self.refill_per_sec = capacity / 60.0;
+            self.tokens = self.tokens.min(capacity);
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take a token, or tell how long to wait until the next one is available
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        if self.capacity == 0.0 {
+            return Ok(());
+        }
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        let missing = 1.0 - self.tokens;
+        return Err(Duration::from_secs_f64(missing / self.refill_per_sec));
+    }
+
+    /// Put back a token taken for a request that was never sent
+    pub fn refund(&mut self) {
+        self.tokens = (self.tokens + 1.0).min(self.capacity);
+    }
+}
+
+/// What a quota is counted for, a data source as a whole or one of its API keys
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum QuotaOwner {
+    DataSource(Uuid),
+    Credential(Uuid),
+}
+
+impl fmt::Display for QuotaOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaOwner::DataSource(id) => write!(f, "datasource/{}", id),
+            QuotaOwner::Credential(id) => write!(f, "credential/{}", id),
+        }
+    }
+}
+
+/// Number of requests sent for a data source or a key on a given day, persisted across restarts
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters, Serialize, Deserialize)]
+#[getset(get = "pub", set = "pub")]
+pub struct DailyUsage {
+    owner: QuotaOwner,
+    date: NaiveDate,
+    used: u32,
+}
+
+impl DailyUsage {
+    pub fn new(owner: QuotaOwner, date: NaiveDate) -> Self {
+        Self { owner, date, used: 0 }
+    }
+
+    /// Reset the counter when the day has changed
+    pub fn roll_over(&mut self, today: NaiveDate) -> &mut Self {
+        if self.date != today {
+            self.date = today;
+            self.used = 0;
+        }
+        return self;
+    }
+}
+
+/// Per data source budget combining the per-minute bucket and the daily counter
+#[derive(Debug, Clone)]
+pub struct RequestBudget {
+    bucket: TokenBucket,
+    daily_usage: DailyUsage,
+}
+
+impl RequestBudget {
+    pub fn new(quota: &Quota, daily_usage: DailyUsage, now: Instant) -> Self {
+        Self {
+            bucket: TokenBucket::per_minute(*quota.max_request_per_minute(), now),
+            daily_usage,
+        }
+    }
+
+    pub fn daily_usage(&self) -> &DailyUsage {
+        &self.daily_usage
+    }
+
+    /// Consume one request from the budget if both the minute and the daily quota allow it.
+    /// A daily limit of zero means the remote has no daily cap.
+    pub fn try_consume(&mut self, quota: &Quota, today: NaiveDate, now: Instant) -> BudgetDecision {
+        self.bucket.reconfigure(*quota.max_request_per_minute());
+        self.daily_usage.roll_over(today);
+
+        let daily_limit = *quota.daily_limit();
+        if daily_limit > 0 && self.daily_usage.used >= daily_limit {
+            return BudgetDecision::DailyLimitReached;
+        }
+        match self.bucket.try_acquire(now) {
+            Ok(()) => {
+                self.daily_usage.used += 1;
+                return BudgetDecision::Granted;
+            }
+            Err(wait) => return BudgetDecision::Wait(wait),
+        }
+    }
+
+    /// Give back a request granted today that was never sent
+    pub fn refund(&mut self, today: NaiveDate) {
+        if self.daily_usage.date == today {
+            self.daily_usage.used = self.daily_usage.used.saturating_sub(1);
+            self.bucket.refund();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quota(per_minute: u32, daily_limit: u32) -> Quota {
+        let mut quota = Quota::default();
+        quota.set_max_request_per_minute(per_minute).set_daily_limit(daily_limit);
+        return quota;
+    }
+
+    #[test]
+    fn it_should_ask_to_wait_when_minute_quota_is_spent() {
+        let start = Instant::now();
+        let today = NaiveDate::from_ymd_opt(2023, 4, 3).unwrap();
+        let quota = quota(2, 0);
+        let mut budget = RequestBudget::new(&quota, DailyUsage::new(QuotaOwner::DataSource(Uuid::new_v4()), today), start);
+
+        assert_eq!(budget.try_consume(&quota, today, start), BudgetDecision::Granted);
+        assert_eq!(budget.try_consume(&quota, today, start), BudgetDecision::Granted);
+        assert_eq!(budget.try_consume(&quota, today, start), BudgetDecision::Wait(Duration::from_secs(30)));
+        assert_eq!(budget.try_consume(&quota, today, start + Duration::from_secs(30)), BudgetDecision::Granted);
+    }
+
+    #[test]
+    fn it_should_stop_at_daily_limit_until_next_day() {
+        let now = Instant::now();
+        let today = NaiveDate::from_ymd_opt(2023, 4, 3).unwrap();
+        let quota = quota(0, 1);
+        let mut budget = RequestBudget::new(&quota, DailyUsage::new(QuotaOwner::DataSource(Uuid::new_v4()), today), now);
+
+        assert_eq!(budget.try_consume(&quota, today, now), BudgetDecision::Granted);
+        assert_eq!(budget.try_consume(&quota, today, now), BudgetDecision::DailyLimitReached);
+        let tomorrow = today.succ_opt().unwrap();
+        assert_eq!(budget.try_consume(&quota, tomorrow, now), BudgetDecision::Granted);
+        assert_eq!(*budget.daily_usage().used(), 1);
+    }
+}
@@ -1,51 +1,82 @@
 // Interfaces for entity repositories
 
-use super::{sync_plan::SyncPlan, custom_errors::RepositoryError, sync_task::SyncTask};
+use super::{sync_plan::SyncPlan, custom_errors::RepositoryError, sync_task::SyncTask, rate_limiter::{DailyUsage, QuotaOwner}, checkpoint::SyncCheckpoint, trading_calendar::TradingCalendar};
 use async_trait::async_trait;
+use chrono::prelude::*;
 use mockall::predicate::*;
 use uuid::Uuid;
 
 #[async_trait]
-pub trait SyncPlanRepository {
+pub trait SyncPlanRepository: Send + Sync {
     // Read
     // Plan
-    async fn get_plan_by_id<'a>(&self, id: &Uuid) -> Result<SyncPlan<'a>, RepositoryError>;
-    async fn get_plan_by_dataset_id<'a>(&self, dataset_id: &Uuid) -> Result<SyncPlan<'a>, RepositoryError>;
-    async fn get_plan_by_dataset_name<'a>(&self, dataset_name: &str) -> Result<SyncPlan<'a>, RepositoryError>;
-    async fn get_plans_by_datasource_id<'a>(&self, datasource_id: &Uuid) -> Result<Vec<SyncPlan<'a>>, RepositoryError>;
-    async fn get_plans_by_datasource_name<'a>(&self, datasource_name: &str) -> Result<Vec<SyncPlan<'a>>, RepositoryError>;
-    async fn get_plan_by_name<'a>(&self, name: &str) -> Result<SyncPlan<'a>, RepositoryError>;
-    async fn get_plans_by_activation_status<'a>(&self, is_active: bool) -> Result<Vec<SyncPlan<'a>>, RepositoryError>;
-    async fn get_plans_by_frequency<'a>(&self, sync_frequency: &str) -> Result<Vec<SyncPlan<'a>>, RepositoryError>;
-    async fn get_plans_pass_due<'a>(&self) -> Result<Vec<SyncPlan<'a>>, RepositoryError>;
-    async fn list_plans<'a>(&self, page_size: Option<usize>, page_number: Option<usize>) -> Result<Vec<SyncPlan<'a>>, RepositoryError>;
+    async fn get_plan_by_id(&self, id: &Uuid) -> Result<SyncPlan, RepositoryError>;
+    async fn get_plan_by_dataset_id(&self, dataset_id: &Uuid) -> Result<SyncPlan, RepositoryError>;
+    async fn get_plan_by_dataset_name(&self, dataset_name: &str) -> Result<SyncPlan, RepositoryError>;
+    async fn get_plans_by_datasource_id(&self, datasource_id: &Uuid) -> Result<Vec<SyncPlan>, RepositoryError>;
+    async fn get_plans_by_datasource_name(&self, datasource_name: &str) -> Result<Vec<SyncPlan>, RepositoryError>;
+    async fn get_plan_by_name(&self, name: &str) -> Result<SyncPlan, RepositoryError>;
+    async fn get_plans_by_activation_status(&self, is_active: bool) -> Result<Vec<SyncPlan>, RepositoryError>;
+    async fn get_plans_by_frequency(&self, sync_frequency: &str) -> Result<Vec<SyncPlan>, RepositoryError>;
+    async fn get_plans_pass_due(&self) -> Result<Vec<SyncPlan>, RepositoryError>;
+    async fn list_plans(&self, page_size: Option<usize>, page_number: Option<usize>) -> Result<Vec<SyncPlan>, RepositoryError>;
     
     // Task
-    async fn get_task_by_id<'a>(&self, id: &Uuid) -> Result<SyncTask<'a>, RepositoryError>;
-    async fn get_tasks_by_plan_id<'a>(&self, plan_id: &Uuid) -> Result<Vec<SyncTask<'a>>, RepositoryError>;
-    async fn get_tasks_by_datasource_id<'a>(&self, datasource_ids: &[&Uuid]) -> Result<Vec<SyncTask<'a>>, RepositoryError>;
-    async fn get_tasks_by_datasource_name<'a>(&self, datasource_name: &str) -> Result<Vec<SyncTask<'a>>, RepositoryError>;
-    async fn get_tasks_by_dataset_id<'a>(&self, dataset_ids: &[&Uuid]) -> Result<Vec<SyncTask<'a>>, RepositoryError>;
-    async fn get_tasks_by_dataset_name<'a>(&self, dataset_name: &str) -> Result<Vec<SyncTask<'a>>, RepositoryError>;
+    async fn get_task_by_id(&self, id: &Uuid) -> Result<SyncTask, RepositoryError>;
+    async fn get_tasks_by_plan_id(&self, plan_id: &Uuid) -> Result<Vec<SyncTask>, RepositoryError>;
+    async fn get_tasks_by_datasource_id(&self, datasource_ids: &[&Uuid]) -> Result<Vec<SyncTask>, RepositoryError>;
+    async fn get_tasks_by_datasource_name(&self, datasource_name: &str) -> Result<Vec<SyncTask>, RepositoryError>;
+    async fn get_tasks_by_dataset_id(&self, dataset_ids: &[&Uuid]) -> Result<Vec<SyncTask>, RepositoryError>;
+    async fn get_tasks_by_dataset_name(&self, dataset_name: &str) -> Result<Vec<SyncTask>, RepositoryError>;
 
     // Create
-    async fn save_plan<'a>(&self, plan: &SyncPlan<'a>) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn save_plans<'a>(&self, plans: &[&SyncPlan<'a>]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn save_plan(&self, plan: &SyncPlan) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn save_plans(&self, plans: &[&SyncPlan]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
 
     // Update
-    async fn add_tasks_to_plans<'a>(&self, tasks: &[&SyncTask<'a>], plan_id: Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn create_plans_for_datasource<'a>(&self, plans: &[&SyncPlan<'a>], datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn create_plan_for_dataset<'a>(&self, plan: &SyncPlan<'a>, dataset_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn update_plan_activation_status<'a>(&self, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn update_activation_status_for_datasource<'a>(&self, active: bool, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn update_sync_frequency<'a>(&self, sync_frequency: &str, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;    
-    async fn update_plans<'a>(&self, plans: &[&SyncPlan<'a>]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn add_tasks_to_plans(&self, tasks: &[&SyncTask], plan_id: Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn create_plans_for_datasource(&self, plans: &[&SyncPlan], datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn create_plan_for_dataset(&self, plan: &SyncPlan, dataset_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn update_plan_activation_status(&self, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn update_activation_status_for_datasource(&self, active: bool, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn update_sync_frequency(&self, sync_frequency: &str, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;    
+    async fn update_plans(&self, plans: &[&SyncPlan]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
 
     // Delete
-    async fn delete_plan_by_id<'a>(&self, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn delete_plans<'a>(&self, plan_ids: &[Uuid]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn delete_plan_for_dataset<'a>(&self, dataset_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn delete_plans_for_datasource<'a>(&self, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn delete_deactivated_plans_for_datasource<'a>(&self, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
-    async fn delete_tasks_for_plan<'a>(&self, task_ids: &[&Uuid], plan_id: Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn delete_plan_by_id(&self, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn delete_plans(&self, plan_ids: &[Uuid]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn delete_plan_for_dataset(&self, dataset_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn delete_plans_for_datasource(&self, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn delete_deactivated_plans_for_datasource(&self, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+    async fn delete_tasks_for_plan(&self, task_ids: &[&Uuid], plan_id: Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError>;
+}
+
+/// Keeps the daily request counters of data sources and their keys so quotas survive restarts
+#[async_trait]
+pub trait QuotaUsageRepository: Send + Sync {
+    async fn get_daily_usage(&self, owner: &QuotaOwner) -> Result<Option<DailyUsage>, RepositoryError>;
+    async fn save_daily_usage(&self, usage: &DailyUsage) -> Result<(), RepositoryError>;
+}
+
+/// Keeps the latest checkpoint of each sync plan
+#[async_trait]
+pub trait CheckpointRepository: Send + Sync {
+    async fn get_checkpoint(&self, sync_plan_id: &Uuid) -> Result<Option<SyncCheckpoint>, RepositoryError>;
+    async fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), RepositoryError>;
+    async fn delete_checkpoint(&self, sync_plan_id: &Uuid) -> Result<(), RepositoryError>;
+}
+
+/// Keeps the trading calendar of each exchange
+#[async_trait]
+pub trait TradingCalendarRepository: Send + Sync {
+    async fn get_calendar(&self, exchange: &str) -> Result<Option<TradingCalendar>, RepositoryError>;
+    /// Merge the calendar into the stored one of its exchange, the days it covers are replaced
+    async fn save_calendar(&self, calendar: &TradingCalendar) -> Result<(), RepositoryError>;
+}
+
+/// Keeps when each request, identified by its task spec fingerprint, last succeeded
+#[async_trait]
+pub trait FingerprintRepository: Send + Sync {
+    async fn get_completion_time(&self, fingerprint: &str) -> Result<Option<DateTime<Local>>, RepositoryError>;
+    async fn record_completion(&self, fingerprint: &str, completion_time: DateTime<Local>) -> Result<(), RepositoryError>;
 }
@@ -1,21 +1,31 @@
 // Synchronization Plan Definition
 // Defines when synchronization of a dataset should happend
 
-use std::str::FromStr;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
 
 use super::{
-    custom_errors::TaskCreationError,
-    sync_task::SyncTask,
-    value_objects::task_spec::{RequestMethod, TaskSpec},
-    value_objects::sync_config::{SyncConfig, self}
+    custom_errors::{CalendarError, DependencyError, ScheduleError, TaskCreationError},
+    sync_task::{SyncStatus, SyncTask},
+    trading_calendar::TradingCalendar,
+    value_objects::task_spec::{Pagination, RequestMethod, TaskSpec},
+    value_objects::sync_config::SyncConfig,
+    value_objects::sync_schedule::SyncSchedule,
+};
+use crate::domain::data_source::{
+    dataset::Dataset,
+    source_adapter::SourceAdapter,
+    value_object::{endpoint_template::bind_url, watermark::Watermark},
 };
 use chrono::prelude::*;
+use chrono_tz::Tz;
 use derivative::Derivative;
-use fake::Fake;
-use getset::{Getters, Setters};
+use getset::{Getters, MutGetters, Setters};
 use itertools::izip;
 use serde_json::Value;
-use url::{ParseError, Url};
 use uuid::Uuid;
 
 #[derive(Derivative)]
@@ -33,57 +43,106 @@ pub enum SyncFrequency {
     Yearly,
 }
 
+impl FromStr for SyncFrequency {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<SyncFrequency, Self::Err> {
+        match input.to_ascii_lowercase().as_str() {
+            "continuous" => Ok(SyncFrequency::Continuous),
+            "perminute" => Ok(SyncFrequency::PerMinute),
+            "perhour" => Ok(SyncFrequency::PerHour),
+            "daily" => Ok(SyncFrequency::Daily),
+            "weekly" => Ok(SyncFrequency::Weekly),
+            "monthly" => Ok(SyncFrequency::Monthly),
+            "quarterly" => Ok(SyncFrequency::Quarterly),
+            "yearly" => Ok(SyncFrequency::Yearly),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What to do with the trigger times a plan missed, e.g. while the tool was down
+#[derive(Derivative)]
+#[derivative(Default(bound = ""))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MissedRunPolicy {
+    /// Run once for every missed trigger time, oldest first
+    RunAll,
+    /// Run once for the latest missed trigger time
+    #[derivative(Default)]
+    LatestOnly,
+    /// Run none of them and wait for the next trigger time
+    Skip,
+}
+
+/// Most missed runs caught up in one go, the rest are caught up when the plan comes due again
+const MAX_CATCH_UP_RUNS: usize = 100;
+
 // Synchronization Plan
-#[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters, Default)]
+#[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters, MutGetters, Default)]
 #[getset(get = "pub", set = "pub")]
-pub struct SyncPlan<'a> {
+pub struct SyncPlan {
     id: Uuid,
     #[derivative(Default(value = "New Plan"))]
     name: String,
     #[derivative(Default(value = "Please add a description"))]
     description: String,
-    trigger_time: Option<DateTime<Local>>,
+    trigger_time: Option<DateTime<Local>>, // next time the plan is due
+    last_trigger_time: Option<DateTime<Local>>,
+    last_successful_trigger_time: Option<DateTime<Local>>, // of the latest run whose tasks all finished
     frequency: SyncFrequency,
+    schedule: Option<SyncSchedule>, // takes precedence over the frequency when set
+    missed_run_policy: MissedRunPolicy,
     #[derivative(Default(value = "false"))]
     active: bool,
     sync_config: SyncConfig,
-    tasks: Vec<SyncTask<'a>>,
+    #[getset(get = "pub", set = "pub", get_mut = "pub")]
+    tasks: Vec<SyncTask>,
     datasource_id: Option<Uuid>,
     datasource_name: Option<String>,
     dataset_id: Option<Uuid>,
     dataset_name: Option<String>,
     param_template_id: Option<Uuid>,
+    pagination: Option<Pagination>, // how the tasks of this plan page through truncated responses
+    upstream_plan_ids: Vec<Uuid>, // plans that must finish first when they are due in the same cycle
 }
 
-impl<'a> SyncPlan<'a> {
+impl SyncPlan {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        name: &'a str,
-        description: &'a str,
+        name: &str,
+        description: &str,
         trigger_time: Option<DateTime<Local>>,
         frequency: SyncFrequency,
         active: bool,
-        tasks: Vec<SyncTask<'a>>,
+        tasks: Vec<SyncTask>,
         datasource_id: Option<Uuid>,
-        datasource_name: &'a str,
+        datasource_name: &str,
         dataset_id: Option<Uuid>,
-        dataset_name: &'a str,
+        dataset_name: &str,
         param_template_id: Option<Uuid>,
         sync_config: SyncConfig,
-    ) -> SyncPlan<'a> {
+    ) -> SyncPlan {
         SyncPlan {
             id: Uuid::new_v4(),
             name: name.to_string(),
             description: description.to_string(),
             trigger_time,
+            last_trigger_time: None,
+            last_successful_trigger_time: None,
             frequency,
+            schedule: None,
+            missed_run_policy: MissedRunPolicy::default(),
             active,
-            tasks: tasks,
+            tasks,
             datasource_id,
             datasource_name: Some(datasource_name.to_string()),
             dataset_name: Some(dataset_name.to_string()),
             dataset_id,
             param_template_id,
-            sync_config
+            sync_config,
+            pagination: None,
+            upstream_plan_ids: vec![],
         }
     }
 
@@ -102,35 +161,191 @@ impl<'a> SyncPlan<'a> {
         return self;
     }
 
-    pub fn should_trigger(self) -> bool {
-        match self.trigger_time {
-            Some(trigger_time) => {
-                let now = Local::now();
-                return now >= trigger_time;
+    /// Only run after the given plan whenever both are due together.
+    /// Refused when the upstream plan already depends on this one, directly or through the other plans.
+    pub fn depend_on(&mut self, upstream_plan_id: Uuid, plans: &[&SyncPlan]) -> Result<&mut Self, DependencyError> {
+        // walk up from the new upstream plan, remembering how each plan was reached to report the cycle
+        let mut reached_from: HashMap<Uuid, Uuid> = HashMap::new();
+        let mut to_visit = vec![upstream_plan_id];
+        while let Some(plan_id) = to_visit.pop() {
+            if plan_id == self.id {
+                let mut cycle = vec![self.id];
+                let mut current = self.id;
+                while let Some(previous) = reached_from.get(&current) {
+                    cycle.push(*previous);
+                    current = *previous;
+                }
+                return Err(DependencyError::Cycle(cycle));
             }
+            let Some(plan) = plans.iter().find(|plan| *plan.id() == plan_id) else {
+                continue;
+            };
+            for next_id in plan.upstream_plan_ids() {
+                if *next_id != upstream_plan_id && !reached_from.contains_key(next_id) {
+                    reached_from.insert(*next_id, plan_id);
+                    to_visit.push(*next_id);
+                }
+            }
+        }
+
+        if !self.upstream_plan_ids.contains(&upstream_plan_id) {
+            self.upstream_plan_ids.push(upstream_plan_id);
+        }
+        return Ok(self);
+    }
+
+    /// Run the plan on a cron schedule from now on, the trigger time moves to the next fire time
+    pub fn schedule_with(&mut self, schedule: SyncSchedule, now: DateTime<Local>) -> &mut Self {
+        self.trigger_time = schedule.next_fire_time(&now);
+        self.schedule = Some(schedule);
+
+        return self;
+    }
+
+    /// Only fire on the trading days of the calendar. A cron schedule keeps its expression and time zone,
+    /// a plan without one keeps repeating at its frequency in the zone of the exchange.
+    /// The trigger time anchors the time of day, `now` is used when the plan has none.
+    pub fn run_on_trading_days(
+        &mut self,
+        trading_calendar: Arc<TradingCalendar>,
+        now: DateTime<Local>,
+    ) -> Result<&mut Self, ScheduleError> {
+        let anchor = self.trigger_time.unwrap_or(now);
+        let schedule = match self.schedule.take() {
+            Some(schedule) => schedule,
+            None => {
+                let Some(time_zone) = trading_calendar.time_zone() else {
+                    return Err(ScheduleError::UnknownTimeZone(trading_calendar.exchange().clone()));
+                };
+                SyncSchedule::from_frequency(&self.frequency, anchor, time_zone)
+            }
+        };
+        let schedule = schedule.on_trading_days(trading_calendar);
+        // the anchor itself fires when it falls on a trading day
+        self.trigger_time = schedule.next_fire_time(&(anchor - chrono::Duration::seconds(1)));
+        self.schedule = Some(schedule);
+
+        return Ok(self);
+    }
+
+    /// Next time the plan fires after `after`.
+    /// Plans without a cron schedule repeat at their frequency, anchored at the trigger time.
+    pub fn next_fire_time(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        match (&self.schedule, self.trigger_time) {
+            (Some(schedule), _) => return schedule.next_fire_time(after),
+            (None, Some(trigger_time)) => {
+                if trigger_time > *after {
+                    return Some(trigger_time);
+                }
+                // no zone to follow, the frequency is counted in UTC from the trigger time
+                return SyncSchedule::from_frequency(&self.frequency, trigger_time, Tz::UTC).next_fire_time(after);
+            }
+            (None, None) => return None,
+        }
+    }
+
+    /// Fails when the plan runs on trading days and its calendar does not cover the day of the fire time
+    pub fn check_trading_day(&self, fire_time: &DateTime<Local>) -> Result<(), CalendarError> {
+        match &self.schedule {
+            Some(schedule) => return schedule.check_trading_day(fire_time),
+            None => return Ok(()),
+        }
+    }
+
+    /// When the plan is due next, computed from the last run if no trigger time is set
+    pub fn due_time(&self) -> Option<DateTime<Local>> {
+        match (self.trigger_time, &self.schedule, self.last_trigger_time) {
+            (Some(trigger_time), _, _) => return Some(trigger_time),
+            (None, Some(schedule), Some(last_trigger_time)) => return schedule.next_fire_time(&last_trigger_time),
+            _ => return None,
+        }
+    }
+
+    pub fn should_trigger(&self) -> bool {
+        return self.should_trigger_at(&Local::now());
+    }
+
+    pub fn should_trigger_at(&self, now: &DateTime<Local>) -> bool {
+        match self.due_time() {
+            Some(due_time) => return *now >= due_time,
             None => return false,
         }
     }
 
+    /// Trigger times to run at `now` according to the missed run policy, oldest first.
+    /// A single passed trigger time is a regular run and is never skipped.
+    pub fn due_runs(&self, now: &DateTime<Local>) -> Vec<DateTime<Local>> {
+        let passed = std::iter::successors(self.due_time(), |fire_time| self.next_fire_time(fire_time))
+            .take_while(|fire_time| fire_time <= now);
+        match self.missed_run_policy {
+            MissedRunPolicy::RunAll => return passed.take(MAX_CATCH_UP_RUNS).collect(),
+            MissedRunPolicy::LatestOnly => return self.latest_fire_time(now).into_iter().collect(),
+            MissedRunPolicy::Skip => {
+                let first_two: Vec<DateTime<Local>> = passed.take(2).collect();
+                match first_two.len() {
+                    1 => return first_two,
+                    _ => return vec![],
+                }
+            }
+        }
+    }
+
+    /// Latest trigger time at or before `now`. It is looked for in a window before `now` that doubles until
+    /// a trigger time falls in it, so a plan idle for long does not walk every trigger time it missed.
+    fn latest_fire_time(&self, now: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let due_time = self.due_time().filter(|due_time| due_time <= now)?;
+        let mut window = chrono::Duration::seconds(1);
+        loop {
+            let start = *now - window;
+            let first = match start > due_time {
+                true => self.next_fire_time(&start),
+                false => Some(due_time),
+            };
+            let latest = std::iter::successors(first, |fire_time| self.next_fire_time(fire_time))
+                .take_while(|fire_time| fire_time <= now)
+                .last();
+            if latest.is_some() || start <= due_time {
+                return latest;
+            }
+            window = window * 2;
+        }
+    }
+
+    /// Move the trigger time past `now` without recording a run
+    pub fn skip_until(&mut self, now: DateTime<Local>) -> &mut Self {
+        self.trigger_time = self.next_fire_time(&now);
+
+        return self;
+    }
+
+    /// Record a run of the plan and move the trigger time to the next fire time
+    pub fn advance(&mut self, fired_at: DateTime<Local>) -> &mut Self {
+        self.trigger_time = self.next_fire_time(&fired_at);
+        self.last_trigger_time = Some(fired_at);
+
+        return self;
+    }
+
+    /// Whether the latest run of the plan, if it ever ran, got all of its data
+    pub fn last_run_succeeded(&self) -> bool {
+        return self.last_successful_trigger_time == self.last_trigger_time;
+    }
+
+    /// Create a task per endpoint, the `{placeholders}` of an endpoint path are bound from its payload
     pub fn create_tasks(
-        &'a mut self,
+        &mut self,
         data_endpoints: &[&str],
         request_methods: &[&str],
-        payloads: &'a [Option<&Value>],
+        payloads: &[Option<&Value>],
     ) -> Result<&mut Self, TaskCreationError> {
         if (data_endpoints.len() != request_methods.len())
-            && (data_endpoints.len() != payloads.len())
-            && (request_methods.len() != payloads.len()) {
+            || (data_endpoints.len() != payloads.len()) {
             return Err(TaskCreationError::InsufficientArgError);
         }
 
         for (endpoint, req_method, payload) in izip!(data_endpoints, request_methods, payloads) {
-            let mut new_task = SyncTask::default();
             let mut task_spec = TaskSpec::default();
-            let url = match Url::parse(endpoint) {
-                Ok(url) => url,
-                Err(parse_error) => return Err(TaskCreationError::UrlParseError((parse_error))),
-            };
+            let (url, payload) = bind_url(endpoint, *payload)?;
             let request_method = match RequestMethod::from_str(req_method) {
                 Ok(req_method) => req_method,
                 Err(_err) => return Err(TaskCreationError::InvalidRequestMethod),
@@ -138,19 +353,233 @@ impl<'a> SyncPlan<'a> {
             task_spec
                 .set_request_endpoint(url)
                 .set_request_method(request_method)
-                .set_payload(*payload);
-
-            new_task
-                .set_spec(task_spec)
-                .set_start_time(Local::now())
-                .set_dataset_id(self.dataset_id)
-                .set_dataset_name(self.dataset_name.clone())
-                .set_datasource_id(self.datasource_id)
-                .set_datasource_name(self.dataset_name.clone())
-                .set_sync_plan_id(Some(self.id));
-            self.tasks.push(new_task);
+                .set_payload(payload);
+            self.push_task(task_spec);
         }
 
         return Ok(self);
     }
+
+    /// Create one task per set of params, requested the way the adapter of the data source expects
+    /// and paged the way the extraction rules of the dataset say, when it declares some
+    pub fn create_adapted_tasks(
+        &mut self,
+        adapter: &dyn SourceAdapter,
+        dataset: &Dataset,
+        params: &[Value],
+    ) -> Result<&mut Self, TaskCreationError> {
+        let mut task_specs = params
+            .iter()
+            .map(|params| adapter.build_request(dataset, params))
+            .collect::<Result<Vec<TaskSpec>, TaskCreationError>>()?;
+        if let Some(rules) = dataset.extraction_rules() {
+            for task_spec in task_specs.iter_mut() {
+                let pagination = task_spec.pagination().clone().or_else(|| self.pagination.clone());
+                task_spec.set_pagination(rules.pagination(pagination));
+            }
+        }
+        task_specs.into_iter().for_each(|task_spec| self.push_task(task_spec));
+        return Ok(self);
+    }
+
+    /// Keep the tasks of the previous run that did not settle, unless they were cancelled or a new task sends the same request.
+    /// They start over, the plan's checkpoint tells the executor what they already got.
+    pub fn carry_over(&mut self, previous_tasks: Vec<SyncTask>) -> &mut Self {
+        let fingerprints: HashSet<String> = self.tasks.iter().map(|task| task.spec().fingerprint()).collect();
+        for mut task in previous_tasks {
+            let is_cancelled = *task.status() == SyncStatus::Cancelled;
+            if !task.is_settled() && !is_cancelled && !fingerprints.contains(&task.spec().fingerprint()) {
+                task.reset();
+                self.tasks.push(task);
+            }
+        }
+
+        return self;
+    }
+
+    /// Add a task of the spec, paged the plan's way unless the spec says otherwise
+    fn push_task(&mut self, mut task_spec: TaskSpec) {
+        if task_spec.pagination().is_none() {
+            task_spec.set_pagination(self.pagination.clone());
+        }
+        let mut new_task = SyncTask::default();
+        new_task
+            .set_spec(task_spec)
+            .set_start_time(Local::now())
+            .set_dataset_id(self.dataset_id)
+            .set_dataset_name(self.dataset_name.clone())
+            .set_datasource_id(self.datasource_id)
+            .set_datasource_name(self.datasource_name.clone())
+            .set_sync_plan_id(Some(self.id));
+        self.tasks.push(new_task);
+    }
+
+    /// Create tasks only for the payloads that ask for data past the dataset's watermark.
+    /// A date window that straddles the watermark is requested whole.
+    pub fn create_incremental_tasks(
+        &mut self,
+        data_endpoint: &str,
+        request_method: &str,
+        payloads: &[&Value],
+        watermark: Option<&Watermark>,
+    ) -> Result<&mut Self, TaskCreationError> {
+        let unsynced: Vec<Option<&Value>> = payloads
+            .iter()
+            .filter(|payload| watermark.is_none_or(|watermark| watermark.is_beyond(payload)))
+            .map(|payload| Some(*payload))
+            .collect();
+        let data_endpoints = vec![data_endpoint; unsynced.len()];
+        let request_methods = vec![request_method; unsynced.len()];
+
+        return self.create_tasks(&data_endpoints, &request_methods, &unsynced);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::synchronization::sync_task::{FailureReason, SyncStatus};
+
+    #[test]
+    fn it_should_trigger_on_the_schedule_and_advance_to_the_next_fire_time() {
+        let mut plan = SyncPlan::default();
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let at = |day: u32, hour: u32| shanghai.with_ymd_and_hms(2023, 6, day, hour, 0, 0).unwrap().with_timezone(&Local);
+        let monday = at(5, 8);
+        let schedule = SyncSchedule::new("0 17 * * Mon-Fri", "Asia/Shanghai").unwrap();
+        plan.schedule_with(schedule, monday);
+
+        assert!(!plan.should_trigger_at(&monday));
+        let fired_at = at(5, 17);
+        assert!(plan.should_trigger_at(&fired_at));
+
+        plan.advance(fired_at);
+        assert_eq!(*plan.last_trigger_time(), Some(fired_at));
+        assert_eq!(*plan.trigger_time(), Some(at(6, 17)));
+        assert!(!plan.should_trigger_at(&fired_at));
+    }
+
+    #[test]
+    fn it_should_catch_up_missed_runs_according_to_the_policy() {
+        let mut plan = SyncPlan::default();
+        let friday = Local.with_ymd_and_hms(2023, 6, 2, 17, 0, 0).unwrap();
+        let monday = Local.with_ymd_and_hms(2023, 6, 5, 18, 0, 0).unwrap();
+        plan.set_trigger_time(Some(friday)).set_frequency(SyncFrequency::Daily);
+
+        plan.set_missed_run_policy(MissedRunPolicy::RunAll);
+        let missed_days: Vec<u32> = plan.due_runs(&monday).iter().map(|fire_time| fire_time.day()).collect();
+        assert_eq!(missed_days, vec![2, 3, 4, 5]);
+
+        plan.set_missed_run_policy(MissedRunPolicy::LatestOnly);
+        assert_eq!(plan.due_runs(&monday), vec![Local.with_ymd_and_hms(2023, 6, 5, 17, 0, 0).unwrap()]);
+        assert_eq!(plan.due_runs(&Local.with_ymd_and_hms(2023, 6, 5, 17, 0, 0).unwrap()).len(), 1);
+        assert!(plan.due_runs(&Local.with_ymd_and_hms(2023, 6, 2, 16, 0, 0).unwrap()).is_empty());
+        // a plan idle for years does not walk each second it missed
+        let mut continuous = SyncPlan::default();
+        continuous.set_trigger_time(Some(friday)).set_frequency(SyncFrequency::Continuous);
+        let years_later = Local.with_ymd_and_hms(2026, 6, 2, 17, 0, 0).unwrap();
+        assert_eq!(continuous.due_runs(&years_later), vec![years_later]);
+
+        plan.set_missed_run_policy(MissedRunPolicy::Skip);
+        assert!(plan.due_runs(&monday).is_empty());
+        let friday_evening = Local.with_ymd_and_hms(2023, 6, 2, 17, 0, 10).unwrap();
+        assert_eq!(plan.due_runs(&friday_evening), vec![friday]);
+
+        plan.skip_until(monday);
+        assert_eq!(*plan.trigger_time(), Some(Local.with_ymd_and_hms(2023, 6, 6, 17, 0, 0).unwrap()));
+        assert_eq!(*plan.last_trigger_time(), None);
+    }
+
+    #[test]
+    fn it_should_run_daily_plans_on_trading_days_only() {
+        let mut plan = SyncPlan::default();
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let friday = shanghai.with_ymd_and_hms(2023, 6, 2, 17, 0, 0).unwrap().with_timezone(&Local);
+        let days = ["20230602", "20230603", "20230604", "20230605"]
+            .iter()
+            .map(|day| (NaiveDate::parse_from_str(day, "%Y%m%d").unwrap(), *day != "20230603" && *day != "20230604"));
+        let calendar = Arc::new(TradingCalendar::new("SSE", days).unwrap());
+        plan.set_trigger_time(Some(friday)).set_frequency(SyncFrequency::Daily);
+
+        // the days of the calendar are the exchange's, whatever the zone of the machine
+        plan.run_on_trading_days(calendar.clone(), friday).unwrap();
+        assert_eq!(*plan.trigger_time(), Some(friday));
+        plan.advance(friday);
+
+        assert_eq!(*plan.trigger_time(), Some(shanghai.with_ymd_and_hms(2023, 6, 5, 17, 0, 0).unwrap().with_timezone(&Local)));
+        let unknown = Arc::new(TradingCalendar::new("XETRA", [(friday.date_naive(), true)]).unwrap());
+        let mut unscheduled = SyncPlan::default();
+        assert!(matches!(unscheduled.run_on_trading_days(unknown, friday), Err(ScheduleError::UnknownTimeZone(_))));
+
+        // a cron schedule keeps its own expression and time zone
+        let schedule = SyncSchedule::new("30 17 * * *", "Asia/Shanghai").unwrap();
+        let friday_morning = shanghai.with_ymd_and_hms(2023, 6, 2, 9, 0, 0).unwrap().with_timezone(&Local);
+        plan.schedule_with(schedule, friday_morning).run_on_trading_days(calendar, friday_morning).unwrap();
+        plan.advance(shanghai.with_ymd_and_hms(2023, 6, 2, 17, 30, 0).unwrap().with_timezone(&Local));
+
+        let schedule = plan.schedule().as_ref().unwrap();
+        assert_eq!((schedule.expression().as_str(), *schedule.time_zone()), ("0 30 17 * * *", shanghai));
+        assert_eq!(*plan.trigger_time(), Some(shanghai.with_ymd_and_hms(2023, 6, 5, 17, 30, 0).unwrap().with_timezone(&Local)));
+    }
+
+    #[test]
+    fn it_should_only_create_tasks_past_the_watermark() {
+        let mut plan = SyncPlan::default();
+        let payloads = [
+            serde_json::json!({"trade_date": "20230620"}),
+            serde_json::json!({"trade_date": "20230621"}),
+            serde_json::json!({"trade_date": "20230622"}),
+        ];
+        let payloads: Vec<&Value> = payloads.iter().collect();
+        let watermark = Watermark::new("trade_date", NaiveDate::from_ymd_opt(2023, 6, 21).unwrap());
+
+        plan.create_incremental_tasks("https://api.tushare.pro", "POST", &payloads, Some(&watermark))
+            .unwrap();
+
+        assert_eq!(plan.tasks().len(), 1);
+        assert_eq!(plan.tasks()[0].spec().payload().as_ref().unwrap()["trade_date"], "20230622");
+    }
+
+    #[test]
+    fn it_should_carry_over_the_unsettled_tasks_of_the_previous_run() {
+        let mut plan = SyncPlan::default();
+        let payloads = [
+            serde_json::json!({"trade_date": "20230620"}),
+            serde_json::json!({"trade_date": "20230621"}),
+            serde_json::json!({"trade_date": "20230622"}),
+            serde_json::json!({"ts_code": "000001.SZ"}),
+        ];
+        let endpoints = ["https://api.tushare.pro"; 4];
+        plan.create_tasks(&endpoints, &["POST"; 4], &payloads.iter().map(Some).collect::<Vec<_>>())
+            .unwrap();
+        let mut previous_tasks = std::mem::take(plan.tasks_mut());
+        previous_tasks[0].finished();
+        previous_tasks[1].fail(FailureReason::DailyLimitExceeded, "quota used up");
+        previous_tasks[2].fail(FailureReason::DailyLimitExceeded, "quota used up");
+        previous_tasks[3].cancel();
+
+        let next_day = serde_json::json!({"trade_date": "20230623"});
+        plan.create_tasks(&endpoints[..2], &["POST"; 2], &[Some(&payloads[2]), Some(&next_day)])
+            .unwrap();
+        plan.carry_over(previous_tasks);
+
+        let trade_dates: Vec<&Value> = plan
+            .tasks()
+            .iter()
+            .map(|task| &task.spec().payload().as_ref().unwrap()["trade_date"])
+            .collect();
+        assert_eq!(trade_dates, vec!["20230622", "20230623", "20230621"]);
+        assert_eq!(*plan.tasks()[2].status(), SyncStatus::Created);
+    }
+
+    #[test]
+    fn it_should_repeat_at_the_frequency_without_a_schedule() {
+        let mut plan = SyncPlan::default();
+        let first_run = Local.with_ymd_and_hms(2023, 6, 5, 9, 30, 0).unwrap();
+        plan.set_trigger_time(Some(first_run)).set_frequency(SyncFrequency::Weekly);
+
+        plan.advance(first_run);
+
+        assert_eq!(*plan.trigger_time(), Some(Local.with_ymd_and_hms(2023, 6, 12, 9, 30, 0).unwrap()));
+    }
 }
@@ -6,11 +6,12 @@ use chrono::prelude::*;
 use super::value_objects::task_spec::TaskSpec;
 use derivative::Derivative;
 use getset::{Getters, Setters};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Derivative)]
 #[derivative(Default(bound = ""))]
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum SyncStatus {
     #[derivative(Default)]
     Created,
@@ -21,9 +22,22 @@ pub enum SyncStatus {
     Finished,
 }
 
-#[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters, Default)]
+/// Why a task failed, each kind of failure is handled differently by the retry policy
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum FailureReason {
+    /// The remote says requests are sent too frequently
+    TooFrequent,
+    /// The remote says the daily limit is exceeded
+    DailyLimitExceeded,
+    /// The remote rejects the arguments of the request
+    InvalidArgument,
+    Timeout,
+    Other,
+}
+
+#[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters, Serialize, Deserialize)]
 #[getset(get = "pub", set = "pub")]
-pub struct SyncTask<'a> {
+pub struct SyncTask {
     id: Uuid,
     sync_plan_id: Option<Uuid>,
     datasource_id: Option<Uuid>,
@@ -34,17 +48,20 @@ pub struct SyncTask<'a> {
     start_time: DateTime<Local>,
     end_time: Option<DateTime<Local>>,
     create_time: DateTime<Local>,
-    spec: TaskSpec<'a>, // data payload and specification of the task
+    spec: TaskSpec, // data payload and specification of the task
     result_message: Option<String>,
+    failure_reason: Option<FailureReason>,
+    attempts: u32, // number of times the request has been sent
+    credential_id: Option<Uuid>, // credential that served the latest request
 }
 
-impl<'a> SyncTask<'a> {
+impl SyncTask {
     pub fn new(
         dataset_id: Uuid,
         dataset_name: &str,
         datasource_id: Uuid,
         datasource_name: &str,
-        task_spec: TaskSpec<'a>,
+        task_spec: TaskSpec,
         sync_plan_id: Uuid,
     ) -> Self {
         let mut new_task = Self::default();
@@ -64,9 +81,12 @@ impl<'a> SyncTask<'a> {
         return self.status;
     }
 
-    /// Set task to running status
+    /// Set task to running status, counting one more attempt
     pub fn start(&mut self) -> SyncStatus {
-        self.set_status(SyncStatus::Running);
+        let attempts = self.attempts + 1;
+        self.set_status(SyncStatus::Running)
+            .set_start_time(Local::now())
+            .set_attempts(attempts);
         return self.status;
     }
 
@@ -78,19 +98,102 @@ impl<'a> SyncTask<'a> {
 
     /// Set task to finished status
     pub fn finished(&mut self) -> SyncStatus {
-        self.set_status(SyncStatus::Finished);
+        self.set_status(SyncStatus::Finished)
+            .set_end_time(Some(Local::now()))
+            .set_failure_reason(None);
         return self.status;
     }
 
+    /// Whether the task has nothing left to request, either it finished or the remote rejected its arguments
+    pub fn is_settled(&self) -> bool {
+        match self.status {
+            SyncStatus::Finished => true,
+            SyncStatus::Failed => self.failure_reason == Some(FailureReason::InvalidArgument),
+            _ => false,
+        }
+    }
+
+    /// Bring the task back to created status for another run of its plan, keeping its id
+    pub fn reset(&mut self) -> SyncStatus {
+        self.set_status(SyncStatus::Created)
+            .set_end_time(None)
+            .set_failure_reason(None)
+            .set_result_message(None)
+            .set_attempts(0);
+        return self.status;
+    }
+
+    /// Set task to failed status and keep the reason in the result message
+    pub fn fail(&mut self, reason: FailureReason, message: &str) -> SyncStatus {
+        self.set_status(SyncStatus::Failed)
+            .set_end_time(Some(Local::now()))
+            .set_failure_reason(Some(reason))
+            .set_result_message(Some(message.to_string()));
+        return self.status;
+    }
+}
+
+impl Default for SyncTask {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            sync_plan_id: None,
+            datasource_id: None,
+            datasource_name: None,
+            dataset_id: None,
+            dataset_name: None,
+            status: SyncStatus::default(),
+            start_time: Local::now(),
+            end_time: None,
+            create_time: Local::now(),
+            spec: TaskSpec::default(),
+            result_message: None,
+            failure_reason: None,
+            attempts: 0,
+            credential_id: None,
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::SyncTask;
+    use serde_json::json;
+
+    use super::*;
 
     #[test]
     fn it_should_create_an_empty_task() {
         let task = SyncTask::default();
         println!("{:#?}", task);
     }
+
+    #[test]
+    fn it_should_give_each_task_its_own_id() {
+        let first = SyncTask::default();
+        let second = SyncTask::default();
+        assert_ne!(first.id(), second.id());
+    }
+
+    #[test]
+    fn it_should_round_trip_through_json_without_the_api_key() {
+        fn assert_sendable<T: Send + Sync + 'static>(_: &T) {}
+        let mut spec = TaskSpec::default();
+        spec.set_payload(Some(json!({"api_name": "daily", "params": {"trade_date": "20230621"}})))
+            .set_api_key(Some("token".to_string()));
+        let mut task = SyncTask::default();
+        task.set_spec(spec).fail(FailureReason::Timeout, "timed out");
+        assert_sendable(&task);
+
+        let stored = serde_json::to_value(&task).unwrap();
+        assert_eq!(stored["status"], "Failed");
+        assert_eq!(stored["spec"]["request_endpoint"], "http://localhost/");
+        assert_eq!(stored["spec"]["payload"]["params"]["trade_date"], "20230621");
+        assert!(stored["spec"].get("api_key").is_none());
+
+        let loaded: SyncTask = serde_json::from_value(stored).unwrap();
+        let mut stored_spec = task.spec().clone();
+        stored_spec.set_api_key(None);
+        task.set_spec(stored_spec);
+        assert_eq!(loaded, task);
+    }
 }
@@ -1,14 +1,42 @@
 /// Task Executor Trait
-/// Defines the common interface for task execution 
-use std::error::Error;
+/// Defines the common interface for task execution
 use async_trait::async_trait;
+use serde_json::Value;
+use uuid::Uuid;
 
-use super::{sync_task::SyncTask, value_objects::execution_result::ExecutionResult};
+use super::{
+    custom_errors::RequestError,
+    sync_task::{SyncStatus, SyncTask},
+    value_objects::{
+        dry_run_report::DryRunReport, execution_result::ExecutionResult, sync_config::SyncConfig, task_spec::TaskSpec,
+    },
+};
+
+/// Which running tasks to cancel
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum CancelScope {
+    Task(Uuid),
+    Plan(Uuid),
+    DataSource(Uuid),
+}
 
 #[async_trait]
 pub trait TaskExecutor {
-    fn assign(&mut self, tasks: &[SyncTask]) -> Result<Box<dyn TaskExecutor>, Box<dyn Error>>;
-    async fn execute_all<'a>(&mut self) -> Result<ExecutionResult<'a>, Box<dyn Error>>;
-    async fn execute<'a>(self, task: &mut SyncTask) -> Result<ExecutionResult<'a>, Box<dyn Error>>;
-    async fn cancel<'a>(self, task: &mut SyncTask) -> Result<&'a mut SyncTask<'a>, Box<dyn Error>>;
-}
\ No newline at end of file
+    /// Run the tasks concurrently within the quota of the sync config, one result per task in the given order
+    async fn execute_all(&self, tasks: &mut [SyncTask], sync_config: &SyncConfig) -> Vec<ExecutionResult>;
+    /// Run a single task the way `execute_all` runs each of its tasks
+    async fn execute(&self, task: &mut SyncTask, sync_config: &SyncConfig) -> ExecutionResult;
+    /// Go through `execute_all` without sending any request, the tasks are left as they are
+    async fn dry_run(&self, tasks: &[SyncTask], sync_config: &SyncConfig) -> DryRunReport;
+    /// Cancel the task, together with any run of it in flight
+    async fn cancel(&self, task: &mut SyncTask) -> SyncStatus;
+    /// Cancel the tasks in flight in `execute_all` from outside, returns how many were told to stop.
+    /// Their requests are aborted and the data they received so far is discarded.
+    fn cancel_running(&self, scope: CancelScope) -> usize;
+}
+
+/// Sends the request described by a task spec to the remote data source
+#[async_trait]
+pub trait RequestSender: Send + Sync {
+    async fn send(&self, spec: &TaskSpec) -> Result<Value, RequestError>;
+}
@@ -0,0 +1,170 @@
+// Trading Calendar
+// Days an exchange is open, so plans can run on trading days only
+
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::custom_errors::CalendarError;
+use crate::common::tabular::{parse_date, records};
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct TradingCalendar {
+    exchange: String,
+    /// First and last day the calendar knows about
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+    open_days: BTreeSet<NaiveDate>,
+}
+
+impl TradingCalendar {
+    /// Build a calendar from each day and whether the exchange is open on it
+    pub fn new(exchange: &str, days: impl IntoIterator<Item = (NaiveDate, bool)>) -> Result<Self, CalendarError> {
+        let mut covered = BTreeSet::new();
+        let mut open_days = BTreeSet::new();
+        for (day, is_open) in days {
+            covered.insert(day);
+            if is_open {
+                open_days.insert(day);
+            }
+        }
+        let (Some(first_day), Some(last_day)) = (covered.first(), covered.last()) else {
+            return Err(CalendarError::NoDays(exchange.to_string()));
+        };
+
+        return Ok(Self {
+            exchange: exchange.to_string(),
+            first_day: *first_day,
+            last_day: *last_day,
+            open_days,
+        });
+    }
+
+    /// Build a calendar from `trade_cal` rows, either the `fields`/`items` table returned by the API
+    /// or a list of records as exported to `trade_cal.json`. Rows of other exchanges are ignored.
+    pub fn from_trade_cal(exchange: &str, rows: &Value) -> Result<Self, CalendarError> {
+        let records = records(rows).ok_or(CalendarError::UnknownFormat)?;
+
+        let mut days = vec![];
+        for record in records {
+            match record.get("exchange").and_then(Value::as_str) {
+                Some(row_exchange) if row_exchange != exchange => continue,
+                _ => {}
+            }
+            let cal_date = record.get("cal_date").ok_or(CalendarError::MissingField("cal_date"))?;
+            let is_open = record.get("is_open").ok_or(CalendarError::MissingField("is_open"))?;
+            days.push((Self::parse_date(cal_date)?, Self::parse_flag(is_open)));
+        }
+        return Self::new(exchange, days);
+    }
+
+    fn parse_date(value: &Value) -> Result<NaiveDate, CalendarError> {
+        return parse_date(value).ok_or_else(|| {
+            CalendarError::InvalidDate(value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string()))
+        });
+    }
+
+    fn parse_flag(value: &Value) -> bool {
+        match value {
+            Value::Bool(flag) => *flag,
+            Value::Number(number) => number.as_u64() == Some(1),
+            Value::String(text) => text == "1",
+            _ => false,
+        }
+    }
+
+    /// Zone the days of the exchange are counted in, None for exchanges this tool does not know
+    pub fn time_zone(&self) -> Option<Tz> {
+        match self.exchange.as_str() {
+            "SSE" | "SZSE" | "BSE" | "CFFEX" | "SHFE" | "CZCE" | "DCE" | "INE" | "GFEX" => return Some(Tz::Asia__Shanghai),
+            "HKEX" => return Some(Tz::Asia__Hong_Kong),
+            "NYSE" | "NASDAQ" => return Some(Tz::America__New_York),
+            _ => return None,
+        }
+    }
+
+    pub fn covers(&self, day: NaiveDate) -> bool {
+        return self.first_day <= day && day <= self.last_day;
+    }
+
+    /// Whether the exchange is open on the day, an error outside the covered range until the calendar is extended
+    pub fn is_trading_day(&self, day: NaiveDate) -> Result<bool, CalendarError> {
+        if !self.covers(day) {
+            return Err(CalendarError::NotCovered(self.exchange.clone(), day));
+        }
+        return Ok(self.open_days.contains(&day));
+    }
+
+    /// Trading days from `start` to `end`, both included, the whole range must be covered
+    pub fn trading_days(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<NaiveDate>, CalendarError> {
+        let mut days = vec![];
+        for day in start.iter_days().take_while(|day| *day <= end) {
+            if self.is_trading_day(day)? {
+                days.push(day);
+            }
+        }
+        return Ok(days);
+    }
+
+    /// Take over the days covered by a newer calendar of the same exchange
+    pub fn merge(&mut self, newer: &TradingCalendar) -> &mut Self {
+        self.open_days.retain(|day| !newer.covers(*day));
+        self.open_days.extend(newer.open_days.iter().copied());
+        self.first_day = self.first_day.min(newer.first_day);
+        self.last_day = self.last_day.max(newer.last_day);
+
+        return self;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn day(ymd: &str) -> NaiveDate {
+        return NaiveDate::parse_from_str(ymd, "%Y%m%d").unwrap();
+    }
+
+    #[test]
+    fn it_should_read_the_api_table_and_exported_records() {
+        let table = json!({"data": {
+            "fields": ["exchange", "cal_date", "is_open"],
+            "items": [["SSE", "20230621", 1], ["SSE", "20230622", 0], ["SZSE", "20230623", 1], ["SSE", "20230623", 0]],
+        }});
+        let records = json!([
+            {"exchange": "SSE", "cal_date": 20230621, "is_open": 1},
+            {"exchange": "SSE", "cal_date": 20230622, "is_open": 0},
+            {"exchange": "SSE", "cal_date": 20230623, "is_open": 0},
+        ]);
+
+        let from_table = TradingCalendar::from_trade_cal("SSE", &table).unwrap();
+        let from_records = TradingCalendar::from_trade_cal("SSE", &records).unwrap();
+
+        assert_eq!(from_table, from_records);
+        assert_eq!(from_table.trading_days(day("20230621"), day("20230623")), Ok(vec![day("20230621")]));
+        assert_eq!(
+            from_table.trading_days(day("20230621"), day("20230626")),
+            Err(CalendarError::NotCovered("SSE".to_string(), day("20230624")))
+        );
+    }
+
+    #[test]
+    fn it_should_let_newer_rows_override_covered_days() {
+        let mut calendar = TradingCalendar::new("SSE", vec![(day("20230621"), true), (day("20230622"), true)]).unwrap();
+        let corrected = TradingCalendar::new("SSE", vec![(day("20230622"), false), (day("20230623"), false)]).unwrap();
+
+        calendar.merge(&corrected);
+
+        assert_eq!(calendar.is_trading_day(day("20230621")), Ok(true));
+        assert_eq!(calendar.is_trading_day(day("20230622")), Ok(false));
+        assert_eq!(*calendar.last_day(), day("20230623"));
+        assert!(matches!(TradingCalendar::new("SSE", vec![]), Err(CalendarError::NoDays(_))));
+    }
+}
@@ -0,0 +1,141 @@
+//! Dry Run Report
+//! What a plan would request and how long it would take under its quota, without sending anything
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    time::{Duration, Instant},
+};
+
+use getset::Getters;
+use serde_json::Value;
+use url::Url;
+
+use super::{
+    sync_config::Quota,
+    task_spec::{RequestMethod, TaskSpec},
+};
+use crate::domain::synchronization::rate_limiter::TokenBucket;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// One request the executor would have sent
+#[derive(Debug, PartialEq, Eq, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct PlannedRequest {
+    request_endpoint: Url,
+    request_method: RequestMethod,
+    payload: Option<Value>,
+}
+
+impl PlannedRequest {
+    /// Copy of the request, the key it would be sent with is left out
+    pub fn from_spec(spec: &TaskSpec) -> Self {
+        Self {
+            request_endpoint: spec.request_endpoint().clone(),
+            request_method: spec.request_method().clone(),
+            payload: spec.payload().clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct DryRunReport {
+    requests: Vec<PlannedRequest>,
+    requests_per_endpoint: BTreeMap<String, usize>,
+    /// Tasks whose later pages depend on the responses, only their first page is counted.
+    /// The requests and the duration are lower bounds when there are any.
+    paginated_tasks: usize,
+    /// Wall-clock time the requests would take under the quota
+    estimated_duration: Duration,
+}
+
+impl DryRunReport {
+    /// Summarize the requests, assuming every request takes `request_latency` to come back
+    /// and `used_today` requests of the daily limit are already spent
+    pub fn new(
+        requests: Vec<PlannedRequest>,
+        paginated_tasks: usize,
+        quota: &Quota,
+        used_today: usize,
+        request_latency: Duration,
+    ) -> Self {
+        let mut requests_per_endpoint = BTreeMap::new();
+        for request in &requests {
+            *requests_per_endpoint
+                .entry(request.request_endpoint.to_string())
+                .or_insert(0) += 1;
+        }
+        let estimated_duration = Self::simulate(requests.len(), quota, used_today, request_latency);
+        return Self {
+            requests,
+            requests_per_endpoint,
+            paginated_tasks,
+            estimated_duration,
+        };
+    }
+
+    /// Replay the pacing of the executor on a virtual clock: the per-minute bucket, the daily limit and the concurrency.
+    /// Days are counted from the start of the run, the first one with `used_today` requests already sent.
+    pub fn simulate(request_count: usize, quota: &Quota, used_today: usize, request_latency: Duration) -> Duration {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(*quota.max_request_per_minute(), start);
+        let daily_limit = *quota.daily_limit() as usize;
+        let concurrency = (*quota.max_concurrent_task() as usize).max(1);
+        let mut free_slots: BinaryHeap<Reverse<Instant>> = (0..concurrency).map(|_| Reverse(start)).collect();
+        let (mut clock, mut day_start, mut sent_today, mut finish) = (start, start, used_today, start);
+
+        for _ in 0..request_count {
+            let Reverse(free_at) = free_slots.pop().expect("There is at least one slot");
+            // requests are dispatched in order, so the clock never goes back
+            clock = clock.max(free_at);
+            while clock >= day_start + DAY {
+                day_start += DAY;
+                sent_today = 0;
+            }
+            if daily_limit > 0 && sent_today >= daily_limit {
+                day_start += DAY;
+                sent_today = 0;
+                clock = day_start;
+            }
+            while let Err(wait) = bucket.try_acquire(clock) {
+                clock += wait;
+            }
+            sent_today += 1;
+            finish = finish.max(clock + request_latency);
+            free_slots.push(Reverse(clock + request_latency));
+        }
+        return finish - start;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quota(per_minute: u32, daily_limit: u32, concurrency: u32) -> Quota {
+        let mut quota = Quota::default();
+        quota
+            .set_max_request_per_minute(per_minute)
+            .set_daily_limit(daily_limit)
+            .set_max_concurrent_task(concurrency);
+        return quota;
+    }
+
+    #[test]
+    fn it_should_estimate_the_time_spent_waiting_for_the_quota() {
+        // a full bucket of 60 requests, then one request per second
+        let paced = DryRunReport::simulate(130, &quota(60, 0, 100), 0, Duration::ZERO);
+        assert!(paced.as_secs_f64() > 69.9 && paced.as_secs_f64() < 70.1);
+
+        let concurrent = DryRunReport::simulate(10, &quota(0, 0, 2), 0, Duration::from_secs(1));
+        assert_eq!(concurrent, Duration::from_secs(5));
+
+        let daily = DryRunReport::simulate(25, &quota(0, 10, 1), 0, Duration::ZERO);
+        assert_eq!(daily, DAY * 2);
+
+        let partly_spent = DryRunReport::simulate(5, &quota(0, 10, 1), 8, Duration::ZERO);
+        assert_eq!(partly_spent, DAY);
+    }
+}
@@ -1,14 +1,46 @@
+//! Execution Result
+//! Outcome of running a single synchronization task
+
 use getset::{Getters, Setters};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
 
-#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters)]
+use crate::domain::{
+    data_source::source_adapter::Row,
+    synchronization::sync_task::{FailureReason, SyncStatus, SyncTask},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters, Serialize, Deserialize)]
 #[getset(get = "pub", set = "pub")]
-pub struct ExecutionResult<'a> {
-    sync_plan_id: &'a Uuid,
-    task_id: &'a Uuid,
-    dataset_id: &'a Uuid,
-    datasource_id: &'a Uuid,
+pub struct ExecutionResult {
+    sync_plan_id: Option<Uuid>,
+    task_id: Uuid,
+    dataset_id: Option<Uuid>,
+    datasource_id: Option<Uuid>,
+    status: SyncStatus,
+    failure_reason: Option<FailureReason>,
     data: Value,
-    result_message: &'a str,
-}
\ No newline at end of file
+    result_message: String,
+    credential_id: Option<Uuid>,
+    #[serde(default)]
+    rows: Option<Vec<Row>>, // data parsed by the adapter of the data source, when the executor knows it
+}
+
+impl ExecutionResult {
+    /// Snapshot the ids and the current status of a task together with the data it received
+    pub fn from_task(task: &SyncTask, data: Value) -> Self {
+        Self {
+            sync_plan_id: *task.sync_plan_id(),
+            task_id: *task.id(),
+            dataset_id: *task.dataset_id(),
+            datasource_id: *task.datasource_id(),
+            status: *task.status(),
+            failure_reason: *task.failure_reason(),
+            data,
+            result_message: task.result_message().clone().unwrap_or_default(),
+            credential_id: *task.credential_id(),
+            rows: None,
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub mod task_spec;
 pub mod execution_result;
-pub mod sync_config;
\ No newline at end of file
+pub mod sync_config;
+pub mod sync_schedule;
+pub mod dry_run_report;
\ No newline at end of file
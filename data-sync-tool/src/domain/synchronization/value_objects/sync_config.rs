@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use getset::{Getters, Setters};
 use derivative::Derivative;
 
+use crate::domain::synchronization::sync_task::FailureReason;
+
 #[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters, Default)]
 #[getset(get = "pub", set = "pub")]
 pub struct Quota {
@@ -10,8 +14,176 @@ pub struct Quota {
     max_concurrent_task: u32
 }
 
+/// What the executor should do with a task whose request just failed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RetryDecision {
+    /// Send the request again after the backoff
+    RetryAfter(Duration),
+    /// Put the task back to the wait list until it has cooled down
+    Requeue(Duration),
+    /// Give up on this task only
+    Drop,
+    /// Give up on this task and cancel every task that has not run yet
+    CancelRemaining,
+}
+
+/// How failed requests are retried, following the handling of each failure reason in the design doc
+#[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters)]
+#[derivative(Default)]
+#[getset(get = "pub", set = "pub")]
+pub struct RetryPolicy {
+    /// Attempts allowed for a timed out request, including the first one
+    #[derivative(Default(value = "3"))]
+    max_attempts: u32,
+    #[derivative(Default(value = "Duration::from_secs(1)"))]
+    initial_backoff: Duration,
+    #[derivative(Default(value = "2"))]
+    backoff_multiplier: u32,
+    #[derivative(Default(value = "Duration::from_secs(60)"))]
+    max_backoff: Duration,
+    /// Wait applied when the remote says requests are too frequent
+    #[derivative(Default(value = "Duration::from_secs(60)"))]
+    cool_down: Duration,
+    /// Longest total cool down a task may accumulate before the sync is cancelled
+    #[derivative(Default(value = "Duration::from_secs(600)"))]
+    max_wait: Duration,
+    #[derivative(Default(value = "true"))]
+    drop_on_invalid_argument: bool,
+    #[derivative(Default(value = "true"))]
+    cancel_on_daily_limit: bool,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff before the next attempt, `attempt` counts from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let factor = self.backoff_multiplier.max(1).saturating_pow(exponent);
+        return self.initial_backoff.saturating_mul(factor).min(self.max_backoff);
+    }
+
+    /// Decide how to handle a failure.
+    /// `attempts` is the number of requests sent so far and `waited` the cool down already spent by the task.
+    /// When the remote tells how long to wait, `retry_after` replaces the configured cool down.
+    pub fn decide(
+        &self,
+        reason: FailureReason,
+        attempts: u32,
+        waited: Duration,
+        retry_after: Option<Duration>,
+    ) -> RetryDecision {
+        match reason {
+            FailureReason::TooFrequent => {
+                let cool_down = retry_after.unwrap_or(self.cool_down);
+                if waited + cool_down > self.max_wait {
+                    return RetryDecision::CancelRemaining;
+                }
+                return RetryDecision::Requeue(cool_down);
+            }
+            FailureReason::DailyLimitExceeded => {
+                if self.cancel_on_daily_limit {
+                    return RetryDecision::CancelRemaining;
+                }
+                return RetryDecision::Drop;
+            }
+            FailureReason::InvalidArgument => {
+                if self.drop_on_invalid_argument {
+                    return RetryDecision::Drop;
+                }
+                return RetryDecision::CancelRemaining;
+            }
+            FailureReason::Timeout => {
+                if attempts < self.max_attempts {
+                    return RetryDecision::RetryAfter(self.backoff(attempts));
+                }
+                return RetryDecision::CancelRemaining;
+            }
+            FailureReason::Other => return RetryDecision::CancelRemaining,
+        }
+    }
+}
+
+/// Additive increase, multiplicative decrease of the requests in flight to a data source,
+/// never above `Quota::max_concurrent_task`
+#[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters)]
+#[derivative(Default)]
+#[getset(get = "pub", set = "pub")]
+pub struct AdaptiveConcurrency {
+    #[derivative(Default(value = "1"))]
+    initial_concurrency: u32,
+    /// Requests observed before deciding whether to raise the concurrency by one
+    #[derivative(Default(value = "10"))]
+    sample_size: u32,
+    /// Average latency of a sample above which the concurrency is not raised
+    #[derivative(Default(value = "Duration::from_secs(2)"))]
+    max_latency: Duration,
+    /// Failed requests of a sample, in percent, above which the concurrency is not raised
+    #[derivative(Default(value = "10"))]
+    max_error_percent: u32,
+    /// Concurrency kept after a rate limit error or a timeout, in percent
+    #[derivative(Default(value = "50"))]
+    decrease_percent: u32,
+}
+
+/// When to stop sending requests to a data source that keeps failing, and when to try again
+#[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters)]
+#[derivative(Default)]
+#[getset(get = "pub", set = "pub")]
+pub struct CircuitBreakerPolicy {
+    /// Timeouts and remote errors in a row that open the breaker
+    #[derivative(Default(value = "5"))]
+    failure_threshold: u32,
+    /// How long the breaker stays open before a probe request is let through
+    #[derivative(Default(value = "Duration::from_secs(30)"))]
+    open_duration: Duration,
+    /// Probe requests that must succeed in a row to close the breaker again
+    #[derivative(Default(value = "1"))]
+    success_threshold: u32,
+    /// Wait for the breaker to close instead of failing the tasks right away
+    park_while_open: bool,
+}
+
 #[derive(Derivative, Debug, PartialEq, Eq, Clone, Getters, Setters, Default)]
 #[getset(get = "pub", set = "pub")]
 pub struct SyncConfig {
-    sync_quota: Quota
-}
\ No newline at end of file
+    sync_quota: Quota,
+    retry_policy: RetryPolicy,
+    /// Skip requests that already succeeded within this window, never skipped when not set
+    dedup_window: Option<Duration>,
+    /// Adapt the concurrency to the health of the remote, the quota's concurrency is used as is when not set
+    adaptive_concurrency: Option<AdaptiveConcurrency>,
+    /// Stop requesting a data source while it keeps failing, requests are never held back when not set
+    circuit_breaker: Option<CircuitBreakerPolicy>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_back_off_exponentially_up_to_the_max() {
+        let mut policy = RetryPolicy::default();
+        policy.set_max_backoff(Duration::from_secs(5));
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn it_should_follow_the_handling_of_each_failure_reason() {
+        let policy = RetryPolicy::default();
+        let no_wait = Duration::ZERO;
+
+        assert_eq!(policy.decide(FailureReason::Timeout, 1, no_wait, None), RetryDecision::RetryAfter(Duration::from_secs(1)));
+        assert_eq!(policy.decide(FailureReason::Timeout, 3, no_wait, None), RetryDecision::CancelRemaining);
+        assert_eq!(policy.decide(FailureReason::TooFrequent, 1, no_wait, None), RetryDecision::Requeue(Duration::from_secs(60)));
+        assert_eq!(
+            policy.decide(FailureReason::TooFrequent, 1, no_wait, Some(Duration::from_secs(5))),
+            RetryDecision::Requeue(Duration::from_secs(5))
+        );
+        assert_eq!(policy.decide(FailureReason::TooFrequent, 1, Duration::from_secs(590), None), RetryDecision::CancelRemaining);
+        assert_eq!(policy.decide(FailureReason::InvalidArgument, 1, no_wait, None), RetryDecision::Drop);
+        assert_eq!(policy.decide(FailureReason::DailyLimitExceeded, 1, no_wait, None), RetryDecision::CancelRemaining);
+        assert_eq!(policy.decide(FailureReason::Other, 1, no_wait, None), RetryDecision::CancelRemaining);
+    }
+}
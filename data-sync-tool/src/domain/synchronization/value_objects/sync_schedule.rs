@@ -0,0 +1,190 @@
+//! Sync Schedule
+//! Cron expression evaluated in the time zone of a sync plan
+
+use std::{str::FromStr, sync::Arc};
+
+use chrono::prelude::*;
+use chrono_tz::Tz;
+use cron::Schedule;
+use getset::Getters;
+
+use crate::domain::synchronization::{
+    custom_errors::{CalendarError, ScheduleError},
+    sync_plan::SyncFrequency,
+    trading_calendar::TradingCalendar,
+};
+
+const QUARTER_MONTHS: [u32; 4] = [1, 4, 7, 10];
+
+/// A calendar closed for this many days in a row is taken as broken and the schedule stops firing
+const MAX_CLOSED_DAYS: usize = 366;
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct SyncSchedule {
+    /// Cron expression with a leading seconds field, `sec min hour day month weekday [year]`
+    expression: String,
+    /// Zone the expression is evaluated in, whatever the zone of the machine running the plan
+    time_zone: Tz,
+    /// Only fire on the trading days of this calendar when set
+    trading_calendar: Option<Arc<TradingCalendar>>,
+    #[getset(skip)]
+    schedule: Schedule,
+}
+
+impl SyncSchedule {
+    /// Build a schedule from a cron expression and an IANA time zone name such as `Asia/Shanghai`.
+    /// Five field expressions are accepted as well and fire on second zero.
+    /// Cron cannot say "first business day of the quarter": a trading calendar skips closed days, never moves to the next.
+    pub fn new(expression: &str, time_zone: &str) -> Result<Self, ScheduleError> {
+        let expression = expression.split_whitespace().collect::<Vec<&str>>();
+        let expression = match expression.len() {
+            5 => format!("0 {}", expression.join(" ")),
+            _ => expression.join(" "),
+        };
+        let schedule = Schedule::from_str(&expression)
+            .map_err(|_| ScheduleError::InvalidExpression(expression.clone()))?;
+        let time_zone = Tz::from_str(time_zone).map_err(|_| ScheduleError::UnknownTimeZone(time_zone.to_string()))?;
+
+        return Ok(Self {
+            expression,
+            time_zone,
+            trading_calendar: None,
+            schedule,
+        });
+    }
+
+    /// Schedule that repeats at the given frequency, anchored at the second, minute, day... of `anchor` in the zone.
+    /// Monthly anchors past the 28th skip the months that do not have that day.
+    pub fn from_frequency(frequency: &SyncFrequency, anchor: DateTime<Local>, time_zone: Tz) -> Self {
+        let anchor = anchor.with_timezone(&time_zone);
+        let (second, minute, hour) = (anchor.second(), anchor.minute(), anchor.hour());
+        let (day, month) = (anchor.day(), anchor.month());
+        let expression = match frequency {
+            SyncFrequency::Continuous => "* * * * * *".to_string(),
+            SyncFrequency::PerMinute => format!("{} * * * * *", second),
+            SyncFrequency::PerHour => format!("{} {} * * * *", second, minute),
+            SyncFrequency::Daily => format!("{} {} {} * * *", second, minute, hour),
+            SyncFrequency::Weekly => format!("{} {} {} * * {}", second, minute, hour, anchor.weekday()),
+            SyncFrequency::Monthly => format!("{} {} {} {} * *", second, minute, hour, day),
+            SyncFrequency::Quarterly => {
+                let months = QUARTER_MONTHS
+                    .iter()
+                    .map(|first| (first + (month - 1) % 3).to_string())
+                    .collect::<Vec<String>>()
+                    .join(",");
+                format!("{} {} {} {} {} *", second, minute, hour, day, months)
+            }
+            SyncFrequency::Yearly => format!("{} {} {} {} {} *", second, minute, hour, day, month),
+        };
+
+        return Self::new(&expression, time_zone.name()).expect("Schedules derived from a frequency are always valid");
+    }
+
+    /// Skip the fire times that fall on days the exchange is closed, days are taken in the schedule's time zone
+    pub fn on_trading_days(mut self, trading_calendar: Arc<TradingCalendar>) -> Self {
+        self.trading_calendar = Some(trading_calendar);
+        return self;
+    }
+
+    /// First fire time strictly after `after`, None when the expression never fires again.
+    /// A fire time on a day the trading calendar does not cover is returned as is, see `check_trading_day`.
+    pub fn next_fire_time(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let Some(trading_calendar) = &self.trading_calendar else {
+            return self.next_cron_time(after);
+        };
+        let mut after = *after;
+        for _ in 0..MAX_CLOSED_DAYS {
+            let fire_time = self.next_cron_time(&after)?;
+            let day = fire_time.with_timezone(&self.time_zone).date_naive();
+            if trading_calendar.is_trading_day(day) != Ok(false) {
+                return Some(fire_time);
+            }
+            // nothing else fires on a closed day, carry on from its last second
+            after = self.start_of_day(day.succ_opt()?)? - chrono::Duration::seconds(1);
+        }
+        return None;
+    }
+
+    /// Fails when the day of the fire time is outside the trading calendar, whether the exchange is open is unknown
+    pub fn check_trading_day(&self, fire_time: &DateTime<Local>) -> Result<(), CalendarError> {
+        let Some(trading_calendar) = &self.trading_calendar else {
+            return Ok(());
+        };
+        trading_calendar.is_trading_day(fire_time.with_timezone(&self.time_zone).date_naive())?;
+        return Ok(());
+    }
+
+    fn start_of_day(&self, day: NaiveDate) -> Option<DateTime<Local>> {
+        let midnight = day.and_hms_opt(0, 0, 0)?;
+        return self.time_zone.from_local_datetime(&midnight).earliest().map(|start| start.with_timezone(&Local));
+    }
+
+    fn next_cron_time(&self, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+        return self
+            .schedule
+            .after(&after.with_timezone(&self.time_zone))
+            .next()
+            .map(|fire_time| fire_time.with_timezone(&Local));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_should_fire_on_the_next_weekday_in_the_plan_time_zone() {
+        let schedule = SyncSchedule::new("30 17 * * Mon-Fri", "Asia/Shanghai").unwrap();
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let friday_evening = shanghai.with_ymd_and_hms(2023, 6, 2, 18, 0, 0).unwrap().with_timezone(&Local);
+
+        let next = schedule.next_fire_time(&friday_evening).unwrap().with_timezone(&shanghai);
+
+        assert_eq!(next, shanghai.with_ymd_and_hms(2023, 6, 5, 17, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn it_should_reject_invalid_expressions_and_time_zones() {
+        assert!(matches!(SyncSchedule::new("61 * * * *", "UTC"), Err(ScheduleError::InvalidExpression(_))));
+        assert!(matches!(SyncSchedule::new("0 17 * * *", "Mars/Olympus"), Err(ScheduleError::UnknownTimeZone(_))));
+    }
+
+    #[test]
+    fn it_should_skip_holidays_of_the_trading_calendar() {
+        let rows = serde_json::json!([
+            {"exchange": "SSE", "cal_date": "20230621", "is_open": 1},
+            {"exchange": "SSE", "cal_date": "20230622", "is_open": 0},
+            {"exchange": "SSE", "cal_date": "20230623", "is_open": 0},
+            {"exchange": "SSE", "cal_date": "20230624", "is_open": 0},
+            {"exchange": "SSE", "cal_date": "20230625", "is_open": 0},
+            {"exchange": "SSE", "cal_date": "20230626", "is_open": 1},
+        ]);
+        let calendar = Arc::new(TradingCalendar::from_trade_cal("SSE", &rows).unwrap());
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let schedule = SyncSchedule::new("0 17 * * *", "Asia/Shanghai").unwrap().on_trading_days(calendar);
+        let wednesday_evening = shanghai.with_ymd_and_hms(2023, 6, 21, 18, 0, 0).unwrap().with_timezone(&Local);
+
+        let next = schedule.next_fire_time(&wednesday_evening).unwrap().with_timezone(&shanghai);
+
+        // closed for the Dragon Boat Festival and the weekend after it
+        assert_eq!(next, shanghai.with_ymd_and_hms(2023, 6, 26, 17, 0, 0).unwrap());
+        assert_eq!(schedule.check_trading_day(&next.with_timezone(&Local)), Ok(()));
+
+        // past the calendar the next day fires as is and is reported instead of being taken for a trading day
+        let after = schedule.next_fire_time(&next.with_timezone(&Local)).unwrap();
+        assert_eq!(after.with_timezone(&shanghai), shanghai.with_ymd_and_hms(2023, 6, 27, 17, 0, 0).unwrap());
+        let uncovered = NaiveDate::from_ymd_opt(2023, 6, 27).unwrap();
+        assert_eq!(schedule.check_trading_day(&after), Err(CalendarError::NotCovered("SSE".to_string(), uncovered)));
+    }
+
+    #[test]
+    fn it_should_derive_a_schedule_from_the_frequency() {
+        let shanghai: Tz = "Asia/Shanghai".parse().unwrap();
+        let anchor = shanghai.with_ymd_and_hms(2023, 2, 15, 9, 30, 0).unwrap().with_timezone(&Local);
+        let schedule = SyncSchedule::from_frequency(&SyncFrequency::Quarterly, anchor, shanghai);
+
+        let next = schedule.next_fire_time(&anchor).unwrap().with_timezone(&shanghai);
+        assert_eq!(next, shanghai.with_ymd_and_hms(2023, 5, 15, 9, 30, 0).unwrap());
+    }
+}
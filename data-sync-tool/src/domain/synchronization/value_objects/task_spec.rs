@@ -3,14 +3,17 @@
 
 use std::str::FromStr;
 
+use chrono::{Duration, NaiveDate};
 use getset::{Getters, Setters};
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 use derivative::Derivative;
 use url::Url;
 
 #[derive(Derivative)]
 #[derivative(Default(bound=""))]
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum RequestMethod {
     #[derivative(Default)]
     Get,
@@ -34,28 +37,359 @@ impl FromStr for RequestMethod {
 }
 
 
-#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters)]
+/// Write `value` at a JSON pointer such as `/params/offset`, creating the objects on the way
+fn set_pointer(target: &mut Value, pointer: &str, value: Value) {
+    let mut current = target;
+    for token in pointer.split('/').skip(1) {
+        let key = token.replace("~1", "/").replace("~0", "~");
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .expect("Value was just made an object")
+            .entry(key)
+            .or_insert(Value::Null);
+    }
+    *current = value;
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum PaginationMode {
+    /// Page through the result with offset and limit arguments, both given as JSON pointers into the payload
+    Offset { offset_key: String, limit_key: String },
+    /// Halve the date window between the start and end arguments until every window fits in one page,
+    /// for remotes without offset paging. Dates are strings formatted with `date_format`.
+    DateRange { start_key: String, end_key: String, date_format: String },
+    /// Send back the cursor each response points to until a response carries none,
+    /// `cursor_key` points into the payload and `next_cursor_pointer` into the response
+    Cursor { cursor_key: String, next_cursor_pointer: String },
+}
+
+/// What to request after a page came back
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NextPages {
+    /// Keep the page, nothing more to request
+    Done,
+    /// Keep the page and request these pages too
+    Continue(Vec<Value>),
+    /// The page was truncated, drop it and request these narrower pages instead
+    Replace(Vec<Value>),
+    /// The page may be truncated and cannot be narrowed any further, the rows past the cap are out of reach
+    Truncated,
+}
+
+/// How a request whose response may be truncated at `Quota::max_line_per_request` rows is split into pages
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters, Serialize, Deserialize)]
+#[getset(get = "pub", set = "pub")]
+pub struct Pagination {
+    mode: PaginationMode,
+    /// JSON pointer to the array of records in a response, empty when the response itself is the array
+    records_pointer: String,
+    /// JSON pointer to the number of records of the whole result in a response, when the remote tells it
+    #[serde(default)]
+    total_count_pointer: Option<String>,
+}
+
+impl Pagination {
+    pub fn new(mode: PaginationMode, records_pointer: &str) -> Self {
+        Self {
+            mode,
+            records_pointer: records_pointer.to_string(),
+            total_count_pointer: None,
+        }
+    }
+
+    /// Offset paging with `offset` and `limit` at the top level of the payload
+    pub fn offset(records_pointer: &str) -> Self {
+        Self::new(
+            PaginationMode::Offset {
+                offset_key: "/offset".to_string(),
+                limit_key: "/limit".to_string(),
+            },
+            records_pointer,
+        )
+    }
+
+    /// Date range paging on `start_date` and `end_date` formatted like `20230403`
+    pub fn date_range(records_pointer: &str) -> Self {
+        Self::new(
+            PaginationMode::DateRange {
+                start_key: "/start_date".to_string(),
+                end_key: "/end_date".to_string(),
+                date_format: "%Y%m%d".to_string(),
+            },
+            records_pointer,
+        )
+    }
+
+    /// Payload of the first page
+    pub fn first_page(&self, payload: Option<&Value>, page_size: usize) -> Value {
+        let mut page = payload.cloned().unwrap_or(Value::Object(Map::new()));
+        match &self.mode {
+            PaginationMode::Offset { offset_key, limit_key } => {
+                set_pointer(&mut page, offset_key, Value::from(0));
+                set_pointer(&mut page, limit_key, Value::from(page_size));
+            }
+            PaginationMode::DateRange { .. } | PaginationMode::Cursor { .. } => {}
+        }
+        return page;
+    }
+
+    /// Cursors tell the last page by themselves, the other modes need `Quota::max_line_per_request`
+    pub fn needs_page_size(&self) -> bool {
+        return !matches!(self.mode, PaginationMode::Cursor { .. });
+    }
+
+    pub fn count_records(&self, response: &Value) -> usize {
+        response
+            .pointer(&self.records_pointer)
+            .and_then(Value::as_array)
+            .map(Vec::len)
+            .unwrap_or(0)
+    }
+
+    /// Number of records of the whole result, numbers sent as strings included
+    pub fn read_total_count(&self, response: &Value) -> Option<usize> {
+        let total_count = response.pointer(self.total_count_pointer.as_ref()?)?;
+        let total_count = total_count.as_u64().or_else(|| total_count.as_str()?.trim().parse().ok())?;
+        return Some(total_count as usize);
+    }
+
+    /// Decide what to request after a page came back, a short page is complete.
+    /// Offset pages stop at the total count instead when the remote tells it.
+    pub fn next_pages(&self, page: &Value, response: &Value, page_size: usize) -> NextPages {
+        if let PaginationMode::Cursor { cursor_key, next_cursor_pointer } = &self.mode {
+            let next_cursor = response.pointer(next_cursor_pointer).and_then(Value::as_str);
+            let Some(next_cursor) = next_cursor.filter(|cursor| !cursor.is_empty()) else {
+                return NextPages::Done;
+            };
+            let mut next_page = page.clone();
+            set_pointer(&mut next_page, cursor_key, Value::from(next_cursor));
+            return NextPages::Continue(vec![next_page]);
+        }
+        let records = self.count_records(response);
+        let next_offset = |offset_key: &str| {
+            let offset = page.pointer(offset_key).and_then(Value::as_u64).unwrap_or(0) as usize;
+            let mut next_page = page.clone();
+            set_pointer(&mut next_page, offset_key, Value::from(offset + records));
+            (offset + records, next_page)
+        };
+        if let (PaginationMode::Offset { offset_key, .. }, Some(total_count)) = (&self.mode, self.read_total_count(response)) {
+            let (next_offset, next_page) = next_offset(offset_key);
+            if records == 0 || next_offset >= total_count {
+                return NextPages::Done;
+            }
+            return NextPages::Continue(vec![next_page]);
+        }
+        if records < page_size {
+            return NextPages::Done;
+        }
+        match &self.mode {
+            PaginationMode::Offset { offset_key, .. } => return NextPages::Continue(vec![next_offset(offset_key).1]),
+            PaginationMode::DateRange { start_key, end_key, date_format } => {
+                let parse_date = |key: &str| {
+                    page.pointer(key)
+                        .and_then(Value::as_str)
+                        .and_then(|date| NaiveDate::parse_from_str(date, date_format).ok())
+                };
+                let (Some(start), Some(end)) = (parse_date(start_key), parse_date(end_key)) else {
+                    return NextPages::Truncated;
+                };
+                // a single day cannot be split any further
+                if start >= end {
+                    return NextPages::Truncated;
+                }
+                let middle = start + Duration::days((end - start).num_days() / 2);
+                let mut first_half = page.clone();
+                set_pointer(&mut first_half, end_key, Value::from(middle.format(date_format).to_string()));
+                let mut second_half = page.clone();
+                let second_start = middle.succ_opt().unwrap_or(middle);
+                set_pointer(&mut second_half, start_key, Value::from(second_start.format(date_format).to_string()));
+                return NextPages::Replace(vec![first_half, second_half]);
+            }
+            PaginationMode::Cursor { .. } => return NextPages::Done,
+        }
+    }
+
+    /// Append the records of a page to the records merged so far, the first page keeps its envelope
+    pub fn merge(&self, merged: &mut Option<Value>, response: Value) {
+        let Some(merged_response) = merged else {
+            *merged = Some(response);
+            return;
+        };
+        let records = match response.pointer(&self.records_pointer) {
+            Some(Value::Array(records)) => records.clone(),
+            _ => return,
+        };
+        match merged_response.pointer_mut(&self.records_pointer) {
+            Some(Value::Array(merged_records)) => merged_records.extend(records),
+            _ => set_pointer(merged_response, &self.records_pointer, Value::Array(records)),
+        }
+    }
+}
+
+/// Where the API key goes in a request
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum AuthPlacement {
+    /// `?name=key`
+    QueryParam { name: String },
+    /// `name: key`
+    Header { name: String },
+    /// `Authorization: Bearer key`
+    Bearer,
+    /// `{"name": "key", ...}`, or a query parameter for GET requests
+    BodyField { name: String },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Getters, Setters, Serialize, Deserialize)]
 #[getset(get = "pub", set = "pub")]
-pub struct TaskSpec<'a> {
+pub struct TaskSpec {
     request_endpoint: Url,
     request_method: RequestMethod,
-    payload:  Option<&'a Value>
+    payload: Option<Value>,
+    pagination: Option<Pagination>,
+    #[serde(skip)]
+    api_key: Option<String>, // key of the credential the request is sent with, never stored
+    auth_placement: Option<AuthPlacement>, // where the key goes, the key is not sent when not set
 }
 
-impl<'a> Default for TaskSpec<'a> {
+impl Default for TaskSpec {
     fn default() -> Self {
         Self {
             request_endpoint: Url::parse("http://localhost/").unwrap(),
             request_method: RequestMethod::Get,
-            payload: None
+            payload: None,
+            pagination: None,
+            api_key: None,
+            auth_placement: None,
         }
     }
 }
 
-impl<'a> TaskSpec<'a> {
-    
+impl TaskSpec {
+    /// Content hash of the request, identical requests share it whatever key they are sent with.
+    /// Payload keys, nested ones included, are hashed in sorted order so the fingerprint does not depend on how the
+    /// payload was built.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.request_endpoint.as_str());
+        hasher.update(format!("{:?}", self.request_method));
+        hasher.update(self.payload.as_ref().map(|payload| canonical(payload).to_string()).unwrap_or_default());
+        hasher.update(format!("{:?}", self.pagination));
+        return format!("{:x}", hasher.finalize());
+    }
+}
+
+/// The value with the keys of every object, nested ones included, in sorted order.
+/// Does not rely on how serde_json orders maps, which a dependency enabling `preserve_order` would change.
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            return Value::Object(entries.into_iter().map(|(key, value)| (key.clone(), canonical(value))).collect());
+        }
+        Value::Array(items) => return Value::Array(items.iter().map(canonical).collect()),
+        _ => return value.clone(),
+    }
 }
 
+#[cfg(test)]
 mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn response_of(records: usize) -> Value {
+        return json!({"data": {"items": vec![Value::Null; records]}});
+    }
 
+    #[test]
+    fn it_should_fingerprint_identical_requests_alike() {
+        let (payload, reordered, other) = (
+            json!({"ts_code": "000001.SZ", "trade_date": "20230621", "fields": {"open": 1, "close": 2}}),
+            serde_json::from_str::<Value>(r#"{"fields": {"close": 2, "open": 1}, "trade_date": "20230621", "ts_code": "000001.SZ"}"#).unwrap(),
+            json!({"ts_code": "000001.SZ", "trade_date": "20230622", "fields": {"open": 1, "close": 2}}),
+        );
+        let spec_with = |payload: &Value| {
+            let mut spec = TaskSpec::default();
+            spec.set_payload(Some(payload.clone()));
+            spec
+        };
+        let mut keyed = spec_with(&reordered);
+        keyed.set_api_key(Some("token".to_string()));
+
+        assert_eq!(spec_with(&payload).fingerprint(), keyed.fingerprint());
+        assert_ne!(spec_with(&payload).fingerprint(), spec_with(&other).fingerprint());
+    }
+
+    #[test]
+    fn it_should_page_through_nested_offset_arguments() {
+        let pagination = Pagination::new(
+            PaginationMode::Offset {
+                offset_key: "/params/offset".to_string(),
+                limit_key: "/params/limit".to_string(),
+            },
+            "/data/items",
+        );
+        let payload = json!({"api_name": "stock_basic", "params": {"list_status": "L"}});
+
+        let first_page = pagination.first_page(Some(&payload), 5000);
+        assert_eq!(first_page, json!({"api_name": "stock_basic", "params": {"list_status": "L", "offset": 0, "limit": 5000}}));
+
+        let NextPages::Continue(next_pages) = pagination.next_pages(&first_page, &response_of(5000), 5000) else {
+            panic!("A full page should be followed by the next one");
+        };
+        assert_eq!(next_pages[0].pointer("/params/offset"), Some(&json!(5000)));
+        assert_eq!(pagination.next_pages(&next_pages[0], &response_of(1200), 5000), NextPages::Done);
+    }
+
+    #[test]
+    fn it_should_halve_a_truncated_date_window() {
+        let pagination = Pagination::date_range("/data/items");
+        let page = json!({"ts_code": "000001.SZ", "start_date": "20230101", "end_date": "20230110"});
+
+        assert_eq!(
+            pagination.next_pages(&page, &response_of(6000), 6000),
+            NextPages::Replace(vec![
+                json!({"ts_code": "000001.SZ", "start_date": "20230101", "end_date": "20230105"}),
+                json!({"ts_code": "000001.SZ", "start_date": "20230106", "end_date": "20230110"}),
+            ])
+        );
+        let single_day = json!({"start_date": "20230101", "end_date": "20230101"});
+        assert_eq!(pagination.next_pages(&single_day, &response_of(6000), 6000), NextPages::Truncated);
+        assert_eq!(pagination.next_pages(&single_day, &response_of(5999), 6000), NextPages::Done);
+    }
+
+    #[test]
+    fn it_should_follow_the_cursor_until_there_is_none() {
+        let pagination = Pagination::new(
+            PaginationMode::Cursor {
+                cursor_key: "/qopts.cursor_id".to_string(),
+                next_cursor_pointer: "/meta/next_cursor_id".to_string(),
+            },
+            "/datatable/data",
+        );
+        let page = pagination.first_page(Some(&json!({"ticker": "AAPL"})), 0);
+        assert!(!pagination.needs_page_size());
+
+        assert_eq!(
+            pagination.next_pages(&page, &json!({"datatable": {"data": [[1]]}, "meta": {"next_cursor_id": "c2"}}), 0),
+            NextPages::Continue(vec![json!({"ticker": "AAPL", "qopts.cursor_id": "c2"})])
+        );
+        let last = json!({"datatable": {"data": [[1]]}, "meta": {"next_cursor_id": null}});
+        assert_eq!(pagination.next_pages(&page, &last, 0), NextPages::Done);
+    }
+
+    #[test]
+    fn it_should_merge_pages_into_the_first_response() {
+        let pagination = Pagination::offset("/data/items");
+        let mut merged = None;
+
+        pagination.merge(&mut merged, json!({"data": {"fields": ["ts_code"], "items": [["000001.SZ"]]}}));
+        pagination.merge(&mut merged, json!({"data": {"fields": ["ts_code"], "items": [["000002.SZ"]]}}));
+
+        assert_eq!(merged, Some(json!({"data": {"fields": ["ts_code"], "items": [["000001.SZ"], ["000002.SZ"]]}})));
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 // Interfaces for entity repositories
 
-use super::param_template::ParameterTemplate;
+use super::template::ParameterTemplate;
 use mockall::predicate::*;
 use mockall::*;
 
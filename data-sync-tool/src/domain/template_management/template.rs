@@ -7,7 +7,6 @@
 //! For example, we can have a datetime_generator object that implements AlgorithmicGenerator trait to generate datetime values
 
 // use chrono::prelude::*;
-use fake::{ Fake};
 use uuid::Uuid;
 
 // use std::collections::HashMap;
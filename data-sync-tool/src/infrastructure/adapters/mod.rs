@@ -0,0 +1,3 @@
+pub mod nasdaq_data_link;
+pub mod rest;
+pub mod tushare;
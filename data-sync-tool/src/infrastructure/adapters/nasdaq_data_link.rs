@@ -0,0 +1,298 @@
+//! Nasdaq Data Link Adapter
+//! Data Link, formerly Quandl, serves tables at `datatables/{publisher}/{table}.json`, paged with `qopts.cursor_id`,
+//! and time series at `datasets/{database}/{code}.json`. Both describe their columns next to the data.
+
+use serde_json::Value;
+use url::Url;
+
+use crate::domain::{
+    data_source::{
+        dataset::Dataset,
+        source_adapter::{Row, SourceAdapter},
+        value_object::{
+            data_schema::{Column, DataSchema},
+            endpoint_template::append_segments,
+        },
+    },
+    synchronization::{
+        custom_errors::{RequestError, TaskCreationError},
+        value_objects::task_spec::{AuthPlacement, Pagination, PaginationMode, RequestMethod, TaskSpec},
+    },
+};
+
+const DATA_LINK_API: &str = "https://data.nasdaq.com/api/v3/";
+/// Code of the limit error returned once the daily call limit is spent
+const DAILY_LIMIT_CODE: &str = "QELx04";
+
+fn invalid(reason: &str) -> RequestError {
+    return RequestError::InvalidResponse(format!("Nasdaq Data Link {}", reason));
+}
+
+/// Where the records and the column block of a response are
+#[derive(Clone, Copy)]
+enum Layout {
+    Datatable,
+    Dataset,
+}
+
+impl Layout {
+    fn of_endpoint(endpoint: &str) -> Self {
+        match endpoint.trim_start_matches('/').starts_with("datatables/") {
+            true => return Layout::Datatable,
+            false => return Layout::Dataset,
+        }
+    }
+
+    fn of_response(response: &Value) -> Option<Self> {
+        if response.get("datatable").is_some() {
+            return Some(Layout::Datatable);
+        }
+        if response.get("dataset").is_some() {
+            return Some(Layout::Dataset);
+        }
+        return None;
+    }
+}
+
+pub struct NasdaqDataLinkAdapter {
+    api_endpoint: Url,
+}
+
+impl NasdaqDataLinkAdapter {
+    pub fn new(api_endpoint: Url) -> Self {
+        Self { api_endpoint }
+    }
+
+    /// Schema read from the column block of a data or a metadata response
+    pub fn schema_of(response: &Value) -> Result<DataSchema, RequestError> {
+        let columns: Vec<Column> = Self::columns(response)?
+            .iter()
+            .map(|(name, field_type)| Column::new(name, field_type, ""))
+            .collect::<Result<_, _>>()
+            .map_err(|err| invalid(&err.to_string()))?;
+        return Ok(DataSchema::new(&columns));
+    }
+
+    /// Names and field types of the columns, in the order of the values of a record.
+    /// Datatables give each column a type, time series only name their columns: a date followed by numbers.
+    fn columns(response: &Value) -> Result<Vec<(String, &'static str)>, RequestError> {
+        let (pointer, layout) = match Layout::of_response(response) {
+            Some(Layout::Datatable) => ("/datatable/columns", Layout::Datatable),
+            Some(Layout::Dataset) => ("/dataset/column_names", Layout::Dataset),
+            None => return Err(invalid("response carries neither a datatable nor a dataset")),
+        };
+        let columns = response
+            .pointer(pointer)
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("response carries no columns"))?;
+        return columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| match layout {
+                Layout::Datatable => {
+                    let name = column.get("name").and_then(Value::as_str);
+                    let data_type = column.get("type").and_then(Value::as_str).unwrap_or_default();
+                    name.map(|name| (name.to_string(), Self::field_type(data_type)))
+                        .ok_or_else(|| invalid("column should have a name"))
+                }
+                Layout::Dataset => {
+                    let field_type = if index == 0 { "str" } else { "float" };
+                    column
+                        .as_str()
+                        .map(|name| (name.to_string(), field_type))
+                        .ok_or_else(|| invalid("column names should be strings"))
+                }
+            })
+            .collect();
+    }
+
+    /// Field type of a Data Link column type, dates are kept as strings
+    fn field_type(data_type: &str) -> &'static str {
+        let data_type = data_type.to_lowercase();
+        if data_type.starts_with("bigdecimal") || data_type == "double" || data_type == "float" {
+            return "float";
+        }
+        if data_type == "integer" || data_type == "long" {
+            return "int";
+        }
+        return "str";
+    }
+}
+
+impl Default for NasdaqDataLinkAdapter {
+    fn default() -> Self {
+        Self::new(Url::parse(DATA_LINK_API).expect("Nasdaq Data Link API endpoint should be a valid url"))
+    }
+}
+
+impl SourceAdapter for NasdaqDataLinkAdapter {
+    /// The params are sent as query arguments next to the `api_key`, datatables follow their cursor
+    fn build_request(&self, dataset: &Dataset, params: &Value) -> Result<TaskSpec, TaskCreationError> {
+        let (mut segments, params) = dataset.bind_endpoint(params)?;
+        if let Some(last) = segments.last_mut().filter(|last| !last.ends_with(".json")) {
+            last.push_str(".json");
+        }
+        let mut task_spec = TaskSpec::default();
+        task_spec
+            .set_request_endpoint(append_segments(&self.api_endpoint, &segments)?)
+            .set_request_method(RequestMethod::Get)
+            .set_payload(Some(params))
+            .set_auth_placement(Some(AuthPlacement::QueryParam { name: "api_key".to_string() }));
+        if let Layout::Datatable = Layout::of_endpoint(dataset.endpoint()) {
+            let cursor = PaginationMode::Cursor {
+                cursor_key: "/qopts.cursor_id".to_string(),
+                next_cursor_pointer: "/meta/next_cursor_id".to_string(),
+            };
+            task_spec.set_pagination(Some(Pagination::new(cursor, "/datatable/data")));
+        }
+        return Ok(task_spec);
+    }
+
+    /// Data Link codes its errors by family: `QELx` for limits, `QECx` for invalid calls.
+    /// The daily limit is told by its code, a limit whose message speaks of the day is taken as one too.
+    fn check_response(&self, response: &Value) -> Result<(), RequestError> {
+        let Some(error) = response.get("quandl_error") else {
+            return Ok(());
+        };
+        let code = error.get("code").and_then(Value::as_str).unwrap_or_default();
+        let message = error.get("message").and_then(Value::as_str).unwrap_or_default().to_string();
+        if code == DAILY_LIMIT_CODE || (code.starts_with("QELx") && message.to_lowercase().contains("daily")) {
+            return Err(RequestError::DailyLimitExceeded(message));
+        }
+        if code.starts_with("QELx") {
+            return Err(RequestError::TooFrequent(None));
+        }
+        if code.starts_with("QECx") {
+            return Err(RequestError::InvalidArgument(message));
+        }
+        return Err(RequestError::Rejected(format!("{} {}", code, message)));
+    }
+
+    /// Zip the column names with each record, then type the record after the schema of the dataset
+    fn parse_rows(&self, dataset: &Dataset, response: &Value) -> Result<Vec<Row>, RequestError> {
+        let names: Vec<String> = Self::columns(response)?.into_iter().map(|(name, _)| name).collect();
+        let data_pointer = match Layout::of_response(response) {
+            Some(Layout::Datatable) => "/datatable/data",
+            _ => "/dataset/data",
+        };
+        let records = response
+            .pointer(data_pointer)
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("response carries no data"))?;
+
+        let mut rows = Vec::with_capacity(records.len());
+        for record in records {
+            let values = record.as_array().filter(|values| values.len() == names.len());
+            let values = values.ok_or_else(|| invalid("record does not match its columns"))?;
+            let record: Row = names.iter().cloned().zip(values.iter().cloned()).collect();
+            rows.push(dataset.schema().typed_row(&record).map_err(|err| invalid(&err.to_string()))?);
+        }
+        return Ok(rows);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::{
+        data_source::value_object::api_param::APIParam, synchronization::value_objects::task_spec::NextPages,
+    };
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            serde_json::from_str::<Value>(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/nasdaq_data_link/",
+                $name
+            )))
+            .unwrap()
+        };
+    }
+
+    fn dataset(endpoint: &str, response: &Value) -> Dataset {
+        let mut dataset = Dataset::default();
+        dataset.set_endpoint(endpoint.to_string());
+        *dataset.schema_mut() = NasdaqDataLinkAdapter::schema_of(response).unwrap();
+        dataset
+            .add_api_params(&vec![
+                APIParam::new("ticker", "", "str", false, None).unwrap(),
+                APIParam::new("rows", "", "int", false, None).unwrap(),
+            ])
+            .unwrap();
+        return dataset;
+    }
+
+    #[test]
+    fn it_should_map_the_column_block_to_a_schema() {
+        let schema = NasdaqDataLinkAdapter::schema_of(&fixture!("datatable_page.json")).unwrap();
+        let expected = DataSchema::new(&vec![
+            Column::new("ticker", "str", "").unwrap(),
+            Column::new("date", "str", "").unwrap(),
+            Column::new("close", "float", "").unwrap(),
+            Column::new("volume", "int", "").unwrap(),
+        ]);
+        assert_eq!(schema, expected);
+
+        let schema = NasdaqDataLinkAdapter::schema_of(&fixture!("dataset.json")).unwrap();
+        assert_eq!(
+            schema,
+            DataSchema::new(&vec![Column::new("Date", "str", "").unwrap(), Column::new("Value", "float", "").unwrap()])
+        );
+    }
+
+    #[test]
+    fn it_should_follow_the_datatable_cursor() {
+        let first_page = fixture!("datatable_page.json");
+        let dataset = dataset("/datatables/SHARADAR/SEP", &first_page);
+        let spec = NasdaqDataLinkAdapter::default().build_request(&dataset, &json!({"ticker": "AAPL"})).unwrap();
+
+        assert_eq!(spec.request_endpoint().as_str(), "https://data.nasdaq.com/api/v3/datatables/SHARADAR/SEP.json");
+        assert_eq!(*spec.auth_placement(), Some(AuthPlacement::QueryParam { name: "api_key".to_string() }));
+        let pagination = spec.pagination().clone().unwrap();
+        let page = pagination.first_page(spec.payload().as_ref(), 0);
+        let NextPages::Continue(next_pages) = pagination.next_pages(&page, &first_page, 0) else {
+            panic!("A page with a cursor should be followed by the next one");
+        };
+        assert_eq!(next_pages[0], json!({"ticker": "AAPL", "qopts.cursor_id": "djFfMTIzNDVfMTY4NzM3OTIwMA=="}));
+        assert_eq!(pagination.next_pages(&next_pages[0], &fixture!("datatable_last_page.json"), 0), NextPages::Done);
+
+        let mut merged = None;
+        pagination.merge(&mut merged, first_page);
+        pagination.merge(&mut merged, fixture!("datatable_last_page.json"));
+        let rows = NasdaqDataLinkAdapter::default().parse_rows(&dataset, &merged.unwrap()).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            Value::Object(rows[2].clone()),
+            json!({"ticker": "AAPL", "date": "2023-06-22", "close": 187.0, "volume": 51245327})
+        );
+    }
+
+    #[test]
+    fn it_should_parse_time_series_without_pagination() {
+        let response = fixture!("dataset.json");
+        let dataset = dataset("datasets/FRED/GDP", &response);
+        let spec = NasdaqDataLinkAdapter::default().build_request(&dataset, &json!({"rows": 3})).unwrap();
+        assert_eq!(spec.request_endpoint().as_str(), "https://data.nasdaq.com/api/v3/datasets/FRED/GDP.json");
+        assert!(spec.pagination().is_none());
+
+        let rows = NasdaqDataLinkAdapter::default().parse_rows(&dataset, &response).unwrap();
+        assert_eq!(Value::Object(rows[0].clone()), json!({"Date": "2023-01-01", "Value": 26529.774}));
+    }
+
+    #[test]
+    fn it_should_classify_data_link_errors() {
+        let adapter = NasdaqDataLinkAdapter::default();
+        let error = adapter.check_response(&fixture!("error.json")).unwrap_err();
+        assert!(matches!(error, RequestError::DailyLimitExceeded(_)));
+        let reworded = json!({"quandl_error": {"code": "QELx04", "message": "Call limit reached, try again tomorrow"}});
+        assert!(matches!(adapter.check_response(&reworded), Err(RequestError::DailyLimitExceeded(_))));
+
+        let speed = json!({"quandl_error": {"code": "QELx03", "message": "You have exceeded the API speed limit"}});
+        assert!(matches!(adapter.check_response(&speed), Err(RequestError::TooFrequent(None))));
+        let invalid = json!({"quandl_error": {"code": "QECx02", "message": "You have submitted an incorrect code"}});
+        assert!(matches!(adapter.check_response(&invalid), Err(RequestError::InvalidArgument(_))));
+        assert!(adapter.check_response(&fixture!("dataset.json")).is_ok());
+    }
+}
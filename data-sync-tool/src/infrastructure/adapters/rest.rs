@@ -0,0 +1,158 @@
+//! REST Adapter
+//! Source adapter driven by a `RestSourceConfig`, for vendors answering with plain JSON records
+
+use serde_json::Value;
+
+use crate::domain::{
+    data_source::{
+        data_source::DataSource,
+        dataset::Dataset,
+        source_adapter::{Row, SourceAdapter},
+        value_object::rest_config::RestSourceConfig,
+    },
+    synchronization::{
+        custom_errors::{RequestError, TaskCreationError},
+        value_objects::task_spec::TaskSpec,
+    },
+};
+
+pub struct RestAdapter {
+    config: RestSourceConfig,
+}
+
+impl RestAdapter {
+    pub fn new(config: RestSourceConfig) -> Self {
+        Self { config }
+    }
+
+    /// Adapter of a data source configured as a generic REST source
+    pub fn from_datasource(datasource: &DataSource) -> Option<Self> {
+        return datasource.rest_config().clone().map(Self::new);
+    }
+}
+
+impl SourceAdapter for RestAdapter {
+    /// The params left after binding the endpoint are sent as the query of a GET or the body of a POST
+    fn build_request(&self, dataset: &Dataset, params: &Value) -> Result<TaskSpec, TaskCreationError> {
+        let (segments, params) = dataset.bind_endpoint(params)?;
+        let mut task_spec = TaskSpec::default();
+        task_spec
+            .set_request_endpoint(self.config.endpoint_url(&segments)?)
+            .set_request_method(self.config.request_method().clone())
+            .set_payload(Some(params))
+            .set_auth_placement(self.config.auth_placement().clone());
+        return Ok(task_spec);
+    }
+
+    /// Failures of a generic source are told by the HTTP status alone
+    fn check_response(&self, _response: &Value) -> Result<(), RequestError> {
+        return Ok(());
+    }
+
+    /// Records at the records pointer of the source, unless the dataset declares its own extraction rules
+    fn parse_rows(&self, dataset: &Dataset, response: &Value) -> Result<Vec<Row>, RequestError> {
+        if let Some(rules) = dataset.extraction_rules() {
+            return Ok(rules.extract_rows(dataset.schema(), response)?);
+        }
+        let invalid = |reason: &str| RequestError::InvalidResponse(reason.to_string());
+        let records = response
+            .pointer(self.config.records_pointer())
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid(&format!("no array of records at '{}'", self.config.records_pointer())))?;
+
+        let mut rows = Vec::with_capacity(records.len());
+        for record in records {
+            let record = record.as_object().ok_or_else(|| invalid("records should be objects"))?;
+            rows.push(dataset.schema().typed_row(record).map_err(|err| invalid(&err.to_string()))?);
+        }
+        return Ok(rows);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Local;
+    use serde_json::json;
+    use url::Url;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::domain::{
+        data_source::value_object::{
+            api_param::APIParam,
+            data_schema::{Column, DataSchema},
+        },
+        synchronization::value_objects::task_spec::{AuthPlacement, RequestMethod},
+    };
+
+    fn adapter() -> RestAdapter {
+        let mut config = RestSourceConfig::new(Url::parse("https://data.example.com/api/").unwrap());
+        config
+            .set_auth_placement(Some(AuthPlacement::Header { name: "X-Api-Key".to_string() }))
+            .set_records_pointer("/results".to_string());
+        return RestAdapter::new(config);
+    }
+
+    fn bars() -> Dataset {
+        let mut dataset = Dataset::default();
+        dataset.set_endpoint("/bars".to_string());
+        dataset.add_api_params(&vec![APIParam::new("symbol", "", "str", true, None).unwrap()]).unwrap();
+        dataset.add_columns_to_schema(&vec![
+            Column::new("symbol", "str", "").unwrap(),
+            Column::new("volume", "int", "").unwrap(),
+        ]);
+        return dataset;
+    }
+
+    #[test]
+    fn it_should_build_the_request_from_the_config() {
+        let spec = adapter().build_request(&bars(), &json!({"symbol": "AAPL"})).unwrap();
+
+        assert_eq!(spec.request_endpoint().as_str(), "https://data.example.com/api/bars");
+        assert_eq!(*spec.request_method(), RequestMethod::Get);
+        assert_eq!(*spec.payload(), Some(json!({"symbol": "AAPL"})));
+        assert_eq!(*spec.auth_placement(), Some(AuthPlacement::Header { name: "X-Api-Key".to_string() }));
+    }
+
+    #[test]
+    fn it_should_type_the_records_found_at_the_pointer() {
+        let response = json!({"results": [{"symbol": "AAPL", "volume": "1200", "extra": 1}]});
+        let rows = adapter().parse_rows(&bars(), &response).unwrap();
+        assert_eq!(Value::Object(rows[0].clone()), json!({"symbol": "AAPL", "volume": 1200}));
+
+        let error = adapter().parse_rows(&bars(), &json!({"data": []})).unwrap_err();
+        assert!(matches!(error, RequestError::InvalidResponse(_)));
+    }
+
+    #[test]
+    fn it_should_bind_the_endpoint_template_from_the_api_params() {
+        let api_params = vec![
+            APIParam::new("symbol", "", "str", true, None).unwrap(),
+            APIParam::new("interval", "", "str", true, None).unwrap(),
+            APIParam::new("limit", "", "int", false, None).unwrap(),
+        ];
+        let dataset = |endpoint: &str| {
+            Dataset::new(Uuid::new_v4(), "bars", "", endpoint, &api_params, DataSchema::default(), Local::now(), None, None, true)
+        };
+        let bars = dataset("/stocks/{symbol}/bars/{interval}").unwrap();
+
+        let spec = adapter().build_request(&bars, &json!({"symbol": "BRK B", "interval": "1d", "limit": 100})).unwrap();
+        assert_eq!(spec.request_endpoint().as_str(), "https://data.example.com/api/stocks/BRK%20B/bars/1d");
+        assert_eq!(*spec.payload(), Some(json!({"limit": 100})));
+
+        let missing = adapter().build_request(&bars, &json!({"symbol": "AAPL"})).unwrap_err();
+        assert!(matches!(missing, TaskCreationError::MissingParam(name) if name == "interval"));
+        let unused = adapter().build_request(&bars, &json!({"symbol": "AAPL", "interval": "1d", "page": 2})).unwrap_err();
+        assert!(matches!(unused, TaskCreationError::UnusedParam(name) if name == "page"));
+        assert!(dataset("/v2/stocks/{symbol").is_err());
+
+        let undeclared =
+            Dataset::new(Uuid::new_v4(), "bars", "", "/stocks/{symbol}", &vec![], DataSchema::default(), Local::now(), None, None, true)
+                .unwrap();
+        let spec = adapter().build_request(&undeclared, &json!({"symbol": "AAPL", "limit": 100})).unwrap();
+        assert_eq!(spec.request_endpoint().as_str(), "https://data.example.com/api/stocks/AAPL");
+        assert_eq!(*spec.payload(), Some(json!({"limit": 100})));
+        let empty = adapter().build_request(&undeclared, &json!({"symbol": ""})).unwrap_err();
+        assert!(matches!(empty, TaskCreationError::InvalidParamValue(name) if name == "symbol"));
+    }
+}
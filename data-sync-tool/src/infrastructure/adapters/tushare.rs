@@ -0,0 +1,188 @@
+//! Tushare Adapter
+//! Tushare takes a POST of `{api_name, token, params, fields}` and answers with `{code, msg, data: {fields, items}}`
+
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::domain::{
+    data_source::{
+        dataset::Dataset,
+        source_adapter::{Row, SourceAdapter},
+    },
+    synchronization::{
+        custom_errors::{RequestError, TaskCreationError},
+        value_objects::task_spec::{AuthPlacement, RequestMethod, TaskSpec},
+    },
+};
+
+const TUSHARE_API: &str = "http://api.tushare.pro";
+
+// error codes reported in the body of a Tushare response
+const INVALID_ARGUMENT: i64 = -2001;
+const RATE_LIMITED: i64 = 40203;
+
+pub struct TushareAdapter {
+    api_endpoint: Url,
+}
+
+impl TushareAdapter {
+    pub fn new(api_endpoint: Url) -> Self {
+        Self { api_endpoint }
+    }
+
+    /// Tushare reports the per-minute and the daily limit with the same code, only the message tells them apart:
+    /// `抱歉，您每分钟最多访问该接口500次，权限的具体详情访问：https://tushare.pro/document/1?doc_id=108。`
+    /// `抱歉，您每天最多访问该接口100000次，权限的具体详情访问：https://tushare.pro/document/1?doc_id=108。`
+    /// Any other limit, e.g. per hour, is throttling that passes before the day is over.
+    fn is_daily_limit(message: &str) -> bool {
+        return message.contains("每天最多访问");
+    }
+}
+
+impl Default for TushareAdapter {
+    fn default() -> Self {
+        Self::new(Url::parse(TUSHARE_API).expect("Tushare API endpoint should be a valid url"))
+    }
+}
+
+impl SourceAdapter for TushareAdapter {
+    fn build_request(&self, dataset: &Dataset, params: &Value) -> Result<TaskSpec, TaskCreationError> {
+        // Tushare names the API in the body, there is no endpoint path to bind
+        dataset.check_params(params, &[])?;
+        let payload = json!({
+            "api_name": dataset.name(),
+            "params": params,
+            "fields": dataset.schema().column_names().join(","),
+        });
+        let mut task_spec = TaskSpec::default();
+        task_spec
+            .set_request_endpoint(self.api_endpoint.clone())
+            .set_request_method(RequestMethod::Post)
+            .set_payload(Some(payload))
+            // the sender adds the key of the data source as the `token` argument
+            .set_auth_placement(Some(AuthPlacement::BodyField { name: "token".to_string() }));
+        return Ok(task_spec);
+    }
+
+    fn check_response(&self, response: &Value) -> Result<(), RequestError> {
+        let Some(code) = response.get("code").and_then(Value::as_i64) else {
+            return Err(RequestError::InvalidResponse("Tushare response carries no code".to_string()));
+        };
+        let message = response.get("msg").and_then(Value::as_str).unwrap_or_default().to_string();
+        match code {
+            0 => return Ok(()),
+            RATE_LIMITED if Self::is_daily_limit(&message) => return Err(RequestError::DailyLimitExceeded(message)),
+            RATE_LIMITED => return Err(RequestError::TooFrequent(None)),
+            INVALID_ARGUMENT => return Err(RequestError::InvalidArgument(message)),
+            _ => return Err(RequestError::Rejected(format!("{} {}", code, message))),
+        }
+    }
+
+    /// Zip `fields` with each of `items`, the columns missing from the response are null.
+    /// Without a schema the rows keep every field as received.
+    fn parse_rows(&self, dataset: &Dataset, response: &Value) -> Result<Vec<Row>, RequestError> {
+        let invalid = |reason: &str| RequestError::InvalidResponse(format!("Tushare {}", reason));
+        let fields: Vec<&str> = response
+            .pointer("/data/fields")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("response carries no fields"))?
+            .iter()
+            .map(|field| field.as_str().ok_or_else(|| invalid("field names should be strings")))
+            .collect::<Result<_, _>>()?;
+        let items = response
+            .pointer("/data/items")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("response carries no items"))?;
+
+        let mut rows = Vec::with_capacity(items.len());
+        for item in items {
+            let values = item.as_array().filter(|values| values.len() == fields.len());
+            let values = values.ok_or_else(|| invalid("item does not match its fields"))?;
+            let record: Row = fields.iter().map(|field| field.to_string()).zip(values.iter().cloned()).collect();
+            rows.push(dataset.schema().typed_row(&record).map_err(|err| invalid(&err.to_string()))?);
+        }
+        return Ok(rows);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::data_source::value_object::{api_param::APIParam, data_schema::Column};
+
+    fn daily() -> Dataset {
+        let mut dataset = Dataset::default();
+        dataset.set_name("daily".to_string());
+        dataset
+            .add_api_params(&vec![
+                APIParam::new("ts_code", "", "str", false, None).unwrap(),
+                APIParam::new("trade_date", "", "str", true, None).unwrap(),
+            ])
+            .unwrap();
+        dataset.add_columns_to_schema(&vec![
+            Column::new("ts_code", "str", "").unwrap(),
+            Column::new("trade_date", "str", "").unwrap(),
+            Column::new("vol", "int", "").unwrap(),
+        ]);
+        return dataset;
+    }
+
+    #[test]
+    fn it_should_request_the_schema_fields_of_the_dataset() {
+        let spec = TushareAdapter::default()
+            .build_request(&daily(), &json!({"trade_date": "20230621"}))
+            .unwrap();
+
+        assert_eq!(*spec.request_method(), RequestMethod::Post);
+        assert_eq!(*spec.auth_placement(), Some(AuthPlacement::BodyField { name: "token".to_string() }));
+        assert_eq!(
+            *spec.payload(),
+            Some(json!({"api_name": "daily", "params": {"trade_date": "20230621"}, "fields": "trade_date,ts_code,vol"}))
+        );
+
+        let missing = TushareAdapter::default().build_request(&daily(), &json!({"ts_code": "000001.SZ"})).unwrap_err();
+        assert!(matches!(missing, TaskCreationError::MissingParam(name) if name == "trade_date"));
+        let unused = TushareAdapter::default().build_request(&daily(), &json!({"trade_date": "20230621", "adj": "qfq"}));
+        assert!(matches!(unused, Err(TaskCreationError::UnusedParam(name)) if name == "adj"));
+    }
+
+    #[test]
+    fn it_should_map_error_codes_to_request_errors() {
+        let adapter = TushareAdapter::default();
+        let response = |code: i64, msg: &str| json!({"code": code, "msg": msg, "data": null});
+
+        assert_eq!(adapter.check_response(&response(0, "")), Ok(()));
+        assert_eq!(
+            adapter.check_response(&response(40203, "抱歉，您每分钟最多访问该接口500次")),
+            Err(RequestError::TooFrequent(None))
+        );
+        assert_eq!(
+            adapter.check_response(&response(40203, "抱歉，您每小时最多访问该接口20次")),
+            Err(RequestError::TooFrequent(None))
+        );
+        assert!(matches!(
+            adapter.check_response(&response(40203, "抱歉，您每天最多访问该接口100000次")),
+            Err(RequestError::DailyLimitExceeded(_))
+        ));
+        assert!(matches!(adapter.check_response(&response(-2001, "参数错误")), Err(RequestError::InvalidArgument(_))));
+        assert!(matches!(adapter.check_response(&response(40101, "您的token不对")), Err(RequestError::Rejected(_))));
+    }
+
+    #[test]
+    fn it_should_zip_fields_and_items_into_typed_rows() {
+        let response = json!({
+            "code": 0,
+            "msg": "",
+            "data": {
+                "fields": ["ts_code", "trade_date", "close", "vol"],
+                "items": [["000001.SZ", "20230621", 11.2, "123"], ["600000.SH", "20230621", 7.1, null]]
+            }
+        });
+
+        let rows = TushareAdapter::default().parse_rows(&daily(), &response).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(Value::Object(rows[0].clone()), json!({"ts_code": "000001.SZ", "trade_date": "20230621", "vol": 123}));
+        assert_eq!(rows[1]["vol"], Value::Null);
+    }
+}
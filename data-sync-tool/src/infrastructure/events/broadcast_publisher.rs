@@ -0,0 +1,29 @@
+//! Broadcast Event Publisher
+//! Hands synchronization events to every subscriber, e.g. the alerting of the application
+
+use tokio::sync::broadcast;
+
+use crate::domain::synchronization::events::sync_events::{EventPublisher, SyncEvent};
+
+pub struct BroadcastEventPublisher {
+    sender: broadcast::Sender<SyncEvent>,
+}
+
+impl BroadcastEventPublisher {
+    /// Subscribers lagging more than `capacity` events behind miss the oldest ones
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SyncEvent> {
+        return self.sender.subscribe();
+    }
+}
+
+impl EventPublisher for BroadcastEventPublisher {
+    fn publish(&self, event: SyncEvent) {
+        // nobody listening is not an error
+        let _ = self.sender.send(event);
+    }
+}
@@ -0,0 +1 @@
+pub mod broadcast_publisher;
\ No newline at end of file
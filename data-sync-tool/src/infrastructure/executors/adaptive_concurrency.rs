@@ -0,0 +1,100 @@
+//! Adaptive Concurrency Controller
+//! Keeps the requests in flight to each data source within its AIMD limit
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::Duration,
+};
+
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::domain::synchronization::{
+    adaptive_concurrency::ConcurrencyLimit, sync_task::FailureReason, value_objects::sync_config::AdaptiveConcurrency,
+};
+
+#[derive(Default)]
+pub struct AdaptiveConcurrencyController {
+    limits: Mutex<HashMap<Uuid, ConcurrencyLimit>>,
+    released: Notify,
+}
+
+impl AdaptiveConcurrencyController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests currently allowed in flight to the data source, None until it was requested adaptively
+    pub fn concurrency(&self, datasource_id: &Uuid) -> Option<u32> {
+        let limits = self.limits.lock().expect("Concurrency limits should never be poisoned");
+        return limits.get(datasource_id).map(ConcurrencyLimit::limit);
+    }
+
+    /// Wait until the limit of the data source allows one more request
+    pub async fn acquire<'c>(&'c self, datasource_id: Uuid, policy: &AdaptiveConcurrency, cap: u32) -> ConcurrencySlot<'c> {
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            // registered before checking, so a release in between is not missed
+            released.as_mut().enable();
+            {
+                let mut limits = self.limits.lock().expect("Concurrency limits should never be poisoned");
+                let limit = limits
+                    .entry(datasource_id)
+                    .or_insert_with(|| ConcurrencyLimit::new(policy, cap));
+                if let Some(generation) = limit.try_acquire(cap) {
+                    return ConcurrencySlot {
+                        controller: self,
+                        datasource_id,
+                        policy: policy.clone(),
+                        cap,
+                        generation,
+                        released: false,
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+}
+
+/// One request in flight, the slot is given back when the outcome is recorded or when dropped
+pub struct ConcurrencySlot<'c> {
+    controller: &'c AdaptiveConcurrencyController,
+    datasource_id: Uuid,
+    policy: AdaptiveConcurrency,
+    cap: u32,
+    generation: u64,
+    released: bool,
+}
+
+impl ConcurrencySlot<'_> {
+    /// Adapt the limit to the outcome of the request and how long the remote took to answer it
+    pub fn complete(mut self, latency: Duration, failure: Option<FailureReason>) {
+        self.release(Some((latency, failure)));
+    }
+
+    fn release(&mut self, outcome: Option<(Duration, Option<FailureReason>)>) {
+        if self.released {
+            return;
+        }
+        self.released = true;
+        {
+            let mut limits = self.controller.limits.lock().expect("Concurrency limits should never be poisoned");
+            if let Some(limit) = limits.get_mut(&self.datasource_id) {
+                match outcome {
+                    Some((latency, failure)) => limit.release(&self.policy, self.cap, self.generation, latency, failure),
+                    None => limit.abandon(),
+                }
+            }
+        }
+        self.controller.released.notify_waiters();
+    }
+}
+
+impl Drop for ConcurrencySlot<'_> {
+    fn drop(&mut self) {
+        self.release(None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Task Cancellation
+//! Lets anyone holding the registry stop tasks while an executor runs them
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::domain::synchronization::{sync_task::SyncTask, task_executor::CancelScope};
+
+struct RunningTask {
+    task_id: Uuid,
+    sync_plan_id: Option<Uuid>,
+    datasource_id: Option<Uuid>,
+    cancelled: watch::Sender<bool>,
+}
+
+impl RunningTask {
+    fn is_in(&self, scope: &CancelScope) -> bool {
+        match scope {
+            CancelScope::Task(task_id) => self.task_id == *task_id,
+            CancelScope::Plan(plan_id) => self.sync_plan_id == Some(*plan_id),
+            CancelScope::DataSource(datasource_id) => self.datasource_id == Some(*datasource_id),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CancellationRegistry {
+    // keyed by registration, the same task may run in several executions at once
+    running: Mutex<HashMap<u64, RunningTask>>,
+    next_registration: AtomicU64,
+}
+
+impl CancellationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the task cancellable until the returned guard is dropped
+    pub fn register(&self, task: &SyncTask) -> Cancellation<'_> {
+        let registration = self.next_registration.fetch_add(1, Ordering::Relaxed);
+        let (cancelled, receiver) = watch::channel(false);
+        let running_task = RunningTask {
+            task_id: *task.id(),
+            sync_plan_id: *task.sync_plan_id(),
+            datasource_id: *task.datasource_id(),
+            cancelled,
+        };
+        self.running
+            .lock()
+            .expect("Running tasks should never be poisoned")
+            .insert(registration, running_task);
+        return Cancellation {
+            registry: self,
+            registration,
+            receiver,
+        };
+    }
+
+    /// Tell the registered tasks in scope to stop, returns how many were not told before
+    pub fn cancel(&self, scope: CancelScope) -> usize {
+        let running = self.running.lock().expect("Running tasks should never be poisoned");
+        return running
+            .values()
+            .filter(|running_task| running_task.is_in(&scope))
+            .filter(|running_task| !running_task.cancelled.send_replace(true))
+            .count();
+    }
+}
+
+/// Registration of one running task
+pub struct Cancellation<'r> {
+    registry: &'r CancellationRegistry,
+    registration: u64,
+    receiver: watch::Receiver<bool>,
+}
+
+impl Cancellation<'_> {
+    /// Resolves once the task is cancelled
+    pub async fn cancelled(&mut self) {
+        // the sender lives in the registry until this registration is dropped
+        let _ = self.receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Drop for Cancellation<'_> {
+    fn drop(&mut self) {
+        self.registry
+            .running
+            .lock()
+            .expect("Running tasks should never be poisoned")
+            .remove(&self.registration);
+    }
+}
@@ -0,0 +1,104 @@
+//! Circuit Breakers
+//! One breaker per data source, shared by every run of the executor
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use crate::domain::synchronization::{
+    circuit_breaker::{CircuitBreaker, CircuitState, Pass},
+    events::sync_events::{EventPublisher, SyncEvent},
+    sync_task::FailureReason,
+    value_objects::sync_config::CircuitBreakerPolicy,
+};
+
+#[derive(Default)]
+pub struct CircuitBreakers {
+    breakers: Mutex<HashMap<Uuid, CircuitBreaker>>,
+    event_publisher: Option<Arc<dyn EventPublisher>>,
+}
+
+impl CircuitBreakers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish the breakers opening and closing
+    pub fn with_event_publisher(mut self, event_publisher: Arc<dyn EventPublisher>) -> Self {
+        self.event_publisher = Some(event_publisher);
+        return self;
+    }
+
+    pub fn state(&self, datasource_id: &Uuid) -> Option<CircuitState> {
+        let breakers = self.breakers.lock().expect("Circuit breakers should never be poisoned");
+        return breakers.get(datasource_id).map(CircuitBreaker::state);
+    }
+
+    /// Whether a request to the data source may be sent now, or how long to wait before asking again
+    pub fn try_pass(&self, datasource_id: Uuid) -> Result<CircuitPass<'_>, Duration> {
+        let (decision, event) = {
+            let mut breakers = self.breakers.lock().expect("Circuit breakers should never be poisoned");
+            breakers
+                .entry(datasource_id)
+                .or_insert_with(|| CircuitBreaker::new(datasource_id))
+                .try_pass(Instant::now())
+        };
+        self.publish(event.map(SyncEvent::CircuitBreaker));
+        return decision.map(|pass| CircuitPass {
+            breakers: self,
+            datasource_id,
+            probe: pass == Pass::Probe,
+        });
+    }
+
+    fn record(&self, datasource_id: Uuid, policy: &CircuitBreakerPolicy, failure: Option<FailureReason>) {
+        let event = {
+            let mut breakers = self.breakers.lock().expect("Circuit breakers should never be poisoned");
+            breakers
+                .entry(datasource_id)
+                .or_insert_with(|| CircuitBreaker::new(datasource_id))
+                .record(policy, failure, Instant::now())
+        };
+        self.publish(event.map(SyncEvent::CircuitBreaker));
+    }
+
+    fn abandon(&self, datasource_id: &Uuid) {
+        let mut breakers = self.breakers.lock().expect("Circuit breakers should never be poisoned");
+        if let Some(breaker) = breakers.get_mut(datasource_id) {
+            breaker.abandon();
+        }
+    }
+
+    fn publish(&self, event: Option<SyncEvent>) {
+        if let (Some(event), Some(event_publisher)) = (event, &self.event_publisher) {
+            event_publisher.publish(event);
+        }
+    }
+}
+
+/// A request let through by the breaker of a data source.
+/// Dropped without its outcome recorded, a probe is given back so the breaker lets the next one through.
+pub struct CircuitPass<'c> {
+    breakers: &'c CircuitBreakers,
+    datasource_id: Uuid,
+    probe: bool,
+}
+
+impl CircuitPass<'_> {
+    pub fn record(mut self, policy: &CircuitBreakerPolicy, failure: Option<FailureReason>) {
+        self.probe = false;
+        self.breakers.record(self.datasource_id, policy, failure);
+    }
+}
+
+impl Drop for CircuitPass<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breakers.abandon(&self.datasource_id);
+        }
+    }
+}
@@ -0,0 +1,1366 @@
+//! Concurrent Task Executor
+//! Runs synchronization tasks on tokio, keeping at most `Quota::max_concurrent_task` requests in flight,
+//! or fewer when the sync config asks for adaptive concurrency
+
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+use futures::future::join_all;
+use serde_json::Value;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use super::{
+    adaptive_concurrency::AdaptiveConcurrencyController, cancellation::CancellationRegistry, circuit_breakers::CircuitBreakers,
+    credential_rotation::CredentialRotation, dry_run_sender::DryRunSender, rate_limiter::RateLimiter,
+};
+use crate::domain::{
+    data_source::{dataset::Dataset, source_adapter::SourceAdapter, value_object::api_credential::ApiCredential},
+    synchronization::{
+        checkpoint::SyncCheckpoint,
+        custom_errors::{QuotaError, RequestError},
+        rate_limiter::QuotaOwner,
+        repository::{CheckpointRepository, FingerprintRepository},
+        sync_task::{FailureReason, SyncStatus, SyncTask},
+        task_executor::{CancelScope, RequestSender, TaskExecutor},
+        value_objects::{
+            dry_run_report::DryRunReport,
+            execution_result::ExecutionResult,
+            sync_config::{RetryDecision, SyncConfig},
+            task_spec::NextPages,
+        },
+    },
+};
+
+/// State shared by the tasks of one `execute_all` run
+struct ExecutionRun<'c> {
+    permits: Semaphore,
+    sync_config: &'c SyncConfig,
+    // set once the remaining tasks must not be dispatched any more
+    halt_reason: OnceLock<String>,
+}
+
+impl<'c> ExecutionRun<'c> {
+    fn new(sync_config: &'c SyncConfig) -> Self {
+        Self {
+            permits: Semaphore::new(ConcurrentTaskExecutor::max_concurrency(sync_config)),
+            sync_config,
+            halt_reason: OnceLock::new(),
+        }
+    }
+
+    fn halt(&self, reason: &str) {
+        let _ = self.halt_reason.set(reason.to_string());
+    }
+}
+
+pub struct ConcurrentTaskExecutor {
+    request_sender: Arc<dyn RequestSender>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    checkpoint_repository: Option<Arc<dyn CheckpointRepository>>,
+    credential_rotation: Option<Arc<CredentialRotation>>,
+    fingerprint_repository: Option<Arc<dyn FingerprintRepository>>,
+    // only used by the runs whose sync config asks for adaptive concurrency
+    adaptive_concurrency: AdaptiveConcurrencyController,
+    // only used by the runs whose sync config has a circuit breaker policy
+    circuit_breakers: Arc<CircuitBreakers>,
+    cancellations: Arc<CancellationRegistry>,
+    // datasets by id, the adapters of their data sources parse the data of their tasks into rows
+    datasets: HashMap<Uuid, Dataset>,
+    adapters: HashMap<Uuid, Arc<dyn SourceAdapter>>,
+    // latency assumed for every request when estimating the duration of a dry run
+    dry_run_latency: Duration,
+}
+
+impl ConcurrentTaskExecutor {
+    pub fn new(request_sender: Arc<dyn RequestSender>) -> Self {
+        Self {
+            request_sender,
+            rate_limiter: None,
+            checkpoint_repository: None,
+            credential_rotation: None,
+            fingerprint_repository: None,
+            adaptive_concurrency: AdaptiveConcurrencyController::new(),
+            circuit_breakers: Arc::new(CircuitBreakers::new()),
+            cancellations: Arc::new(CancellationRegistry::new()),
+            datasets: HashMap::new(),
+            adapters: HashMap::new(),
+            dry_run_latency: Duration::from_secs(1),
+        }
+    }
+
+    /// Pace requests with a rate limiter, usually shared by all executors of the application
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        return self;
+    }
+
+    /// Resume plans from their checkpoints and save a checkpoint after every run
+    pub fn with_checkpoints(mut self, checkpoint_repository: Arc<dyn CheckpointRepository>) -> Self {
+        self.checkpoint_repository = Some(checkpoint_repository);
+        return self;
+    }
+
+    /// Send the requests of data sources with registered credentials with their keys in turn
+    pub fn with_credentials(mut self, credential_rotation: Arc<CredentialRotation>) -> Self {
+        self.credential_rotation = Some(credential_rotation);
+        return self;
+    }
+
+    /// Skip the requests that already succeeded within the dedup window of the sync config
+    pub fn with_fingerprints(mut self, fingerprint_repository: Arc<dyn FingerprintRepository>) -> Self {
+        self.fingerprint_repository = Some(fingerprint_repository);
+        return self;
+    }
+
+    /// Share the breakers of the data sources, e.g. with the executors of other plans or with monitoring
+    pub fn with_circuit_breakers(mut self, circuit_breakers: Arc<CircuitBreakers>) -> Self {
+        self.circuit_breakers = circuit_breakers;
+        return self;
+    }
+
+    /// Share the registry the running tasks are cancelled through, e.g. with a CLI command or an API handler
+    pub fn with_cancellations(mut self, cancellations: Arc<CancellationRegistry>) -> Self {
+        self.cancellations = cancellations;
+        return self;
+    }
+
+    /// Parse the data of the tasks of these datasets into rows, with the adapters of their data sources
+    pub fn with_datasets(mut self, datasets: &[Dataset]) -> Self {
+        for dataset in datasets {
+            self.datasets.insert(*dataset.id(), dataset.clone());
+        }
+        return self;
+    }
+
+    /// Check the responses of the tasks of a data source and parse their rows through its adapter
+    pub fn with_adapter(mut self, datasource_id: Uuid, adapter: Arc<dyn SourceAdapter>) -> Self {
+        self.adapters.insert(datasource_id, adapter);
+        return self;
+    }
+
+    /// How long a request is assumed to take when estimating a dry run
+    pub fn with_dry_run_latency(mut self, dry_run_latency: Duration) -> Self {
+        self.dry_run_latency = dry_run_latency;
+        return self;
+    }
+
+    /// Requests currently allowed in flight to the data source by adaptive concurrency, for monitoring
+    pub fn effective_concurrency(&self, datasource_id: &Uuid) -> Option<u32> {
+        return self.adaptive_concurrency.concurrency(datasource_id);
+    }
+
+    fn plan_ids(tasks: &[SyncTask]) -> BTreeSet<Uuid> {
+        tasks.iter().filter_map(|task| *task.sync_plan_id()).collect()
+    }
+
+    async fn restore_checkpoints(&self, tasks: &mut [SyncTask]) {
+        let Some(checkpoint_repository) = &self.checkpoint_repository else {
+            return;
+        };
+        for plan_id in Self::plan_ids(tasks) {
+            // a checkpoint that cannot be read only costs re-requesting the plan's data
+            if let Ok(Some(checkpoint)) = checkpoint_repository.get_checkpoint(&plan_id).await {
+                checkpoint.restore(tasks);
+            }
+        }
+    }
+
+    async fn save_checkpoints(&self, tasks: &[SyncTask]) {
+        let Some(checkpoint_repository) = &self.checkpoint_repository else {
+            return;
+        };
+        for plan_id in Self::plan_ids(tasks) {
+            let checkpoint = SyncCheckpoint::from_tasks(plan_id, tasks);
+            // a plan that ran to completion starts from scratch next time
+            let saved = if checkpoint.is_complete() {
+                checkpoint_repository.delete_checkpoint(&plan_id).await
+            } else {
+                checkpoint_repository.save_checkpoint(&checkpoint).await
+            };
+            // the results are already in hand, a lost checkpoint only costs requesting them again on resume
+            if let Err(error) = saved {
+                log::warn!("Failed to save the checkpoint of plan {}: {}", plan_id, error);
+            }
+        }
+    }
+
+    /// When the task's request already succeeded within the dedup window of the sync config
+    async fn recent_completion(&self, task: &SyncTask, sync_config: &SyncConfig) -> Option<DateTime<Local>> {
+        let fingerprint_repository = self.fingerprint_repository.as_ref()?;
+        let dedup_window = chrono::Duration::from_std((*sync_config.dedup_window())?).ok()?;
+        // an unreadable index only costs sending the request again
+        let completion_time = fingerprint_repository
+            .get_completion_time(&task.spec().fingerprint())
+            .await
+            .ok()??;
+        return (Local::now() - completion_time <= dedup_window).then_some(completion_time);
+    }
+
+    async fn record_completion(&self, task: &SyncTask) {
+        let Some(fingerprint_repository) = &self.fingerprint_repository else {
+            return;
+        };
+        if *task.status() == SyncStatus::Finished {
+            let fingerprint = task.spec().fingerprint();
+            if let Err(error) = fingerprint_repository.record_completion(&fingerprint, Local::now()).await {
+                log::warn!("Failed to record the completion of request {}: {}", fingerprint, error);
+            }
+        }
+    }
+
+    /// Settle a task whose run was cancelled, the pages it received are dropped with the run
+    fn settle_cancelled(&self, task: &mut SyncTask) -> ExecutionResult {
+        let message = match task.attempts() {
+            0 => "Cancelled before any request was sent",
+            _ => "Cancelled, the data received so far was discarded",
+        };
+        task.cancel();
+        task.set_end_time(Some(Local::now()))
+            .set_result_message(Some(message.to_string()));
+        return ExecutionResult::from_task(task, Value::Null);
+    }
+
+    fn skip(task: &mut SyncTask, reason: &str) -> ExecutionResult {
+        task.cancel();
+        task.set_result_message(Some(reason.to_string()));
+        return ExecutionResult::from_task(task, Value::Null);
+    }
+
+    /// A quota without a concurrency limit runs the tasks one at a time
+    fn max_concurrency(sync_config: &SyncConfig) -> usize {
+        let max_concurrent_task = *sync_config.sync_quota().max_concurrent_task();
+        return (max_concurrent_task as usize).max(1);
+    }
+
+    /// Wait until the task may send one more request, with the key to send it with when the data source has credentials
+    async fn acquire_budget(&self, task: &SyncTask, run: &ExecutionRun<'_>) -> Result<Option<ApiCredential>, QuotaError> {
+        let datasource_id = task.datasource_id().unwrap_or_default();
+        // the quota of the data source holds whichever key is used, then the key must have room of its own
+        let source = QuotaOwner::DataSource(datasource_id);
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(source, run.sync_config.sync_quota()).await?;
+        }
+        let Some(credential_rotation) = &self.credential_rotation else {
+            return Ok(None);
+        };
+        match credential_rotation.acquire(datasource_id).await {
+            Ok(credential) => return Ok(credential),
+            Err(err) => {
+                // no key sends the request, the data source must not pay for it
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    rate_limiter.refund(source).await?;
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    /// Fail on the errors the adapter of the task's data source finds in a response
+    fn checked(&self, task: &SyncTask, outcome: Result<Value, RequestError>) -> Result<Value, RequestError> {
+        let Some(adapter) = task.datasource_id().and_then(|datasource_id| self.adapters.get(&datasource_id)) else {
+            return outcome;
+        };
+        match outcome {
+            Ok(response) => {
+                // remotes like Tushare answer errors with HTTP 200, the retry policy needs them as request errors
+                adapter.check_response(&response)?;
+                return Ok(response);
+            }
+            // the body of an error status tells more than the status when the adapter knows it
+            Err(RequestError::ErrorResponse { status, retry_after, body }) => {
+                adapter.check_response(&body)?;
+                return Err(RequestError::ErrorResponse { status, retry_after, body });
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    /// Send one request on behalf of the task, a failure is recorded on the task
+    async fn send_once(
+        &self,
+        task: &mut SyncTask,
+        payload: Option<&Value>,
+        credential: Option<&ApiCredential>,
+    ) -> Result<Value, RequestError> {
+        task.start();
+        task.set_credential_id(credential.map(|credential| *credential.id()));
+        let mut request_spec = task.spec().clone();
+        request_spec
+            .set_payload(payload.cloned())
+            .set_api_key(credential.map(|credential| credential.api_key().clone()));
+        let outcome = self.checked(task, self.request_sender.send(&request_spec).await);
+        if let Err(err) = &outcome {
+            task.fail(err.failure_reason(), &err.to_string());
+        }
+        return outcome;
+    }
+
+    /// Run a task until all its pages are received or the retry policy gives up on it
+    async fn run_task(&self, task: &mut SyncTask, run: &ExecutionRun<'_>) -> ExecutionResult {
+        let retry_policy = run.sync_config.retry_policy();
+        let page_size = *run.sync_config.sync_quota().max_line_per_request() as usize;
+        // without a row cap there is no way to tell a truncated page from the last one
+        let pagination = task
+            .spec()
+            .pagination()
+            .clone()
+            .filter(|pagination| page_size > 0 || !pagination.needs_page_size());
+        let mut requests = VecDeque::from([match &pagination {
+            Some(pagination) => Some(pagination.first_page(task.spec().payload().as_ref(), page_size)),
+            None => task.spec().payload().clone(),
+        }]);
+        let mut merged = None;
+        let mut attempts = 0;
+        let mut waited = Duration::ZERO;
+
+        let datasource_id = task.datasource_id().unwrap_or_default();
+        while let Some(payload) = requests.pop_front() {
+            let permit = run.permits.acquire().await.expect("Semaphore should never be closed");
+            if let Some(reason) = run.halt_reason.get() {
+                return Self::skip(task, reason);
+            }
+            let circuit_breaker = run.sync_config.circuit_breaker().as_ref();
+            // a probe let through but never sent is given back when the pass is dropped, cancellation included
+            let mut pass = None;
+            if let Some(policy) = circuit_breaker {
+                match self.circuit_breakers.try_pass(datasource_id) {
+                    Ok(granted) => pass = Some((granted, policy)),
+                    Err(_) if !*policy.park_while_open() => {
+                        let message = format!("Circuit breaker of data source {} is open", datasource_id);
+                        task.fail(FailureReason::Other, &message);
+                        return ExecutionResult::from_task(task, Value::Null);
+                    }
+                    Err(retry_in) => {
+                        // parked tasks hold no slot while waiting for the breaker
+                        drop(permit);
+                        task.wait();
+                        requests.push_front(payload);
+                        tokio::time::sleep(retry_in).await;
+                        continue;
+                    }
+                }
+            }
+            let credential = match self.acquire_budget(task, run).await {
+                Ok(credential) => credential,
+                Err(QuotaError::DailyLimitReached) => {
+                    run.halt(&QuotaError::DailyLimitReached.to_string());
+                    return Self::skip(task, &QuotaError::DailyLimitReached.to_string());
+                }
+                Err(err) => {
+                    task.fail(FailureReason::Other, &err.to_string());
+                    return ExecutionResult::from_task(task, Value::Null);
+                }
+            };
+
+            // taken once the budget is granted, so the latency the limit adapts to is the remote's alone
+            let slot = match run.sync_config.adaptive_concurrency() {
+                Some(policy) => {
+                    let cap = Self::max_concurrency(run.sync_config) as u32;
+                    Some(self.adaptive_concurrency.acquire(datasource_id, policy, cap).await)
+                }
+                None => None,
+            };
+
+            attempts += 1;
+            let sent_at = Instant::now();
+            let outcome = self.send_once(task, payload.as_ref(), credential.as_ref()).await;
+            let failure = outcome.as_ref().err().map(|err| err.failure_reason());
+            if let Some(slot) = slot {
+                slot.complete(sent_at.elapsed(), failure);
+            }
+            if let Some((pass, policy)) = pass {
+                pass.record(policy, failure);
+            }
+            let err = match outcome {
+                Ok(response) => {
+                    match (&pagination, &payload) {
+                        (Some(pagination), Some(page)) => {
+                            match pagination.next_pages(page, &response, page_size) {
+                                NextPages::Done => pagination.merge(&mut merged, response),
+                                NextPages::Continue(next_pages) => {
+                                    requests.extend(next_pages.into_iter().map(Some));
+                                    pagination.merge(&mut merged, response);
+                                }
+                                NextPages::Replace(narrower_pages) => {
+                                    requests.extend(narrower_pages.into_iter().map(Some));
+                                }
+                                // finishing would pass a partial result off as the whole of it
+                                NextPages::Truncated => {
+                                    let message = format!("Page may be truncated at {} rows, too narrow to split", page_size);
+                                    task.fail(FailureReason::Other, &message);
+                                    return ExecutionResult::from_task(task, Value::Null);
+                                }
+                            }
+                        }
+                        _ => merged = Some(response),
+                    }
+                    attempts = 0;
+                    continue;
+                }
+                Err(err) => err,
+            };
+            // the slot is free for other tasks while this one backs off
+            drop(permit);
+
+            // the remote counts the daily limit per key, so the request moves on to the next key
+            if let (Some(credential), Some(credential_rotation)) = (&credential, &self.credential_rotation) {
+                if err.failure_reason() == FailureReason::DailyLimitExceeded
+                    && credential_rotation.exhaust(datasource_id, credential.id()).await
+                {
+                    requests.push_front(payload);
+                    continue;
+                }
+            }
+
+            // pages received so far are discarded when the task is given up
+            match retry_policy.decide(err.failure_reason(), attempts, waited, err.retry_after()) {
+                RetryDecision::RetryAfter(backoff) => {
+                    task.wait();
+                    requests.push_front(payload);
+                    tokio::time::sleep(backoff).await;
+                }
+                RetryDecision::Requeue(cool_down) => {
+                    task.wait();
+                    requests.push_front(payload);
+                    waited += cool_down;
+                    tokio::time::sleep(cool_down).await;
+                }
+                RetryDecision::Drop => return ExecutionResult::from_task(task, Value::Null),
+                RetryDecision::CancelRemaining => {
+                    run.halt(&format!("Synchronization cancelled: {}", err));
+                    return ExecutionResult::from_task(task, Value::Null);
+                }
+            }
+        }
+
+        return self.complete(task, merged.unwrap_or(Value::Null));
+    }
+
+    /// Finish a task with the data it received, parsed into rows when its dataset and the adapter of its source are known
+    fn complete(&self, task: &mut SyncTask, data: Value) -> ExecutionResult {
+        let dataset = task.dataset_id().and_then(|dataset_id| self.datasets.get(&dataset_id));
+        let adapter = task.datasource_id().and_then(|datasource_id| self.adapters.get(&datasource_id));
+        let parsed = dataset.zip(adapter).map(|(dataset, adapter)| adapter.parse_rows(dataset, &data));
+        match parsed {
+            Some(Err(err)) => {
+                task.fail(FailureReason::Other, &format!("Failed to parse rows: {}", err));
+                return ExecutionResult::from_task(task, data);
+            }
+            Some(Ok(rows)) => {
+                task.finished();
+                let mut result = ExecutionResult::from_task(task, data);
+                result.set_rows(Some(rows));
+                return result;
+            }
+            None => {
+                task.finished();
+                return ExecutionResult::from_task(task, data);
+            }
+        }
+    }
+
+    /// Run a task of `run` unless it is settled or succeeded within the dedup window, it can be cancelled from outside
+    async fn run_one(&self, task: &mut SyncTask, run: &ExecutionRun<'_>) -> ExecutionResult {
+        if task.is_settled() {
+            return ExecutionResult::from_task(task, Value::Null);
+        }
+        if let Some(completion_time) = self.recent_completion(task, run.sync_config).await {
+            task.finished();
+            task.set_result_message(Some(format!("Skipped, the same request succeeded at {}", completion_time)));
+            return ExecutionResult::from_task(task, Value::Null);
+        }
+        let mut cancellation = self.cancellations.register(task);
+        // dropping the run aborts the request in flight
+        let outcome = tokio::select! {
+            result = self.run_task(task, run) => Some(result),
+            _ = cancellation.cancelled() => None,
+        };
+        let Some(result) = outcome else {
+            return self.settle_cancelled(task);
+        };
+        self.record_completion(task).await;
+        return result;
+    }
+}
+
+#[async_trait]
+impl TaskExecutor for ConcurrentTaskExecutor {
+    async fn execute_all(&self, tasks: &mut [SyncTask], sync_config: &SyncConfig) -> Vec<ExecutionResult> {
+        self.restore_checkpoints(tasks).await;
+        let run = ExecutionRun::new(sync_config);
+        tasks.iter_mut().filter(|task| !task.is_settled()).for_each(|task| {
+            task.wait();
+        });
+
+        let results = join_all(tasks.iter_mut().map(|task| self.run_one(task, &run))).await;
+        self.save_checkpoints(tasks).await;
+        return results;
+    }
+
+    async fn execute(&self, task: &mut SyncTask, sync_config: &SyncConfig) -> ExecutionResult {
+        let run = ExecutionRun::new(sync_config);
+        if !task.is_settled() {
+            task.wait();
+        }
+        return self.run_one(task, &run).await;
+    }
+
+    async fn dry_run(&self, tasks: &[SyncTask], sync_config: &SyncConfig) -> DryRunReport {
+        // nothing is paced, checkpointed or remembered, the pacing is simulated on the recorded requests instead
+        let recorder = Arc::new(DryRunSender::default());
+        let executor = ConcurrentTaskExecutor::new(recorder.clone());
+        executor.execute_all(&mut tasks.to_vec(), sync_config).await;
+        let page_size = *sync_config.sync_quota().max_line_per_request() as usize;
+        let paginated_tasks = tasks
+            .iter()
+            .filter_map(|task| task.spec().pagination().as_ref())
+            .filter(|pagination| page_size > 0 || !pagination.needs_page_size())
+            .count();
+        let datasource_ids: BTreeSet<Uuid> = tasks.iter().map(|task| task.datasource_id().unwrap_or_default()).collect();
+        let mut used_today = 0;
+        if let Some(rate_limiter) = &self.rate_limiter {
+            // the quota is per data source, the one spent the most paces the whole run
+            for datasource_id in &datasource_ids {
+                let used = rate_limiter.used_today(&QuotaOwner::DataSource(*datasource_id)).await.unwrap_or_else(|err| {
+                    log::warn!("Failed to read the usage of data source {}: {}", datasource_id, err);
+                    0
+                });
+                used_today = used_today.max(used as usize);
+            }
+        }
+        return DryRunReport::new(
+            recorder.take_requests(),
+            paginated_tasks,
+            sync_config.sync_quota(),
+            used_today,
+            self.dry_run_latency,
+        );
+    }
+
+    async fn cancel(&self, task: &mut SyncTask) -> SyncStatus {
+        self.cancellations.cancel(CancelScope::Task(*task.id()));
+        match task.status() {
+            SyncStatus::Finished | SyncStatus::Failed => *task.status(),
+            _ => task.cancel(),
+        }
+    }
+
+    fn cancel_running(&self, scope: CancelScope) -> usize {
+        return self.cancellations.cancel(scope);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+    use url::Url;
+
+    use super::*;
+    use crate::domain::data_source::value_object::{
+        data_schema::Column, extraction_rules::ExtractionRules, rest_config::RestSourceConfig,
+    };
+    use crate::infrastructure::{
+        adapters::{nasdaq_data_link::NasdaqDataLinkAdapter, rest::RestAdapter},
+        net::http_client::HttpRequestSender,
+    };
+    use crate::domain::synchronization::{
+        custom_errors::RepositoryError,
+        events::sync_events::{CircuitBreakerEvent, EventPublisher, SyncEvent},
+        rate_limiter::DailyUsage,
+        repository::CheckpointRepository,
+        repository::QuotaUsageRepository,
+        value_objects::{
+            sync_config::{AdaptiveConcurrency, CircuitBreakerPolicy, Quota, RetryPolicy},
+            task_spec::{Pagination, TaskSpec},
+        },
+    };
+
+    #[derive(Default)]
+    struct CountingSender {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl RequestSender for CountingSender {
+        async fn send(&self, _spec: &TaskSpec) -> Result<Value, RequestError> {
+            let now_in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            return Ok(json!({"rows": 1}));
+        }
+    }
+
+    /// Fails every request with the given errors in turn, then succeeds
+    struct ScriptedSender {
+        errors: std::sync::Mutex<Vec<RequestError>>,
+    }
+
+    impl ScriptedSender {
+        fn failing_with(mut errors: Vec<RequestError>) -> Self {
+            errors.reverse();
+            Self {
+                errors: std::sync::Mutex::new(errors),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl RequestSender for ScriptedSender {
+        async fn send(&self, _spec: &TaskSpec) -> Result<Value, RequestError> {
+            match self.errors.lock().unwrap().pop() {
+                Some(err) => return Err(err),
+                None => return Ok(json!({"rows": 1})),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct InMemoryUsageRepository {
+        usage: std::sync::Mutex<Option<DailyUsage>>,
+    }
+
+    #[async_trait]
+    impl QuotaUsageRepository for InMemoryUsageRepository {
+        async fn get_daily_usage(&self, owner: &QuotaOwner) -> Result<Option<DailyUsage>, RepositoryError> {
+            let usage = self.usage.lock().unwrap().clone();
+            return Ok(usage.filter(|usage| usage.owner() == owner));
+        }
+
+        async fn save_daily_usage(&self, usage: &DailyUsage) -> Result<(), RepositoryError> {
+            *self.usage.lock().unwrap() = Some(usage.clone());
+            return Ok(());
+        }
+    }
+
+    fn config_with_concurrency(max_concurrent_task: u32) -> SyncConfig {
+        let mut quota = Quota::default();
+        quota.set_max_concurrent_task(max_concurrent_task);
+        let mut retry_policy = RetryPolicy::default();
+        retry_policy
+            .set_initial_backoff(Duration::from_millis(1))
+            .set_cool_down(Duration::from_millis(1));
+        let mut config = SyncConfig::default();
+        config.set_sync_quota(quota).set_retry_policy(retry_policy);
+        return config;
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_in_flight_requests_within_quota() {
+        let sender = Arc::new(CountingSender::default());
+        let executor = ConcurrentTaskExecutor::new(sender.clone());
+        let mut tasks: Vec<SyncTask> = (0..10).map(|_| SyncTask::default()).collect();
+
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(3)).await;
+
+        assert_eq!(results.len(), 10);
+        assert!(sender.max_in_flight.load(Ordering::SeqCst) <= 3);
+        assert!(tasks.iter().all(|task| *task.status() == SyncStatus::Finished));
+        let task_ids: Vec<_> = tasks.iter().map(|task| *task.id()).collect();
+        let result_ids: Vec<_> = results.iter().map(|result| *result.task_id()).collect();
+        assert_eq!(task_ids, result_ids);
+    }
+
+    #[tokio::test]
+    async fn it_should_retry_timeouts_until_the_request_succeeds() {
+        let sender = ScriptedSender::failing_with(vec![RequestError::Timeout, RequestError::TooFrequent(None)]);
+        let executor = ConcurrentTaskExecutor::new(Arc::new(sender));
+        let mut tasks = vec![SyncTask::default()];
+
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(1)).await;
+
+        assert_eq!(*results[0].status(), SyncStatus::Finished);
+        assert_eq!(*tasks[0].attempts(), 3);
+    }
+
+    #[tokio::test]
+    async fn it_should_drop_tasks_with_invalid_arguments_and_keep_going() {
+        let sender = ScriptedSender::failing_with(vec![RequestError::InvalidArgument("bad date".to_string())]);
+        let executor = ConcurrentTaskExecutor::new(Arc::new(sender));
+        let mut tasks = vec![SyncTask::default(), SyncTask::default()];
+
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(1)).await;
+
+        assert_eq!(*results[0].status(), SyncStatus::Failed);
+        assert_eq!(*tasks[0].failure_reason(), Some(FailureReason::InvalidArgument));
+        assert_eq!(*results[1].status(), SyncStatus::Finished);
+    }
+
+    #[tokio::test]
+    async fn it_should_cancel_remaining_tasks_when_remote_daily_limit_is_exceeded() {
+        let sender = ScriptedSender::failing_with(vec![RequestError::DailyLimitExceeded("quota used up".to_string())]);
+        let executor = ConcurrentTaskExecutor::new(Arc::new(sender));
+        let mut tasks = vec![SyncTask::default(), SyncTask::default(), SyncTask::default()];
+
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(1)).await;
+
+        assert_eq!(*results[0].status(), SyncStatus::Failed);
+        assert_eq!(*tasks[0].failure_reason(), Some(FailureReason::DailyLimitExceeded));
+        assert!(results[1..].iter().all(|result| *result.status() == SyncStatus::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn it_should_stop_dispatching_once_daily_limit_is_reached() {
+        let sender = Arc::new(CountingSender::default());
+        let usage_repository = Arc::new(InMemoryUsageRepository::default());
+        let rate_limiter = Arc::new(RateLimiter::new(usage_repository.clone()));
+        let executor = ConcurrentTaskExecutor::new(sender).with_rate_limiter(rate_limiter);
+        let datasource_id = Uuid::new_v4();
+        let mut tasks: Vec<SyncTask> = (0..5)
+            .map(|_| {
+                let mut task = SyncTask::default();
+                task.set_datasource_id(Some(datasource_id));
+                task
+            })
+            .collect();
+        let mut config = config_with_concurrency(1);
+        let mut quota = config.sync_quota().clone();
+        quota.set_daily_limit(3);
+        config.set_sync_quota(quota);
+
+        let results = executor.execute_all(&mut tasks, &config).await;
+
+        let finished = results.iter().filter(|r| *r.status() == SyncStatus::Finished).count();
+        let cancelled = results.iter().filter(|r| *r.status() == SyncStatus::Cancelled).count();
+        assert_eq!((finished, cancelled), (3, 2));
+        assert_eq!(*usage_repository.usage.lock().unwrap().as_ref().unwrap().used(), 3);
+    }
+
+    #[derive(Default)]
+    struct InMemoryCheckpointRepository {
+        checkpoints: std::sync::Mutex<std::collections::HashMap<Uuid, SyncCheckpoint>>,
+    }
+
+    #[async_trait]
+    impl CheckpointRepository for InMemoryCheckpointRepository {
+        async fn get_checkpoint(&self, sync_plan_id: &Uuid) -> Result<Option<SyncCheckpoint>, RepositoryError> {
+            return Ok(self.checkpoints.lock().unwrap().get(sync_plan_id).cloned());
+        }
+
+        async fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), RepositoryError> {
+            self.checkpoints.lock().unwrap().insert(*checkpoint.sync_plan_id(), checkpoint.clone());
+            return Ok(());
+        }
+
+        async fn delete_checkpoint(&self, sync_plan_id: &Uuid) -> Result<(), RepositoryError> {
+            self.checkpoints.lock().unwrap().remove(sync_plan_id);
+            return Ok(());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_resume_an_interrupted_plan_from_its_checkpoint() {
+        let checkpoints = Arc::new(InMemoryCheckpointRepository::default());
+        let plan_id = Uuid::new_v4();
+        let generate_tasks = || -> Vec<SyncTask> {
+            (0..3)
+                .map(|position| {
+                    let mut spec = TaskSpec::default();
+                    spec.set_payload(Some(json!({"trade_date": format!("2023062{}", position)})));
+                    let mut task = SyncTask::default();
+                    task.set_sync_plan_id(Some(plan_id)).set_spec(spec);
+                    task
+                })
+                .collect()
+        };
+        let mut tasks = generate_tasks();
+        tasks[0].finished();
+        let config = config_with_concurrency(1);
+
+        let sender = ScriptedSender::failing_with(vec![RequestError::DailyLimitExceeded("quota used up".to_string())]);
+        let executor = ConcurrentTaskExecutor::new(Arc::new(sender)).with_checkpoints(checkpoints.clone());
+        executor.execute_all(&mut tasks, &config).await;
+        let checkpoint = checkpoints.get_checkpoint(&plan_id).await.unwrap().unwrap();
+        assert_eq!((checkpoint.finished().len(), checkpoint.failed().len(), checkpoint.pending().len()), (1, 1, 1));
+
+        // the process restarts and generates the plan's tasks again, under new ids
+        let mut regenerated = generate_tasks();
+        let executor = ConcurrentTaskExecutor::new(Arc::new(CountingSender::default())).with_checkpoints(checkpoints.clone());
+        let results = executor.execute_all(&mut regenerated, &config).await;
+
+        assert!(results.iter().all(|result| *result.status() == SyncStatus::Finished));
+        let attempts: Vec<u32> = regenerated.iter().map(|task| *task.attempts()).collect();
+        assert_eq!(attempts, vec![0, 1, 1]);
+        assert_eq!(checkpoints.get_checkpoint(&plan_id).await.unwrap(), None);
+    }
+
+    /// Remembers the key of every request and rejects the keys whose remote daily limit is used up
+    #[derive(Default)]
+    struct KeyRecordingSender {
+        spent_keys: Vec<String>,
+        keys: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl RequestSender for KeyRecordingSender {
+        async fn send(&self, spec: &TaskSpec) -> Result<Value, RequestError> {
+            let api_key = spec.api_key().clone().unwrap_or_default();
+            self.keys.lock().unwrap().push(api_key.clone());
+            if self.spent_keys.contains(&api_key) {
+                return Err(RequestError::DailyLimitExceeded("points used up".to_string()));
+            }
+            return Ok(json!({"rows": 1}));
+        }
+    }
+
+    fn datasource_with_keys(daily_limits: &[u32]) -> crate::domain::data_source::data_source::DataSource {
+        let mut datasource = crate::domain::data_source::data_source::DataSource::default();
+        for (position, daily_limit) in daily_limits.iter().enumerate() {
+            let mut quota = Quota::default();
+            quota.set_daily_limit(*daily_limit);
+            datasource.add_credential(ApiCredential::new("tushare", &format!("token-{}", position), quota));
+        }
+        return datasource;
+    }
+
+    fn tasks_of(datasource_id: Uuid, count: usize) -> Vec<SyncTask> {
+        return (0..count)
+            .map(|_| {
+                let mut task = SyncTask::default();
+                task.set_datasource_id(Some(datasource_id));
+                task
+            })
+            .collect();
+    }
+
+    #[tokio::test]
+    async fn it_should_spread_requests_over_keys_within_their_own_quota() {
+        let datasource = datasource_with_keys(&[1, 2]);
+        let rate_limiter = Arc::new(RateLimiter::new(Arc::new(InMemoryUsageRepository::default())));
+        let credential_rotation = Arc::new(CredentialRotation::new(rate_limiter));
+        credential_rotation.register(&datasource).await;
+        let sender = Arc::new(KeyRecordingSender::default());
+        let executor = ConcurrentTaskExecutor::new(sender.clone()).with_credentials(credential_rotation);
+        let mut tasks = tasks_of(*datasource.id(), 4);
+
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(1)).await;
+
+        let statuses: Vec<SyncStatus> = results.iter().map(|result| *result.status()).collect();
+        assert_eq!(statuses, vec![SyncStatus::Finished, SyncStatus::Finished, SyncStatus::Finished, SyncStatus::Cancelled]);
+        assert_eq!(*sender.keys.lock().unwrap(), vec!["token-0", "token-1", "token-1"]);
+        let credentials = datasource.credentials().credentials();
+        assert_eq!(*results[0].credential_id(), Some(*credentials[0].id()));
+        assert_eq!(*results[1].credential_id(), Some(*credentials[1].id()));
+    }
+
+    #[tokio::test]
+    async fn it_should_move_to_the_next_key_when_the_remote_says_a_key_is_spent() {
+        let datasource = datasource_with_keys(&[0, 0]);
+        let rate_limiter = Arc::new(RateLimiter::new(Arc::new(InMemoryUsageRepository::default())));
+        let credential_rotation = Arc::new(CredentialRotation::new(rate_limiter));
+        credential_rotation.register(&datasource).await;
+        let sender = Arc::new(KeyRecordingSender {
+            spent_keys: vec!["token-0".to_string()],
+            ..Default::default()
+        });
+        let executor = ConcurrentTaskExecutor::new(sender.clone()).with_credentials(credential_rotation);
+        let mut tasks = tasks_of(*datasource.id(), 3);
+
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(1)).await;
+
+        assert!(results.iter().all(|result| *result.status() == SyncStatus::Finished));
+        assert_eq!(*sender.keys.lock().unwrap(), vec!["token-0", "token-1", "token-1", "token-1"]);
+    }
+
+    #[tokio::test]
+    async fn it_should_hold_requests_to_the_quota_of_the_data_source_and_of_each_key() {
+        let datasource = datasource_with_keys(&[2, 2]);
+        let rate_limiter = Arc::new(RateLimiter::new(Arc::new(InMemoryUsageRepository::default())));
+        let credential_rotation = Arc::new(CredentialRotation::new(rate_limiter.clone()));
+        credential_rotation.register(&datasource).await;
+        let sender = Arc::new(KeyRecordingSender::default());
+        let executor = ConcurrentTaskExecutor::new(sender.clone())
+            .with_rate_limiter(rate_limiter.clone())
+            .with_credentials(credential_rotation);
+        let mut tasks = tasks_of(*datasource.id(), 4);
+        let mut config = config_with_concurrency(1);
+        let mut quota = config.sync_quota().clone();
+        quota.set_daily_limit(3);
+        config.set_sync_quota(quota);
+
+        let results = executor.execute_all(&mut tasks, &config).await;
+
+        let finished = results.iter().filter(|result| *result.status() == SyncStatus::Finished).count();
+        assert_eq!(finished, 3);
+        assert_eq!(*sender.keys.lock().unwrap(), vec!["token-0", "token-1", "token-0"]);
+        let used_today = rate_limiter.used_today(&QuotaOwner::DataSource(*datasource.id())).await.unwrap();
+        assert_eq!(used_today, 3);
+    }
+
+    #[tokio::test]
+    async fn it_should_not_charge_the_data_source_for_a_request_no_key_can_send() {
+        let datasource = datasource_with_keys(&[1]);
+        let rate_limiter = Arc::new(RateLimiter::new(Arc::new(InMemoryUsageRepository::default())));
+        let credential_rotation = Arc::new(CredentialRotation::new(rate_limiter.clone()));
+        credential_rotation.register(&datasource).await;
+        let executor = ConcurrentTaskExecutor::new(Arc::new(KeyRecordingSender::default()))
+            .with_rate_limiter(rate_limiter.clone())
+            .with_credentials(credential_rotation);
+        let mut tasks = tasks_of(*datasource.id(), 3);
+
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(1)).await;
+
+        let statuses: Vec<SyncStatus> = results.iter().map(|result| *result.status()).collect();
+        assert_eq!(statuses, vec![SyncStatus::Finished, SyncStatus::Cancelled, SyncStatus::Cancelled]);
+        let used_today = rate_limiter.used_today(&QuotaOwner::DataSource(*datasource.id())).await.unwrap();
+        assert_eq!(used_today, 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_run_a_single_task_within_the_quota_of_its_keys() {
+        let datasource = datasource_with_keys(&[1]);
+        let rate_limiter = Arc::new(RateLimiter::new(Arc::new(InMemoryUsageRepository::default())));
+        let credential_rotation = Arc::new(CredentialRotation::new(rate_limiter.clone()));
+        credential_rotation.register(&datasource).await;
+        let sender = Arc::new(KeyRecordingSender::default());
+        let executor = ConcurrentTaskExecutor::new(sender.clone())
+            .with_rate_limiter(rate_limiter.clone())
+            .with_credentials(credential_rotation);
+        let mut tasks = tasks_of(*datasource.id(), 2);
+        let config = config_with_concurrency(1);
+
+        let first = executor.execute(&mut tasks[0], &config).await;
+        let second = executor.execute(&mut tasks[1], &config).await;
+
+        assert_eq!(*first.status(), SyncStatus::Finished);
+        assert_eq!(*second.status(), SyncStatus::Cancelled);
+        assert_eq!(*sender.keys.lock().unwrap(), vec!["token-0"]);
+        let used_today = rate_limiter.used_today(&QuotaOwner::DataSource(*datasource.id())).await.unwrap();
+        assert_eq!(used_today, 1);
+    }
+
+    #[tokio::test]
+    async fn it_should_start_adaptive_runs_at_the_initial_concurrency() {
+        let sender = Arc::new(CountingSender::default());
+        let executor = ConcurrentTaskExecutor::new(sender.clone());
+        let mut tasks: Vec<SyncTask> = (0..6).map(|_| SyncTask::default()).collect();
+        let mut config = config_with_concurrency(4);
+        let mut policy = AdaptiveConcurrency::default();
+        policy.set_initial_concurrency(2).set_sample_size(100);
+        config.set_adaptive_concurrency(Some(policy));
+
+        let results = executor.execute_all(&mut tasks, &config).await;
+
+        assert!(results.iter().all(|result| *result.status() == SyncStatus::Finished));
+        assert_eq!(sender.max_in_flight.load(Ordering::SeqCst), 2);
+        assert_eq!(executor.effective_concurrency(&Uuid::default()), Some(2));
+    }
+
+    #[tokio::test]
+    async fn it_should_cut_adaptive_concurrency_on_throttling() {
+        let sender = Arc::new(ScriptedSender::failing_with(vec![RequestError::TooFrequent(None)]));
+        let executor = ConcurrentTaskExecutor::new(sender);
+        let mut tasks = vec![SyncTask::default()];
+        let mut config = config_with_concurrency(4);
+        let mut policy = AdaptiveConcurrency::default();
+        policy.set_initial_concurrency(4);
+        config.set_adaptive_concurrency(Some(policy));
+
+        executor.execute_all(&mut tasks, &config).await;
+
+        assert_eq!(executor.effective_concurrency(&Uuid::default()), Some(2));
+    }
+
+    #[tokio::test]
+    async fn it_should_report_the_requests_of_a_dry_run_without_sending_them() {
+        let sender = Arc::new(CountingSender::default());
+        let executor = ConcurrentTaskExecutor::new(sender.clone()).with_dry_run_latency(Duration::from_secs(2));
+        let (daily, stock_basic) = (Url::parse("https://api.tushare.pro/daily").unwrap(), Url::parse("https://api.tushare.pro/stock_basic").unwrap());
+        let tasks: Vec<SyncTask> = [&daily, &daily, &stock_basic]
+            .into_iter()
+            .map(|endpoint| {
+                let mut spec = TaskSpec::default();
+                spec.set_request_endpoint(endpoint.clone());
+                let mut task = SyncTask::default();
+                task.set_spec(spec);
+                task
+            })
+            .collect();
+
+        let report = executor.dry_run(&tasks, &config_with_concurrency(1)).await;
+
+        assert_eq!(sender.max_in_flight.load(Ordering::SeqCst), 0);
+        assert!(tasks.iter().all(|task| *task.status() == *SyncTask::default().status()));
+        assert_eq!(report.requests().len(), 3);
+        assert_eq!(report.requests_per_endpoint()[daily.as_str()], 2);
+        assert_eq!(*report.estimated_duration(), Duration::from_secs(6));
+    }
+
+    #[tokio::test]
+    async fn it_should_dry_run_on_what_is_left_of_the_daily_limit() {
+        let mut usage = DailyUsage::new(QuotaOwner::DataSource(Uuid::default()), Local::now().date_naive());
+        usage.set_used(9);
+        let usage_repository = InMemoryUsageRepository { usage: std::sync::Mutex::new(Some(usage)) };
+        let executor = ConcurrentTaskExecutor::new(Arc::new(CountingSender::default()))
+            .with_rate_limiter(Arc::new(RateLimiter::new(Arc::new(usage_repository))))
+            .with_dry_run_latency(Duration::from_secs(2));
+        let mut paginated = TaskSpec::default();
+        paginated.set_pagination(Some(Pagination::offset("/data/items")));
+        let tasks: Vec<SyncTask> = [TaskSpec::default(), paginated]
+            .into_iter()
+            .map(|spec| {
+                let mut task = SyncTask::default();
+                task.set_spec(spec);
+                task
+            })
+            .collect();
+        let mut config = config_with_concurrency(1);
+        let mut quota = config.sync_quota().clone();
+        quota.set_daily_limit(10).set_max_line_per_request(100);
+        config.set_sync_quota(quota);
+
+        let report = executor.dry_run(&tasks, &config).await;
+
+        assert_eq!(report.requests().len(), 2);
+        assert_eq!(*report.paginated_tasks(), 1);
+        // one request is left today, the other waits for tomorrow
+        assert_eq!(*report.estimated_duration(), Duration::from_secs(24 * 60 * 60 + 2));
+    }
+
+    /// Never answers requests to endpoints under `/hang`
+    struct HangingSender;
+
+    #[async_trait]
+    impl RequestSender for HangingSender {
+        async fn send(&self, spec: &TaskSpec) -> Result<Value, RequestError> {
+            if spec.request_endpoint().path().starts_with("/hang") {
+                std::future::pending::<()>().await;
+            }
+            return Ok(json!({"rows": 1}));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_abort_the_requests_of_a_cancelled_plan() {
+        let cancellations = Arc::new(CancellationRegistry::new());
+        let executor = ConcurrentTaskExecutor::new(Arc::new(HangingSender)).with_cancellations(cancellations.clone());
+        let (cancelled_plan, other_plan) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut tasks: Vec<SyncTask> = [(cancelled_plan, "/hang"), (cancelled_plan, "/hang"), (other_plan, "/daily")]
+            .into_iter()
+            .map(|(plan_id, path)| {
+                let mut spec = TaskSpec::default();
+                spec.set_request_endpoint(Url::parse("http://localhost/").unwrap().join(path).unwrap());
+                let mut task = SyncTask::default();
+                task.set_sync_plan_id(Some(plan_id)).set_spec(spec);
+                task
+            })
+            .collect();
+        let config = config_with_concurrency(3);
+
+        let (results, cancelled) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(executor.execute_all(&mut tasks, &config), async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                executor.cancel_running(CancelScope::Plan(cancelled_plan))
+            })
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(cancelled, 2);
+        let statuses: Vec<SyncStatus> = results.iter().map(|result| *result.status()).collect();
+        assert_eq!(statuses, vec![SyncStatus::Cancelled, SyncStatus::Cancelled, SyncStatus::Finished]);
+        assert!(results[0].result_message().contains("discarded"));
+        assert_eq!(cancellations.cancel(CancelScope::Plan(cancelled_plan)), 0);
+    }
+
+    #[derive(Default)]
+    struct RecordingPublisher {
+        events: std::sync::Mutex<Vec<SyncEvent>>,
+    }
+
+    impl EventPublisher for RecordingPublisher {
+        fn publish(&self, event: SyncEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_fail_fast_once_the_circuit_breaker_opens() {
+        let sender = Arc::new(ScriptedSender::failing_with(vec![RequestError::Timeout; 10]));
+        let publisher = Arc::new(RecordingPublisher::default());
+        let circuit_breakers = Arc::new(CircuitBreakers::new().with_event_publisher(publisher.clone()));
+        let executor = ConcurrentTaskExecutor::new(sender.clone()).with_circuit_breakers(circuit_breakers);
+        let mut tasks: Vec<SyncTask> = (0..3).map(|_| SyncTask::default()).collect();
+        let mut config = config_with_concurrency(1);
+        let mut policy = CircuitBreakerPolicy::default();
+        policy.set_failure_threshold(2);
+        config.set_circuit_breaker(Some(policy));
+
+        let results = executor.execute_all(&mut tasks, &config).await;
+
+        // two timeouts open the breaker, nothing else reaches the remote
+        assert_eq!(sender.errors.lock().unwrap().len(), 8);
+        assert!(results.iter().all(|result| *result.status() == SyncStatus::Failed));
+        assert!(results[2].result_message().contains("Circuit breaker"));
+        assert!(matches!(publisher.events.lock().unwrap()[..], [SyncEvent::CircuitBreaker(CircuitBreakerEvent::Opened { .. })]));
+    }
+
+    #[tokio::test]
+    async fn it_should_leave_the_probe_of_a_half_open_breaker_to_its_holder() {
+        let mut policy = CircuitBreakerPolicy::default();
+        policy.set_failure_threshold(1).set_open_duration(Duration::ZERO);
+        let circuit_breakers = Arc::new(CircuitBreakers::new());
+        let datasource_id = Uuid::default();
+        circuit_breakers.try_pass(datasource_id).unwrap().record(&policy, Some(FailureReason::Timeout));
+        let probe = circuit_breakers.try_pass(datasource_id).unwrap();
+        let executor = ConcurrentTaskExecutor::new(Arc::new(CountingSender::default()))
+            .with_circuit_breakers(circuit_breakers.clone())
+            .with_rate_limiter(Arc::new(RateLimiter::new(Arc::new(InMemoryUsageRepository::default()))));
+        let mut tasks: Vec<SyncTask> = (0..2).map(|_| SyncTask::default()).collect();
+        let mut config = config_with_concurrency(1);
+        let mut quota = config.sync_quota().clone();
+        quota.set_daily_limit(1);
+        config.set_sync_quota(quota);
+
+        // the second task is halted by the quota, it never held the probe
+        executor.execute_all(&mut tasks, &config).await;
+
+        assert!(circuit_breakers.try_pass(datasource_id).is_err());
+        drop(probe);
+        assert!(circuit_breakers.try_pass(datasource_id).is_ok());
+    }
+
+    #[derive(Default)]
+    struct InMemoryFingerprintRepository {
+        completions: std::sync::Mutex<std::collections::HashMap<String, DateTime<Local>>>,
+    }
+
+    #[async_trait]
+    impl FingerprintRepository for InMemoryFingerprintRepository {
+        async fn get_completion_time(&self, fingerprint: &str) -> Result<Option<DateTime<Local>>, RepositoryError> {
+            return Ok(self.completions.lock().unwrap().get(fingerprint).copied());
+        }
+
+        async fn record_completion(&self, fingerprint: &str, completion_time: DateTime<Local>) -> Result<(), RepositoryError> {
+            self.completions.lock().unwrap().insert(fingerprint.to_string(), completion_time);
+            return Ok(());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_skip_requests_that_succeeded_within_the_dedup_window() {
+        let sender = Arc::new(KeyRecordingSender::default());
+        let fingerprints = Arc::new(InMemoryFingerprintRepository::default());
+        let executor = ConcurrentTaskExecutor::new(sender.clone()).with_fingerprints(fingerprints);
+        let payload = json!({"trade_date": "20230621"});
+        let mut task = SyncTask::default();
+        let mut spec = TaskSpec::default();
+        spec.set_payload(Some(payload.clone()));
+        task.set_spec(spec);
+        let mut config = config_with_concurrency(1);
+
+        executor.execute_all(&mut [task.clone()], &config).await;
+        executor.execute_all(&mut [task.clone()], &config).await;
+        assert_eq!(sender.keys.lock().unwrap().len(), 2);
+
+        config.set_dedup_window(Some(Duration::from_secs(3600)));
+        let results = executor.execute_all(&mut [task.clone()], &config).await;
+
+        assert_eq!(sender.keys.lock().unwrap().len(), 2);
+        assert_eq!(*results[0].status(), SyncStatus::Finished);
+        assert!(results[0].result_message().starts_with("Skipped"));
+    }
+
+    /// Serves `total` rows through offset and limit, like an endpoint truncating at the row cap
+    struct PagedSender {
+        total: usize,
+        requests: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl RequestSender for PagedSender {
+        async fn send(&self, spec: &TaskSpec) -> Result<Value, RequestError> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let payload = spec.payload().as_ref().unwrap();
+            let offset = payload["offset"].as_u64().unwrap() as usize;
+            let limit = payload["limit"].as_u64().unwrap() as usize;
+            let items: Vec<Value> = (offset..self.total.min(offset + limit)).map(|row| json!([row])).collect();
+            return Ok(json!({"data": {"fields": ["row"], "items": items}}));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_follow_pages_until_a_short_page_arrives() {
+        let sender = Arc::new(PagedSender { total: 7, requests: AtomicUsize::new(0) });
+        let executor = ConcurrentTaskExecutor::new(sender.clone());
+        let payload = json!({"list_status": "L"});
+        let mut task = SyncTask::default();
+        let mut spec = TaskSpec::default();
+        spec.set_payload(Some(payload.clone())).set_pagination(Some(Pagination::offset("/data/items")));
+        task.set_spec(spec);
+        let mut tasks = vec![task];
+        let mut config = config_with_concurrency(2);
+        let mut quota = config.sync_quota().clone();
+        quota.set_max_line_per_request(3);
+        config.set_sync_quota(quota);
+
+        let results = executor.execute_all(&mut tasks, &config).await;
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(*results[0].status(), SyncStatus::Finished);
+        assert_eq!(results[0].data()["data"]["items"].as_array().unwrap().len(), 7);
+        assert_eq!(sender.requests.load(Ordering::SeqCst), 3);
+    }
+
+    /// Serves one row per day and truncates at `cap` rows, ignoring any offset
+    struct DailyRowsSender {
+        cap: usize,
+    }
+
+    #[async_trait]
+    impl RequestSender for DailyRowsSender {
+        async fn send(&self, spec: &TaskSpec) -> Result<Value, RequestError> {
+            let payload = spec.payload().as_ref().unwrap();
+            let parse = |key: &str| chrono::NaiveDate::parse_from_str(payload[key].as_str().unwrap(), "%Y%m%d").unwrap();
+            let (start, end) = (parse("start_date"), parse("end_date"));
+            let items: Vec<Value> = start
+                .iter_days()
+                .take_while(|day| *day <= end)
+                .take(self.cap)
+                .map(|day| json!([day.format("%Y%m%d").to_string()]))
+                .collect();
+            return Ok(json!({"data": {"fields": ["trade_date"], "items": items}}));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_should_split_date_windows_until_every_slice_fits() {
+        let executor = ConcurrentTaskExecutor::new(Arc::new(DailyRowsSender { cap: 4 }));
+        let payload = json!({"start_date": "20230101", "end_date": "20230131"});
+        let mut task = SyncTask::default();
+        let mut spec = TaskSpec::default();
+        spec.set_payload(Some(payload.clone())).set_pagination(Some(Pagination::date_range("/data/items")));
+        task.set_spec(spec);
+        let mut tasks = vec![task];
+        let mut config = config_with_concurrency(4);
+        let mut quota = config.sync_quota().clone();
+        quota.set_max_line_per_request(4);
+        config.set_sync_quota(quota);
+
+        let results = executor.execute_all(&mut tasks, &config).await;
+
+        let mut days: Vec<String> = results[0].data()["data"]["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item[0].as_str().unwrap().to_string())
+            .collect();
+        days.sort();
+        assert_eq!(days.len(), 31);
+        assert_eq!((days[0].as_str(), days[30].as_str()), ("20230101", "20230131"));
+    }
+
+    #[tokio::test]
+    async fn it_should_fail_a_single_day_that_still_fills_a_page() {
+        let executor = ConcurrentTaskExecutor::new(Arc::new(DailyRowsSender { cap: 1 }));
+        let payload = json!({"start_date": "20230101", "end_date": "20230102"});
+        let mut task = SyncTask::default();
+        let mut spec = TaskSpec::default();
+        spec.set_payload(Some(payload)).set_pagination(Some(Pagination::date_range("/data/items")));
+        task.set_spec(spec);
+        let mut tasks = vec![task];
+        let mut config = config_with_concurrency(1);
+        let mut quota = config.sync_quota().clone();
+        quota.set_max_line_per_request(1);
+        config.set_sync_quota(quota);
+
+        let results = executor.execute_all(&mut tasks, &config).await;
+
+        assert_eq!(*results[0].status(), SyncStatus::Failed);
+        assert!(results[0].result_message().contains("truncated"));
+    }
+
+    /// Answer the first request on a local port with the given status and body, returns the url to request
+    fn serve_once(status_line: &'static str, body: &'static str) -> Url {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/datatables/ZACKS/FC.json", listener.local_addr().unwrap())).unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status_line,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        return url;
+    }
+
+    /// Outcome of one request to the url for a task of a Nasdaq Data Link source
+    async fn send_to_data_link(url: Url) -> (SyncTask, Result<Value, RequestError>) {
+        let datasource_id = Uuid::new_v4();
+        let executor = ConcurrentTaskExecutor::new(Arc::new(HttpRequestSender::default()))
+            .with_adapter(datasource_id, Arc::new(NasdaqDataLinkAdapter::default()));
+        let mut task = SyncTask::default();
+        let mut spec = TaskSpec::default();
+        spec.set_request_endpoint(url);
+        task.set_datasource_id(Some(datasource_id)).set_spec(spec);
+        let outcome = executor.send_once(&mut task, None, None).await;
+        return (task, outcome);
+    }
+
+    #[tokio::test]
+    async fn it_should_let_the_adapter_classify_the_body_of_an_error_status() {
+        let daily_limit = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nasdaq_data_link/error.json"));
+
+        let (task, outcome) = send_to_data_link(serve_once("429 Too Many Requests", daily_limit)).await;
+
+        assert!(matches!(outcome, Err(RequestError::DailyLimitExceeded(message)) if message.contains("daily call limit")));
+        assert_eq!(*task.failure_reason(), Some(FailureReason::DailyLimitExceeded));
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_the_status_of_an_error_the_adapter_does_not_know() {
+        let body = "{\"message\": \"down for maintenance\"}";
+
+        let (_, outcome) = send_to_data_link(serve_once("503 Service Unavailable", body)).await;
+
+        assert!(matches!(outcome, Err(RequestError::ErrorResponse { status: 503, .. })));
+    }
+
+    #[tokio::test]
+    async fn it_should_extract_the_rows_declared_by_the_dataset() {
+        let mut dataset = Dataset::default();
+        dataset.add_columns_to_schema(&vec![Column::new("trade_date", "str", "").unwrap()]);
+        let rules = ExtractionRules::new("data.items").and_then(|rules| rules.with_field("trade_date", "[0]"));
+        dataset.set_extraction_rules(Some(rules.unwrap()));
+        let mut misplaced = dataset.clone();
+        misplaced.set_id(Uuid::new_v4()).set_extraction_rules(ExtractionRules::new("$.items").ok());
+        let datasource_id = Uuid::new_v4();
+        let adapter = RestAdapter::new(RestSourceConfig::new(Url::parse("https://data.example.com/").unwrap()));
+        let executor = ConcurrentTaskExecutor::new(Arc::new(DailyRowsSender { cap: 10 }))
+            .with_datasets(&[dataset.clone(), misplaced.clone()])
+            .with_adapter(datasource_id, Arc::new(adapter));
+
+        let mut tasks: Vec<SyncTask> = [&dataset, &misplaced]
+            .iter()
+            .map(|dataset| {
+                let mut task = SyncTask::default();
+                let mut spec = TaskSpec::default();
+                spec.set_payload(Some(json!({"start_date": "20230101", "end_date": "20230102"})));
+                task.set_dataset_id(Some(*dataset.id())).set_datasource_id(Some(datasource_id)).set_spec(spec);
+                task
+            })
+            .collect();
+        let results = executor.execute_all(&mut tasks, &config_with_concurrency(2)).await;
+
+        let rows = results[0].rows().clone().unwrap();
+        assert_eq!(Value::Object(rows[1].clone()), json!({"trade_date": "20230102"}));
+        assert_eq!(*results[1].status(), SyncStatus::Failed);
+        assert!(results[1].rows().is_none());
+    }
+}
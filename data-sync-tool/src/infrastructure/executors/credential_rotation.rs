@@ -0,0 +1,72 @@
+//! Credential Rotation
+//! Spreads the requests to a data source over its API keys, each key paced by its own quota
+
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::Local;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::rate_limiter::RateLimiter;
+use crate::domain::{
+    data_source::{
+        data_source::DataSource,
+        value_object::api_credential::{ApiCredential, CredentialPool},
+    },
+    synchronization::{custom_errors::QuotaError, rate_limiter::QuotaOwner},
+};
+
+pub struct CredentialRotation {
+    pools: Mutex<HashMap<Uuid, CredentialPool>>,
+    // usage is counted per credential, so each key keeps its own budget apart from the data source's
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl CredentialRotation {
+    pub fn new(rate_limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            pools: Mutex::new(HashMap::new()),
+            rate_limiter,
+        }
+    }
+
+    /// Use the credentials of the data source for its requests from now on
+    pub async fn register(&self, datasource: &DataSource) {
+        self.pools
+            .lock()
+            .await
+            .insert(*datasource.id(), datasource.credentials().clone());
+    }
+
+    /// Wait for a key of the data source whose quota allows one more request.
+    /// None when the data source has no credentials registered, fails once every key reached its daily limit.
+    pub async fn acquire(&self, datasource_id: Uuid) -> Result<Option<ApiCredential>, QuotaError> {
+        loop {
+            let credential = {
+                let mut pools = self.pools.lock().await;
+                let Some(pool) = pools.get_mut(&datasource_id).filter(|pool| !pool.credentials().is_empty()) else {
+                    return Ok(None);
+                };
+                pool.next(Local::now().date_naive()).ok_or(QuotaError::DailyLimitReached)?
+            };
+
+            match self.rate_limiter.acquire(QuotaOwner::Credential(*credential.id()), credential.quota()).await {
+                Ok(()) => return Ok(Some(credential)),
+                Err(QuotaError::DailyLimitReached) => {
+                    self.exhaust(datasource_id, credential.id()).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Take a key out of rotation for the rest of the day, returns whether the data source has keys left
+    pub async fn exhaust(&self, datasource_id: Uuid, credential_id: &Uuid) -> bool {
+        let today = Local::now().date_naive();
+        let mut pools = self.pools.lock().await;
+        match pools.get_mut(&datasource_id) {
+            Some(pool) => return pool.exhaust(credential_id, today).available(today) > 0,
+            None => return false,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Dry Run Sender
+//! Records the requests it is given instead of sending them
+
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::domain::synchronization::{
+    custom_errors::RequestError,
+    task_executor::RequestSender,
+    value_objects::{dry_run_report::PlannedRequest, task_spec::TaskSpec},
+};
+
+#[derive(Default)]
+pub struct DryRunSender {
+    requests: Mutex<Vec<PlannedRequest>>,
+}
+
+impl DryRunSender {
+    /// The requests recorded so far, in the order they would have been sent
+    pub fn take_requests(&self) -> Vec<PlannedRequest> {
+        return std::mem::take(&mut *self.requests.lock().expect("Recorded requests should never be poisoned"));
+    }
+}
+
+#[async_trait]
+impl RequestSender for DryRunSender {
+    async fn send(&self, spec: &TaskSpec) -> Result<Value, RequestError> {
+        self.requests
+            .lock()
+            .expect("Recorded requests should never be poisoned")
+            .push(PlannedRequest::from_spec(spec));
+        // an empty response, so paginated requests stop after their first page, the report counts them apart
+        return Ok(Value::Null);
+    }
+}
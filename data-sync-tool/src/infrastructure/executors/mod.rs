@@ -0,0 +1,7 @@
+pub mod adaptive_concurrency;
+pub mod cancellation;
+pub mod circuit_breakers;
+pub mod concurrent_executor;
+pub mod credential_rotation;
+pub mod dry_run_sender;
+pub mod rate_limiter;
\ No newline at end of file
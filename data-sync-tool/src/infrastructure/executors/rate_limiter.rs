@@ -0,0 +1,101 @@
+//! Rate Limiter
+//! Shares one request budget per data source, or per API key, between every plan and executor using it
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+    time::Instant,
+};
+
+use chrono::Local;
+use tokio::sync::Mutex;
+
+use crate::domain::synchronization::{
+    custom_errors::QuotaError,
+    rate_limiter::{BudgetDecision, DailyUsage, QuotaOwner, RequestBudget},
+    repository::QuotaUsageRepository,
+    value_objects::sync_config::Quota,
+};
+
+pub struct RateLimiter {
+    budgets: Mutex<HashMap<QuotaOwner, RequestBudget>>,
+    // saves one usage at a time, each writing the latest count, so a slower save never writes an older one
+    saving: Mutex<()>,
+    usage_repository: Arc<dyn QuotaUsageRepository>,
+}
+
+impl RateLimiter {
+    pub fn new(usage_repository: Arc<dyn QuotaUsageRepository>) -> Self {
+        Self {
+            budgets: Mutex::new(HashMap::new()),
+            saving: Mutex::new(()),
+            usage_repository,
+        }
+    }
+
+    /// Requests sent for the data source or key today, by this limiter or before the last restart
+    pub async fn used_today(&self, owner: &QuotaOwner) -> Result<u32, QuotaError> {
+        let budget_usage = self
+            .budgets
+            .lock()
+            .await
+            .get(owner)
+            .map(|budget| budget.daily_usage().clone());
+        let usage = match budget_usage {
+            Some(usage) => Some(usage),
+            None => self.usage_repository.get_daily_usage(owner).await?,
+        };
+        let today = Local::now().date_naive();
+        return Ok(usage.map(|mut usage| *usage.roll_over(today).used()).unwrap_or(0));
+    }
+
+    /// Wait until the quota of the data source or key allows one more request.
+    /// Fails immediately once the daily limit is reached, so no request is wasted on the remote.
+    pub async fn acquire(&self, owner: QuotaOwner, quota: &Quota) -> Result<(), QuotaError> {
+        loop {
+            let decision = {
+                let today = Local::now().date_naive();
+                let mut budgets = self.budgets.lock().await;
+                let budget = match budgets.entry(owner) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let usage = self
+                            .usage_repository
+                            .get_daily_usage(&owner)
+                            .await?
+                            .unwrap_or_else(|| DailyUsage::new(owner, today));
+                        entry.insert(RequestBudget::new(quota, usage, Instant::now()))
+                    }
+                };
+                budget.try_consume(quota, today, Instant::now())
+            };
+            match decision {
+                BudgetDecision::Granted => return self.persist(&owner).await,
+                BudgetDecision::DailyLimitReached => return Err(QuotaError::DailyLimitReached),
+                BudgetDecision::Wait(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+
+    /// Give back a request granted today that will not be sent after all
+    pub async fn refund(&self, owner: QuotaOwner) -> Result<(), QuotaError> {
+        {
+            let mut budgets = self.budgets.lock().await;
+            let Some(budget) = budgets.get_mut(&owner) else {
+                return Ok(());
+            };
+            budget.refund(Local::now().date_naive());
+        }
+        return self.persist(&owner).await;
+    }
+
+    /// Save the current usage of the owner, once the budgets are released other data sources need not wait for the file
+    async fn persist(&self, owner: &QuotaOwner) -> Result<(), QuotaError> {
+        let _saving = self.saving.lock().await;
+        let usage = self.budgets.lock().await.get(owner).map(|budget| budget.daily_usage().clone());
+        if let Some(usage) = usage {
+            self.usage_repository.save_daily_usage(&usage).await?;
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod adapters;
+pub mod events;
+pub mod executors;
+pub mod net;
+pub mod repositories;
@@ -0,0 +1,170 @@
+//! HTTP Request Sender
+//! Turns task specs into HTTP requests using reqwest
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{header::RETRY_AFTER, RequestBuilder};
+use serde_json::{Map, Value};
+
+use crate::domain::synchronization::{
+    custom_errors::RequestError,
+    task_executor::RequestSender,
+    value_objects::task_spec::{AuthPlacement, RequestMethod, TaskSpec},
+};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct HttpRequestSender {
+    client: reqwest::Client,
+}
+
+impl HttpRequestSender {
+    pub fn new(timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to build the HTTP client");
+        Self { client }
+    }
+}
+
+impl HttpRequestSender {
+    /// Request of the spec with its key where the spec says, without the key when the spec does not place it
+    fn request(&self, spec: &TaskSpec) -> RequestBuilder {
+        let endpoint = spec.request_endpoint().clone();
+        let payload = spec.payload().as_ref();
+        let payload = match (spec.api_key(), spec.auth_placement()) {
+            (Some(api_key), Some(AuthPlacement::BodyField { name })) => with_field(payload, name, api_key),
+            _ => payload.cloned(),
+        };
+        let request = match spec.request_method() {
+            RequestMethod::Get => self.client.get(endpoint).query(&query_pairs(payload.as_ref())),
+            RequestMethod::Post => match &payload {
+                Some(payload) => self.client.post(endpoint).json(payload),
+                None => self.client.post(endpoint),
+            },
+        };
+        match (spec.api_key(), spec.auth_placement()) {
+            (Some(api_key), Some(AuthPlacement::QueryParam { name })) => return request.query(&[(name, api_key)]),
+            (Some(api_key), Some(AuthPlacement::Header { name })) => return request.header(name.as_str(), api_key),
+            (Some(api_key), Some(AuthPlacement::Bearer)) => return request.bearer_auth(api_key),
+            _ => return request,
+        }
+    }
+}
+
+impl Default for HttpRequestSender {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+    }
+}
+
+/// Flatten a JSON object payload into query pairs, strings are sent without quotes
+fn query_pairs(payload: Option<&Value>) -> Vec<(String, String)> {
+    match payload {
+        Some(Value::Object(map)) => map
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (key.clone(), value)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Add the API key as an argument of the payload
+fn with_field(payload: Option<&Value>, name: &str, api_key: &str) -> Option<Value> {
+    let mut payload = match payload {
+        Some(Value::Object(map)) => map.clone(),
+        _ => Map::new(),
+    };
+    payload.insert(name.to_string(), Value::String(api_key.to_string()));
+    return Some(Value::Object(payload));
+}
+
+impl From<reqwest::Error> for RequestError {
+    fn from(err: reqwest::Error) -> RequestError {
+        if err.is_timeout() {
+            RequestError::Timeout
+        } else if let Some(status) = err.status() {
+            RequestError::UnexpectedStatus(status.as_u16())
+        } else if err.is_decode() {
+            RequestError::InvalidResponse(err.to_string())
+        } else {
+            RequestError::ConnectionFailed(err.to_string())
+        }
+    }
+}
+
+#[async_trait]
+impl RequestSender for HttpRequestSender {
+    async fn send(&self, spec: &TaskSpec) -> Result<Value, RequestError> {
+        let response = self.request(spec).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Duration::from_secs);
+            // the source adapter reads the body to tell e.g. a daily limit from throttling
+            let body = response.json::<Value>().await.unwrap_or(Value::Null);
+            return Err(RequestError::ErrorResponse {
+                status: status.as_u16(),
+                retry_after,
+                body,
+            });
+        }
+        let data = response.json::<Value>().await?;
+        return Ok(data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use url::Url;
+
+    use super::*;
+
+    fn built(request_method: RequestMethod, auth_placement: Option<AuthPlacement>) -> reqwest::Request {
+        let mut spec = TaskSpec::default();
+        spec.set_request_endpoint(Url::parse("https://data.example.com/daily").unwrap())
+            .set_request_method(request_method)
+            .set_payload(Some(json!({"trade_date": "20230621"})))
+            .set_api_key(Some("secret".to_string()))
+            .set_auth_placement(auth_placement);
+        return HttpRequestSender::default().request(&spec).build().unwrap();
+    }
+
+    fn body(request: &reqwest::Request) -> Value {
+        return serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn it_should_place_the_key_where_the_source_wants_it() {
+        let unplaced = built(RequestMethod::Get, None);
+        assert_eq!(unplaced.url().query(), Some("trade_date=20230621"));
+        assert!(unplaced.headers().is_empty());
+
+        let query = built(RequestMethod::Get, Some(AuthPlacement::QueryParam { name: "api_key".to_string() }));
+        assert_eq!(query.url().query(), Some("trade_date=20230621&api_key=secret"));
+
+        let header = built(RequestMethod::Get, Some(AuthPlacement::Header { name: "X-Api-Key".to_string() }));
+        assert_eq!(header.headers()["X-Api-Key"], "secret");
+
+        let bearer = built(RequestMethod::Post, Some(AuthPlacement::Bearer));
+        assert_eq!(bearer.headers()["Authorization"], "Bearer secret");
+        assert_eq!(body(&bearer), json!({"trade_date": "20230621"}));
+
+        let body_field = built(RequestMethod::Post, Some(AuthPlacement::BodyField { name: "token".to_string() }));
+        assert_eq!(body(&body_field), json!({"trade_date": "20230621", "token": "secret"}));
+    }
+}
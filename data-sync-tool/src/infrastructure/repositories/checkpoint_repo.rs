@@ -0,0 +1,48 @@
+//! Checkpoint Repository
+//! Persists sync plan checkpoints in a JSON file
+
+use std::{collections::HashMap, path::Path};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::json_file_store::JsonFileStore;
+use crate::domain::synchronization::{
+    checkpoint::SyncCheckpoint, custom_errors::RepositoryError, repository::CheckpointRepository,
+};
+
+pub struct JsonCheckpointRepository {
+    store: JsonFileStore<HashMap<Uuid, SyncCheckpoint>>,
+}
+
+impl JsonCheckpointRepository {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            store: JsonFileStore::new(path),
+        }
+    }
+}
+
+#[async_trait]
+impl CheckpointRepository for JsonCheckpointRepository {
+    async fn get_checkpoint(&self, sync_plan_id: &Uuid) -> Result<Option<SyncCheckpoint>, RepositoryError> {
+        let checkpoints = self.store.load().await?;
+        return Ok(checkpoints.get(sync_plan_id).cloned());
+    }
+
+    async fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), RepositoryError> {
+        self.store
+            .update(|checkpoints| {
+                checkpoints.insert(*checkpoint.sync_plan_id(), checkpoint.clone());
+            })
+            .await
+    }
+
+    async fn delete_checkpoint(&self, sync_plan_id: &Uuid) -> Result<(), RepositoryError> {
+        self.store
+            .update(|checkpoints| {
+                checkpoints.remove(sync_plan_id);
+            })
+            .await
+    }
+}
@@ -0,0 +1,84 @@
+//! Fingerprint Repository
+//! Persists when each request fingerprint last succeeded in a JSON file
+
+use std::{collections::HashMap, path::Path};
+
+use async_trait::async_trait;
+use chrono::prelude::*;
+
+use super::json_file_store::JsonFileStore;
+use crate::domain::synchronization::{custom_errors::RepositoryError, repository::FingerprintRepository};
+
+pub struct JsonFingerprintRepository {
+    store: JsonFileStore<HashMap<String, DateTime<Local>>>,
+    // completions older than this are dropped whenever a new one is recorded, keep it above every dedup window
+    retention: chrono::Duration,
+}
+
+impl JsonFingerprintRepository {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            store: JsonFileStore::new(path),
+            retention: chrono::Duration::days(7),
+        }
+    }
+
+    pub fn with_retention(mut self, retention: chrono::Duration) -> Self {
+        self.retention = retention;
+        return self;
+    }
+
+    /// Forget the requests that succeeded before `before`, they are too old to be skipped anyway
+    pub async fn prune(&self, before: DateTime<Local>) -> Result<usize, RepositoryError> {
+        self.store
+            .update(|completions| Self::retain_since(completions, before))
+            .await
+    }
+
+    fn retain_since(completions: &mut HashMap<String, DateTime<Local>>, since: DateTime<Local>) -> usize {
+        let count = completions.len();
+        completions.retain(|_, completion_time| *completion_time >= since);
+        return count - completions.len();
+    }
+}
+
+#[async_trait]
+impl FingerprintRepository for JsonFingerprintRepository {
+    async fn get_completion_time(&self, fingerprint: &str) -> Result<Option<DateTime<Local>>, RepositoryError> {
+        let completions = self.store.load().await?;
+        return Ok(completions.get(fingerprint).copied());
+    }
+
+    async fn record_completion(&self, fingerprint: &str, completion_time: DateTime<Local>) -> Result<(), RepositoryError> {
+        self.store
+            .update(|completions| {
+                Self::retain_since(completions, completion_time - self.retention);
+                completions.insert(fingerprint.to_string(), completion_time);
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_should_drop_completions_past_the_retention_when_recording() {
+        let path = std::env::temp_dir().join(format!("fingerprints-{}.json", Uuid::new_v4()));
+        let repository = JsonFingerprintRepository::new(&path).with_retention(chrono::Duration::hours(1));
+        let now = Local::now();
+
+        repository.record_completion("stale", now - chrono::Duration::hours(2)).await.unwrap();
+        repository.record_completion("recent", now - chrono::Duration::minutes(30)).await.unwrap();
+        repository.record_completion("fresh", now).await.unwrap();
+
+        assert_eq!(repository.get_completion_time("stale").await.unwrap(), None);
+        assert!(repository.get_completion_time("recent").await.unwrap().is_some());
+        assert_eq!(repository.prune(now).await.unwrap(), 1);
+        assert_eq!(repository.get_completion_time("fresh").await.unwrap(), Some(now));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! JSON File Store
+//! Keeps a small serializable state in a JSON file, used by repositories that must survive restarts
+
+use std::{
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{fs, sync::Mutex};
+
+use crate::domain::synchronization::custom_errors::RepositoryError;
+
+pub struct JsonFileStore<T> {
+    path: PathBuf,
+    // serializes read-modify-write cycles on the same file
+    lock: Mutex<()>,
+    _state: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonFileStore<T>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Mutex::new(()),
+            _state: PhantomData,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    async fn read(&self) -> Result<T, RepositoryError> {
+        match fs::read(&self.path).await {
+            Ok(content) => serde_json::from_slice(&content).map_err(|_| RepositoryError::DataSerializationFailed),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+            Err(_) => Err(RepositoryError::DatabaseConnectionFailed),
+        }
+    }
+
+    async fn write(&self, state: &T) -> Result<(), RepositoryError> {
+        let content = serde_json::to_vec_pretty(state).map_err(|_| RepositoryError::DataSerializationFailed)?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await.map_err(|_| RepositoryError::PermissionDenied)?;
+        }
+        // write to a sibling file first so a crash never leaves a truncated state behind
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).await.map_err(|_| RepositoryError::PermissionDenied)?;
+        fs::rename(&tmp_path, &self.path).await.map_err(|_| RepositoryError::PermissionDenied)?;
+        return Ok(());
+    }
+
+    /// Read the whole state, a missing file reads as the default state
+    pub async fn load(&self) -> Result<T, RepositoryError> {
+        let _guard = self.lock.lock().await;
+        return self.read().await;
+    }
+
+    /// Apply a change to the stored state and write it back
+    pub async fn update<R>(&self, change: impl FnOnce(&mut T) -> R) -> Result<R, RepositoryError> {
+        let _guard = self.lock.lock().await;
+        let mut state = self.read().await?;
+        let output = change(&mut state);
+        self.write(&state).await?;
+        return Ok(output);
+    }
+}
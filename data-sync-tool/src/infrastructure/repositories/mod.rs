@@ -0,0 +1,6 @@
+pub mod checkpoint_repo;
+pub mod fingerprint_repo;
+pub mod json_file_store;
+pub mod plan_repo;
+pub mod quota_usage_repo;
+pub mod trading_calendar_repo;
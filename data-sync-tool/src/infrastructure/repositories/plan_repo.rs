@@ -0,0 +1,262 @@
+//! Plan Repository
+//! Keeps sync plans and their tasks in memory, clones share the same plans
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::domain::synchronization::{
+    custom_errors::RepositoryError,
+    repository::SyncPlanRepository,
+    sync_plan::{SyncFrequency, SyncPlan},
+    sync_task::SyncTask,
+};
+
+#[derive(Default, Clone)]
+pub struct InMemoryPlanRepository {
+    plans: Arc<Mutex<HashMap<Uuid, SyncPlan>>>,
+}
+
+impl InMemoryPlanRepository {
+    pub fn with_plans(plans: Vec<SyncPlan>) -> Self {
+        let plans = plans.into_iter().map(|plan| (*plan.id(), plan)).collect();
+        Self {
+            plans: Arc::new(Mutex::new(plans)),
+        }
+    }
+
+    fn find_plans(&self, predicate: impl Fn(&SyncPlan) -> bool) -> Vec<SyncPlan> {
+        let plans = self.plans.lock().unwrap();
+        return plans.values().filter(|plan| predicate(plan)).cloned().collect();
+    }
+
+    fn find_plan(&self, predicate: impl Fn(&SyncPlan) -> bool) -> Result<SyncPlan, RepositoryError> {
+        return self.find_plans(predicate).into_iter().next().ok_or(RepositoryError::ItemNotFound);
+    }
+
+    fn find_tasks(&self, predicate: impl Fn(&SyncTask) -> bool) -> Vec<SyncTask> {
+        let plans = self.plans.lock().unwrap();
+        return plans.values().flat_map(|plan| plan.tasks()).filter(|task| predicate(task)).cloned().collect();
+    }
+
+    fn update_plan(
+        &self,
+        plan_id: &Uuid,
+        update: impl FnOnce(&mut SyncPlan),
+    ) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        let mut plans = self.plans.lock().unwrap();
+        let plan = plans.get_mut(plan_id).ok_or(RepositoryError::ItemNotFound)?;
+        update(plan);
+        return Ok(Box::new(self.clone()));
+    }
+
+    fn delete_plans_where(&self, predicate: impl Fn(&SyncPlan) -> bool) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        self.plans.lock().unwrap().retain(|_, plan| !predicate(plan));
+        return Ok(Box::new(self.clone()));
+    }
+}
+
+#[async_trait]
+impl SyncPlanRepository for InMemoryPlanRepository {
+    async fn get_plan_by_id(&self, id: &Uuid) -> Result<SyncPlan, RepositoryError> {
+        return self.find_plan(|plan| plan.id() == id);
+    }
+
+    async fn get_plan_by_dataset_id(&self, dataset_id: &Uuid) -> Result<SyncPlan, RepositoryError> {
+        return self.find_plan(|plan| *plan.dataset_id() == Some(*dataset_id));
+    }
+
+    async fn get_plan_by_dataset_name(&self, dataset_name: &str) -> Result<SyncPlan, RepositoryError> {
+        return self.find_plan(|plan| plan.dataset_name().as_deref() == Some(dataset_name));
+    }
+
+    async fn get_plans_by_datasource_id(&self, datasource_id: &Uuid) -> Result<Vec<SyncPlan>, RepositoryError> {
+        return Ok(self.find_plans(|plan| *plan.datasource_id() == Some(*datasource_id)));
+    }
+
+    async fn get_plans_by_datasource_name(&self, datasource_name: &str) -> Result<Vec<SyncPlan>, RepositoryError> {
+        return Ok(self.find_plans(|plan| plan.datasource_name().as_deref() == Some(datasource_name)));
+    }
+
+    async fn get_plan_by_name(&self, name: &str) -> Result<SyncPlan, RepositoryError> {
+        return self.find_plan(|plan| plan.name() == name);
+    }
+
+    async fn get_plans_by_activation_status(&self, is_active: bool) -> Result<Vec<SyncPlan>, RepositoryError> {
+        return Ok(self.find_plans(|plan| *plan.active() == is_active));
+    }
+
+    async fn get_plans_by_frequency(&self, sync_frequency: &str) -> Result<Vec<SyncPlan>, RepositoryError> {
+        let frequency = SyncFrequency::from_str(sync_frequency).map_err(|_| RepositoryError::ItemNotFound)?;
+        return Ok(self.find_plans(|plan| *plan.frequency() == frequency));
+    }
+
+    async fn get_plans_pass_due(&self) -> Result<Vec<SyncPlan>, RepositoryError> {
+        return Ok(self.find_plans(SyncPlan::should_trigger));
+    }
+
+    /// Plans ordered by name, pages are numbered from 1
+    async fn list_plans(&self, page_size: Option<usize>, page_number: Option<usize>) -> Result<Vec<SyncPlan>, RepositoryError> {
+        let mut plans = self.find_plans(|_| true);
+        plans.sort_by(|a, b| (a.name(), a.id()).cmp(&(b.name(), b.id())));
+        let Some(page_size) = page_size else {
+            return Ok(plans);
+        };
+        let skipped = page_number.unwrap_or(1).saturating_sub(1) * page_size;
+        return Ok(plans.into_iter().skip(skipped).take(page_size).collect());
+    }
+
+    async fn get_task_by_id(&self, id: &Uuid) -> Result<SyncTask, RepositoryError> {
+        return self.find_tasks(|task| task.id() == id).into_iter().next().ok_or(RepositoryError::ItemNotFound);
+    }
+
+    async fn get_tasks_by_plan_id(&self, plan_id: &Uuid) -> Result<Vec<SyncTask>, RepositoryError> {
+        return Ok(self.get_plan_by_id(plan_id).await?.tasks().clone());
+    }
+
+    async fn get_tasks_by_datasource_id(&self, datasource_ids: &[&Uuid]) -> Result<Vec<SyncTask>, RepositoryError> {
+        return Ok(self.find_tasks(|task| task.datasource_id().is_some_and(|id| datasource_ids.contains(&&id))));
+    }
+
+    async fn get_tasks_by_datasource_name(&self, datasource_name: &str) -> Result<Vec<SyncTask>, RepositoryError> {
+        return Ok(self.find_tasks(|task| task.datasource_name().as_deref() == Some(datasource_name)));
+    }
+
+    async fn get_tasks_by_dataset_id(&self, dataset_ids: &[&Uuid]) -> Result<Vec<SyncTask>, RepositoryError> {
+        return Ok(self.find_tasks(|task| task.dataset_id().is_some_and(|id| dataset_ids.contains(&&id))));
+    }
+
+    async fn get_tasks_by_dataset_name(&self, dataset_name: &str) -> Result<Vec<SyncTask>, RepositoryError> {
+        return Ok(self.find_tasks(|task| task.dataset_name().as_deref() == Some(dataset_name)));
+    }
+
+    async fn save_plan(&self, plan: &SyncPlan) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.save_plans(&[plan]).await;
+    }
+
+    async fn save_plans(&self, plans: &[&SyncPlan]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        let mut stored_plans = self.plans.lock().unwrap();
+        if plans.iter().any(|plan| stored_plans.contains_key(plan.id())) {
+            return Err(RepositoryError::DuplicateItem);
+        }
+        stored_plans.extend(plans.iter().map(|plan| (*plan.id(), (*plan).clone())));
+        return Ok(Box::new(self.clone()));
+    }
+
+    async fn add_tasks_to_plans(&self, tasks: &[&SyncTask], plan_id: Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.update_plan(&plan_id, |plan| {
+            plan.tasks_mut().extend(tasks.iter().map(|task| {
+                let mut task = (*task).clone();
+                task.set_sync_plan_id(Some(plan_id));
+                task
+            }));
+        });
+    }
+
+    async fn create_plans_for_datasource(&self, plans: &[&SyncPlan], datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        let plans: Vec<SyncPlan> = plans
+            .iter()
+            .map(|plan| {
+                let mut plan = (*plan).clone();
+                plan.set_datasource_id(Some(*datasource_id));
+                plan
+            })
+            .collect();
+        return self.save_plans(&plans.iter().collect::<Vec<_>>()).await;
+    }
+
+    async fn create_plan_for_dataset(&self, plan: &SyncPlan, dataset_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        let mut plan = plan.clone();
+        plan.set_dataset_id(Some(*dataset_id));
+        return self.save_plan(&plan).await;
+    }
+
+    /// Switch the plan between active and inactive
+    async fn update_plan_activation_status(&self, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.update_plan(plan_id, |plan| {
+            let active = !*plan.active();
+            plan.set_active(active);
+        });
+    }
+
+    async fn update_activation_status_for_datasource(&self, active: bool, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        let mut plans = self.plans.lock().unwrap();
+        plans
+            .values_mut()
+            .filter(|plan| *plan.datasource_id() == Some(*datasource_id))
+            .for_each(|plan| {
+                plan.set_active(active);
+            });
+        return Ok(Box::new(self.clone()));
+    }
+
+    async fn update_sync_frequency(&self, sync_frequency: &str, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        let frequency = SyncFrequency::from_str(sync_frequency).map_err(|_| RepositoryError::DataSerializationFailed)?;
+        return self.update_plan(plan_id, |plan| {
+            plan.set_frequency(frequency);
+        });
+    }
+
+    /// Replace the stored plans with the given ones, their tasks included
+    async fn update_plans(&self, plans: &[&SyncPlan]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        let mut stored_plans = self.plans.lock().unwrap();
+        if plans.iter().any(|plan| !stored_plans.contains_key(plan.id())) {
+            return Err(RepositoryError::ItemNotFound);
+        }
+        stored_plans.extend(plans.iter().map(|plan| (*plan.id(), (*plan).clone())));
+        return Ok(Box::new(self.clone()));
+    }
+
+    async fn delete_plan_by_id(&self, plan_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.delete_plans(&[*plan_id]).await;
+    }
+
+    async fn delete_plans(&self, plan_ids: &[Uuid]) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.delete_plans_where(|plan| plan_ids.contains(plan.id()));
+    }
+
+    async fn delete_plan_for_dataset(&self, dataset_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.delete_plans_where(|plan| *plan.dataset_id() == Some(*dataset_id));
+    }
+
+    async fn delete_plans_for_datasource(&self, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.delete_plans_where(|plan| *plan.datasource_id() == Some(*datasource_id));
+    }
+
+    async fn delete_deactivated_plans_for_datasource(&self, datasource_id: &Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.delete_plans_where(|plan| *plan.datasource_id() == Some(*datasource_id) && !*plan.active());
+    }
+
+    async fn delete_tasks_for_plan(&self, task_ids: &[&Uuid], plan_id: Uuid) -> Result<Box<dyn SyncPlanRepository>, RepositoryError> {
+        return self.update_plan(&plan_id, |plan| {
+            plan.tasks_mut().retain(|task| !task_ids.contains(&task.id()));
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_should_share_the_plans_and_their_tasks_between_clones() {
+        let mut plan = SyncPlan::default();
+        plan.set_id(Uuid::new_v4()).set_name("daily".to_string());
+        let plan_id = *plan.id();
+        let repository = InMemoryPlanRepository::default();
+
+        let shared = repository.save_plan(&plan).await.unwrap();
+        shared.add_tasks_to_plans(&[&SyncTask::default()], plan_id).await.unwrap();
+
+        let tasks = repository.get_tasks_by_plan_id(&plan_id).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(*tasks[0].sync_plan_id(), Some(plan_id));
+        assert_eq!(*repository.get_plan_by_name("daily").await.unwrap().id(), plan_id);
+        assert!(matches!(repository.save_plan(&plan).await, Err(RepositoryError::DuplicateItem)));
+    }
+}
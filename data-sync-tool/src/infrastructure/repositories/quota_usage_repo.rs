@@ -0,0 +1,64 @@
+//! Quota Usage Repository
+//! Persists the daily request counters of data sources and their keys in a JSON file
+
+use std::{collections::HashMap, path::Path};
+
+use async_trait::async_trait;
+use super::json_file_store::JsonFileStore;
+use crate::domain::synchronization::{
+    custom_errors::RepositoryError, rate_limiter::{DailyUsage, QuotaOwner}, repository::QuotaUsageRepository,
+};
+
+pub struct JsonQuotaUsageRepository {
+    // JSON keys are strings, so the usages are keyed by their owner's display
+    store: JsonFileStore<HashMap<String, DailyUsage>>,
+}
+
+impl JsonQuotaUsageRepository {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            store: JsonFileStore::new(path),
+        }
+    }
+}
+
+#[async_trait]
+impl QuotaUsageRepository for JsonQuotaUsageRepository {
+    async fn get_daily_usage(&self, owner: &QuotaOwner) -> Result<Option<DailyUsage>, RepositoryError> {
+        let usages = self.store.load().await?;
+        return Ok(usages.get(&owner.to_string()).cloned());
+    }
+
+    async fn save_daily_usage(&self, usage: &DailyUsage) -> Result<(), RepositoryError> {
+        self.store
+            .update(|usages| {
+                usages.insert(usage.owner().to_string(), usage.clone());
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_should_read_back_usage_after_restart() {
+        let path = std::env::temp_dir().join(format!("quota-usage-{}.json", Uuid::new_v4()));
+        let id = Uuid::new_v4();
+        let mut usage = DailyUsage::new(QuotaOwner::DataSource(id), NaiveDate::from_ymd_opt(2023, 4, 3).unwrap());
+        usage.set_used(42);
+
+        JsonQuotaUsageRepository::new(&path).save_daily_usage(&usage).await.unwrap();
+        let repository = JsonQuotaUsageRepository::new(&path);
+        let reloaded = repository.get_daily_usage(&QuotaOwner::DataSource(id)).await.unwrap();
+
+        assert_eq!(reloaded, Some(usage));
+        // a key sharing the id of a data source has a counter of its own
+        assert_eq!(repository.get_daily_usage(&QuotaOwner::Credential(id)).await.unwrap(), None);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+//! Trading Calendar Repository
+//! Persists the trading calendars of exchanges in a JSON file
+
+use std::{collections::HashMap, path::Path};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::fs;
+
+use super::json_file_store::JsonFileStore;
+use crate::domain::synchronization::{
+    custom_errors::RepositoryError, repository::TradingCalendarRepository, trading_calendar::TradingCalendar,
+};
+
+pub struct JsonTradingCalendarRepository {
+    store: JsonFileStore<HashMap<String, TradingCalendar>>,
+}
+
+impl JsonTradingCalendarRepository {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            store: JsonFileStore::new(path),
+        }
+    }
+
+    /// Import the exchange's days from a `trade_cal` export, such as the `trade_cal.json` of the prototype
+    pub async fn import(&self, exchange: &str, path: impl AsRef<Path>) -> Result<TradingCalendar, RepositoryError> {
+        let content = fs::read(path).await.map_err(|_| RepositoryError::ItemNotFound)?;
+        let rows: Value = serde_json::from_slice(&content).map_err(|_| RepositoryError::DataSerializationFailed)?;
+        let calendar =
+            TradingCalendar::from_trade_cal(exchange, &rows).map_err(|_| RepositoryError::DataSerializationFailed)?;
+        self.save_calendar(&calendar).await?;
+        return Ok(calendar);
+    }
+}
+
+#[async_trait]
+impl TradingCalendarRepository for JsonTradingCalendarRepository {
+    async fn get_calendar(&self, exchange: &str) -> Result<Option<TradingCalendar>, RepositoryError> {
+        let calendars = self.store.load().await?;
+        return Ok(calendars.get(exchange).cloned());
+    }
+
+    async fn save_calendar(&self, calendar: &TradingCalendar) -> Result<(), RepositoryError> {
+        self.store
+            .update(|calendars| {
+                calendars
+                    .entry(calendar.exchange().clone())
+                    .and_modify(|stored| {
+                        stored.merge(calendar);
+                    })
+                    .or_insert_with(|| calendar.clone());
+            })
+            .await
+    }
+}
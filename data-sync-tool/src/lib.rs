@@ -0,0 +1,9 @@
+// Explicit `return`s are the convention of this codebase
+#![allow(clippy::needless_return)]
+
+pub mod application;
+pub mod common;
+pub mod domain;
+pub mod infrastructure;
+// pub mod presentation;
+// pub mod services;